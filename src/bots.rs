@@ -1,13 +1,14 @@
 use crate::event_handler::EventHandler;
 use serenity::cache::Cache;
 use serenity::http::Http;
-use serenity::model::prelude::{ChannelId, GuildId, UserId, VoiceState};
+use serenity::model::prelude::{ChannelId, GuildId, Member, RoleId, UserId, VoiceState};
 use serenity::prelude::SerenityError;
 use serenity::{CacheAndHttp, Client};
 use songbird::{SerenityInit, Songbird, SongbirdKey};
 use std::ops::{Deref, Not};
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::broadcast::Receiver as BroadcastReceiver;
 use tokio::task::JoinHandle;
 
 pub struct BotMap {
@@ -37,6 +38,11 @@ impl BotMap {
         Ok(handle)
     }
 
+    ///Receiver for every User whose VoiceState changed in any Guild
+    pub fn voice_updates(&self) -> BroadcastReceiver<UserId> {
+        self.event_handler.subscribe_voice_updates()
+    }
+
     pub async fn get_user_voice_state(&self, user: &UserId, guild: &GuildId) -> Option<VoiceState> {
         for bot in &self.bots {
             if let Some(voice_states) = bot
@@ -121,6 +127,65 @@ impl BotMap {
         Some(false)
     }
 
+    ///Returns whether the user has the role in the guild
+    pub async fn user_has_role(&self, user: &UserId, guild: &GuildId, role: &RoleId) -> bool {
+        for bot in &self.bots {
+            if let Some(member) = bot.cache.member(guild, user).await {
+                return member.roles.contains(role);
+            }
+        }
+        false
+    }
+
+    ///Returns the amount of non-bot users in a channel
+    pub async fn listener_count(&self, channel: &ChannelId, guild: &GuildId) -> usize {
+        for bot in &self.bots {
+            if let Some((voice_states, members)) = bot
+                .cache
+                .guild_field(guild, |g| (g.voice_states.clone(), g.members.clone()))
+                .await
+            {
+                return voice_states
+                    .iter()
+                    .filter(|(_, state)| state.channel_id.eq(&Some(*channel)))
+                    .filter(|(user, _)| {
+                        members
+                            .get(user)
+                            .map(|member| !member.user.bot)
+                            .unwrap_or(false)
+                    })
+                    .count();
+            }
+        }
+        0
+    }
+
+    ///Returns the non-bot members in a channel, at most limit of them
+    pub async fn channel_members(
+        &self,
+        channel: &ChannelId,
+        guild: &GuildId,
+        limit: usize,
+    ) -> Vec<Member> {
+        for bot in &self.bots {
+            if let Some((voice_states, members)) = bot
+                .cache
+                .guild_field(guild, |g| (g.voice_states.clone(), g.members.clone()))
+                .await
+            {
+                return voice_states
+                    .iter()
+                    .filter(|(_, state)| state.channel_id.eq(&Some(*channel)))
+                    .filter_map(|(user, _)| members.get(user))
+                    .filter(|member| !member.user.bot)
+                    .take(limit)
+                    .cloned()
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
     pub fn contains_id(&self, bot: &UserId) -> bool {
         self.bots.iter().any(|b| b.id.eq(bot))
     }
@@ -144,6 +209,31 @@ impl BotMap {
         self.bots.iter().map(|b| b.id).collect()
     }
 
+    ///Ids of the Bots in the Guild
+    /// - A Bot counts, if its own Cache has the Guild or another Cache has it as Member
+    pub async fn guild_bot_ids(&self, guild: &GuildId) -> Vec<UserId> {
+        let mut ids = Vec::new();
+        for bot in &self.bots {
+            let members = bot
+                .cache
+                .guild_field(guild, |g| {
+                    self.bots
+                        .iter()
+                        .filter(|b| g.members.contains_key(&b.id))
+                        .map(|b| b.id)
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            if let Some(members) = members {
+                ids.push(bot.id);
+                ids.extend(members);
+            }
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
     pub async fn get_any_guild_bot(&self, guild: &GuildId) -> Option<Arc<Bot>> {
         for bot in &self.bots {
             if bot.cache.guild_field(guild, |_| ()).await.is_some() {
@@ -1,27 +1,311 @@
+use crate::player::Playback;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
+
+///Most Controls of a Main Message, Discord allows 20 Reactions per Message
+pub const CONTROLS_LIMIT: usize = 20;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub bots: HashMap<String, String>,
     pub guilds: HashMap<String, GuildConfig>,
     pub lavalink: LavalinkConfig,
+    ///Additional Lavalink Nodes, Players are spread over all Nodes
+    #[serde(default)]
+    pub lavalink_nodes: Vec<LavalinkConfig>,
     pub net: Option<NetConfig>,
+    ///Enables resolving Spotify Urls. Disabled if not set
+    #[serde(default)]
+    pub spotify: Option<SpotifyConfig>,
+    ///Enables looking up Lyrics. Disabled if not set
+    #[serde(default)]
+    pub lyrics: Option<LyricsConfig>,
+    ///Persists Queues across restarts. Disabled if not set
+    #[serde(default)]
+    pub persistence: Option<PersistenceConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PersistenceConfig {
+    ///Directory for the stored Queues, created if missing
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LyricsConfig {
+    ///Url queried with the title and artist parameters
+    pub endpoint: String,
+    ///Sent as Bearer Token, if set
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SpotifyConfig {
+    pub client_id: String,
+    pub client_secret: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct NetConfig {
-    pub address: Ipv4Addr,
+    ///IPv4 or IPv6 address, "::" binds dual-stack where the OS allows it
+    pub address: IpAddr,
     pub port: u16,
+    ///Path to a PEM certificate chain. Enables TLS together with `key`
+    #[serde(default)]
+    pub cert: Option<String>,
+    ///Path to a PEM private key (PKCS8 or RSA)
+    #[serde(default)]
+    pub key: Option<String>,
+    ///Seconds between WebSocket Pings
+    #[serde(default = "default_ping_interval")]
+    pub ping_interval: u64,
+    ///Seconds without a Pong, after which a connection is considered dead
+    #[serde(default = "default_ping_timeout")]
+    pub ping_timeout: u64,
+    ///Control Requests per Second per connection
+    #[serde(default = "default_control_rate")]
+    pub control_rate: f64,
+    ///Control Requests that can be sent in a burst
+    #[serde(default = "default_control_burst")]
+    pub control_burst: f64,
+    ///Search Requests per Second per connection
+    #[serde(default = "default_search_rate")]
+    pub search_rate: f64,
+    ///Search Requests that can be sent in a burst
+    #[serde(default = "default_search_burst")]
+    pub search_burst: f64,
+    ///Minimum Milliseconds between two Player State Patches per connection
+    #[serde(default = "default_state_interval")]
+    pub state_interval: u64,
+    ///Seconds to wait for companions to disconnect on shutdown
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout: u64,
+    ///Seconds a connection may stay unauthenticated before it gets closed
+    #[serde(default = "default_auth_timeout")]
+    pub auth_timeout: u64,
+    ///Most Companion connections at the same time
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+    ///Most authenticated connections of a single User
+    #[serde(default = "default_max_user_connections")]
+    pub max_user_connections: usize,
+    ///Origins allowed to connect, e.g. "https://example.com". Any Origin if not set
+    #[serde(default)]
+    pub allowed_origins: Option<Vec<String>>,
+    ///Peer addresses allowed to connect. Any address if not set
+    #[serde(default)]
+    pub allowed_ips: Option<Vec<IpAddr>>,
+    ///Discord User Ids allowed to list every active Player. Nobody if not set
+    #[serde(default)]
+    pub admins: Vec<u64>,
+    ///Deflates Binary Messages (zlib), Companions have to inflate every Binary Frame
+    /// - tungstenite can not negotiate permessage-deflate, so every Message is compressed on its own
+    /// - JSON Messages stay uncompressed Text
+    #[serde(default)]
+    pub compression: bool,
+}
+
+fn default_ping_interval() -> u64 {
+    15
+}
+
+fn default_ping_timeout() -> u64 {
+    45
+}
+
+fn default_control_rate() -> f64 {
+    5.0
+}
+
+fn default_control_burst() -> f64 {
+    10.0
+}
+
+fn default_search_rate() -> f64 {
+    0.5
+}
+
+fn default_search_burst() -> f64 {
+    2.0
+}
+
+fn default_state_interval() -> u64 {
+    250
+}
+
+fn default_drain_timeout() -> u64 {
+    5
+}
+
+fn default_auth_timeout() -> u64 {
+    30
+}
+
+fn default_max_connections() -> usize {
+    256
+}
+
+fn default_max_user_connections() -> usize {
+    4
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GuildConfig {
     pub guild_id: u64,
     pub channel_id: u64,
+    ///Fraction of listeners required to skip a Track. Disabled if not set
+    /// - Users with the DJ Role skip without a Vote
+    #[serde(default)]
+    pub vote_skip: Option<f64>,
+    ///Restricts controls to users with the DJ Role. Disabled if not set
+    #[serde(default)]
+    pub dj: Option<DjConfig>,
+    ///Use Buttons instead of Reactions for the Main Message
+    #[serde(default)]
+    pub buttons: bool,
+    ///Seconds between Main Message updates while a Track is playing
+    /// - Lower is smoother, but eats more of the Discord rate limit
+    /// - Player changes (play, pause, skip, ...) are always shown right away
+    #[serde(default = "default_update_interval")]
+    pub update_interval: u64,
+    ///Seconds a "Now playing" Message stays at most, it is gone with the Track anyway
+    /// - Disabled if not set
+    #[serde(default)]
+    pub now_playing: Option<u64>,
+    ///Skip enqueued Tracks that are already queued or playing
+    #[serde(default)]
+    pub no_duplicates: bool,
+    ///How often failed Lavalink calls are retried before the Action fails
+    #[serde(default = "default_lavalink_retries")]
+    pub lavalink_retries: u32,
+    ///Names of the Main Message Controls in order, e.g. PlayPause, Next, Nothing, Queue
+    /// - Must contain PlayPause. All Controls if not set
+    #[serde(default)]
+    pub controls: Option<Vec<Control>>,
+    ///Seconds a Search Message waits for a selection
+    #[serde(default = "default_search_timeout")]
+    pub search_timeout: u64,
+    ///Tracks per Search page, at most 10 with Reactions and 25 with Buttons
+    /// - The most Discord allows if not set
+    #[serde(default)]
+    pub search_results: Option<usize>,
+    ///Playback new Players start with, e.g. AllLoop. Normal if not set
+    #[serde(default)]
+    pub playback: Option<Playback>,
+    ///Volume new Players start with in percent, at most 1000
+    #[serde(default = "default_volume")]
+    pub volume: u16,
+    ///Longest Track in seconds, that can be enqueued. Unlimited if not set
+    #[serde(default)]
+    pub max_track_length: Option<u64>,
+    ///Whether Live Streams can be enqueued
+    #[serde(default = "default_allow_streams")]
+    pub allow_streams: bool,
+    ///Seconds a User has to wait between Enqueues, DJs are exempt. Disabled if not set
+    #[serde(default)]
+    pub enqueue_cooldown: Option<u64>,
+    ///Most finished Tracks kept in the History, independent of the Playlist size
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    ///Remove Reactions on the Main Message after handling them
+    /// - Needs the Manage Messages permission, without it Reactions stay in place anyway
+    #[serde(default = "default_remove_reactions")]
+    pub remove_reactions: bool,
+    ///Prefix of Text Commands like !skip or !play, in the Channel of the Guild
+    /// - Disabled if not set
+    #[serde(default)]
+    pub command_prefix: Option<String>,
+    ///Register Slash Commands like /play and /skip for the Guild
+    /// - Needs the applications.commands Scope for the Bot with the lowest Id
+    #[serde(default)]
+    pub slash_commands: bool,
+}
+
+impl GuildConfig {
+    ///Checks the configured Controls
+    /// - Returns Error for duplicate Controls, too many Controls or missing PlayPause
+    pub fn check_controls(&self) -> Result<(), String> {
+        let controls = match &self.controls {
+            None => return Ok(()),
+            Some(controls) => controls,
+        };
+        if let Some((i, c)) = controls
+            .iter()
+            .enumerate()
+            .find(|(i, c)| controls[..*i].contains(c))
+        {
+            return Err(format!("Duplicate Control: {:?}, position: {}", c, i));
+        }
+        if !controls.contains(&Control::PlayPause) {
+            return Err("Controls must contain PlayPause".to_string());
+        }
+        if controls.len() > CONTROLS_LIMIT {
+            return Err(format!("At most {} Controls are allowed", CONTROLS_LIMIT));
+        }
+        Ok(())
+    }
+}
+
+///Control of the Main Message, as named in the Config
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Control {
+    PlayPause,
+    Next,
+    Prev,
+    Join,
+    Leave,
+    Delete,
+    LoopOne,
+    LoopAll,
+    Queue,
+    BassBoost,
+    Lyrics,
+    Clear,
+    Shuffle,
+    Nothing,
+}
+
+fn default_update_interval() -> u64 {
+    1
+}
+
+fn default_lavalink_retries() -> u32 {
+    2
+}
+
+fn default_search_timeout() -> u64 {
+    60
+}
+
+fn default_volume() -> u16 {
+    100
+}
+
+fn default_allow_streams() -> bool {
+    true
+}
+
+fn default_history_limit() -> usize {
+    20
+}
+
+fn default_remove_reactions() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DjConfig {
+    pub role_id: u64,
+    ///Names of the restricted Controls, e.g. Next, Prev, Clear, Leave, BassBoost, Volume, Seek
+    /// - BassBoost restricts every Filter, including Speed and Pitch
+    /// - Companion Controls count as the closest one, e.g. Skip To as Next
+    /// - Playlists are enqueued like single Tracks, only limited by the Enqueue Cooldown
+    /// - PauseAll, pausing every Player of the Guild, is always restricted
+    pub restricted: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -35,11 +319,68 @@ impl Config {
         let error_message =
             |error: &dyn std::fmt::Display| format!("{}, file: {}", error.to_string(), file);
 
-        serde_yaml::from_reader(BufReader::new(
+        let config: Config = serde_yaml::from_reader(BufReader::new(
             File::open(&file).map_err(|e| error_message(&e))?,
         ))
-        .map_err(|e| error_message(&e))
+        .map_err(|e| error_message(&e))?;
+        for (name, guild) in config.guilds.iter() {
+            guild
+                .check_controls()
+                .map_err(|e| error_message(&format!("{}, guild: {}", e, name)))?;
+        }
+        Ok(config)
     }
 }
 
 //TODO add more stuff to the config
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    ///Guild Config with the required Ids and the given Yaml
+    pub fn guild_config(yaml: &str) -> GuildConfig {
+        serde_yaml::from_str(&format!("guild_id: 1\nchannel_id: 2\n{}", yaml))
+            .expect("Invalid Guild Config")
+    }
+
+    #[test]
+    fn ipv6_address_is_bindable() {
+        let net: NetConfig =
+            serde_yaml::from_str("address: \"::\"\nport: 1337").expect("Invalid Net Config");
+        let addr = std::net::SocketAddr::new(net.address, net.port);
+        assert!(addr.is_ipv6() && addr.ip().is_unspecified());
+        assert_eq!(addr.to_string(), "[::]:1337");
+        assert_eq!(addr, "[::]:1337".parse().unwrap());
+
+        let net: NetConfig =
+            serde_yaml::from_str("address: \"fe80::1\"\nport: 1337").expect("Invalid Net Config");
+        assert_eq!(
+            std::net::SocketAddr::new(net.address, net.port),
+            "[fe80::1]:1337".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn controls_are_checked() {
+        assert!(guild_config("").check_controls().is_ok());
+        assert!(guild_config("controls: [Prev, PlayPause, Next]")
+            .check_controls()
+            .is_ok());
+        assert!(guild_config("controls: [Prev, Next]")
+            .check_controls()
+            .is_err());
+        assert!(guild_config("controls: [PlayPause, Next, PlayPause]")
+            .check_controls()
+            .is_err());
+        assert!(
+            guild_config("controls: [PlayPause, Nothing, Queue, Nothing]")
+                .check_controls()
+                .is_err()
+        );
+        assert!(serde_yaml::from_str::<GuildConfig>(
+            "guild_id: 1\nchannel_id: 2\ncontrols: [PlayPause, Unknown]"
+        )
+        .is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::bots::BotMap;
-use crate::config::Config;
+use crate::config::{Config, GuildConfig};
 use crate::event_handler::EventHandler;
 use crate::guild::message_manager::{EmoteAction, MainMessage};
 use crate::guild::player_manager::PlayerManager;
@@ -7,18 +7,22 @@ use crate::guild::scheduler::GuildScheduler;
 use crate::lavalink_handler::LavalinkEvent;
 use lavalink_rs::LavalinkClient;
 use serenity::async_trait;
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+use serenity::model::interactions::message_component::MessageComponentInteraction;
 use serenity::model::prelude::{
     ChannelId, GuildId, Message, MessageId, ResumedEvent, UserId, VoiceState,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
+use tokio::sync::mpsc::Sender;
 use tokio::sync::{Mutex, RwLock};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 pub type MainMessageData = (MainMessage, JoinHandle<()>);
 pub type SearchMessageId = (Option<MessageId>, Uuid);
+pub type SearchInteractionSender = Sender<(UserId, Vec<String>)>;
 
 #[derive(Clone)]
 pub struct Context {
@@ -27,11 +31,17 @@ pub struct Context {
     pub bots: Arc<BotMap>,
     pub event_handler: EventHandler,
     pub config: Arc<Config>,
+    pub guild_config: GuildConfig,
     pub scheduler: GuildScheduler,
     pub player_manager: Arc<PlayerManager>,
     pub search_messages: Arc<RwLock<HashMap<UserId, SearchMessageId>>>,
+    pub search_interactions: Arc<RwLock<HashMap<MessageId, SearchInteractionSender>>>,
     pub main_message: Arc<RwLock<Option<MainMessageData>>>,
     pub delete_pool: Arc<Mutex<Vec<MessageId>>>,
+    ///Last announced Track per Bot
+    pub now_playing: Arc<Mutex<HashMap<UserId, String>>>,
+    ///User who asked to clear the Queue and when, waiting for confirmation
+    pub clear_request: Arc<Mutex<Option<(UserId, Instant)>>>,
 }
 
 #[async_trait]
@@ -68,6 +78,12 @@ pub trait GuildEventHandler: Send + Sync {
     /// When the Main Message might be missing
     async fn check_main_message(&self);
 
+    /// When a Message Component (Button) was used
+    async fn component_interaction(&self, interaction: MessageComponentInteraction);
+
+    /// When a Slash Command was used, received by the Bot
+    async fn command_interaction(&self, interaction: ApplicationCommandInteraction, bot: UserId);
+
     /// When the Cache for a Bot is ready
     async fn cache_ready(&self, bot: UserId);
 }
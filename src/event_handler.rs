@@ -4,6 +4,9 @@ use log::{debug, error, info};
 use serenity::async_trait;
 use serenity::client::bridge::gateway::ShardMessenger;
 use serenity::client::EventHandler as SerenityEventHandler;
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+use serenity::model::interactions::message_component::MessageComponentInteraction;
+use serenity::model::interactions::{Interaction, InteractionResponseType};
 use serenity::model::prelude::{
     ChannelId, GuildId, Message, MessageId, ResumedEvent, UserId, VoiceState,
 };
@@ -13,9 +16,11 @@ use std::ops::BitAnd;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::sync::broadcast::{Receiver as BroadcastReceiver, Sender as BroadcastSender};
 use tokio::sync::{Mutex, RwLock};
 
 pub const CACHE_SIZE: usize = 100;
+pub const VOICE_UPDATE_CHANNEL_SIZE: usize = 1024;
 pub const VOICE_STATE_MAX_DIFF: Duration = Duration::from_millis(500);
 
 type Cache = Arc<Mutex<ArrayDeque<[Event; CACHE_SIZE]>>>;
@@ -26,6 +31,7 @@ type GuildShardSenderMap = RwLock<HashMap<UserId, (u64, ShardMessenger)>>;
 pub struct EventHandler {
     shard_sender: Arc<RwLock<HashMap<GuildId, GuildShardSenderMap>>>,
     cache: Arc<RwLock<HashMap<GuildId, EventHandlerGuild>>>,
+    voice_updates: BroadcastSender<UserId>,
 }
 
 #[derive(Error, Debug)]
@@ -43,6 +49,9 @@ pub enum Event {
     VoiceUpdate(Option<VoiceState>, VoiceState, UserId, Instant),
     BulkReactionDelete(ChannelId, MessageId),
     CacheReady(UserId),
+    ComponentInteraction(MessageComponentInteraction),
+    ///Slash Command / Receiving Bot
+    CommandInteraction(ApplicationCommandInteraction, UserId),
 }
 
 impl Event {
@@ -89,9 +98,11 @@ impl PartialEq for Event {
 
 impl Default for EventHandler {
     fn default() -> Self {
+        let (voice_updates, _) = tokio::sync::broadcast::channel(VOICE_UPDATE_CHANNEL_SIZE);
         EventHandler {
             shard_sender: Arc::new(RwLock::new(HashMap::new())),
             cache: Arc::new(RwLock::new(HashMap::new())),
+            voice_updates,
         }
     }
 }
@@ -110,6 +121,11 @@ impl EventHandler {
             .insert(guild, RwLock::new(HashMap::new()));
     }
 
+    ///Subscribe to the Users whose VoiceState changed
+    pub fn subscribe_voice_updates(&self) -> BroadcastReceiver<UserId> {
+        self.voice_updates.subscribe()
+    }
+
     async fn process(&self, guild: GuildId, event: Event) -> Result<(), EventHandlerError> {
         let (event, handler) = match self.cache.read().await.get(&guild) {
             None => return Err(EventHandlerError::NoGuild(guild)),
@@ -137,6 +153,8 @@ impl EventHandler {
             Event::VoiceUpdate(ov, nv, bot, _) => handler.voice_update(ov, nv, bot).await,
             Event::BulkReactionDelete(ch, msg) => handler.bulk_reaction_delete(ch, msg).await,
             Event::CacheReady(bot) => handler.cache_ready(bot).await,
+            Event::ComponentInteraction(i) => handler.component_interaction(i).await,
+            Event::CommandInteraction(i, bot) => handler.command_interaction(i, bot).await,
         }
         Ok(())
     }
@@ -237,6 +255,38 @@ impl SerenityEventHandler for EventHandler {
         EventHandler::handle_result(self.process(guild_id, event).await);
     }
 
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let interaction = match interaction {
+            Interaction::MessageComponent(i) => i,
+            //Answered by the Guild, the Reply depends on the Command
+            Interaction::ApplicationCommand(i) => {
+                if let Some(guild_id) = i.guild_id {
+                    let event = Event::CommandInteraction(i, ctx.cache.current_user_id().await);
+                    EventHandler::handle_result(self.process(guild_id, event).await);
+                }
+                return;
+            }
+            _ => return,
+        };
+        let guild_id = match interaction.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        //Acknowledge right away, the result is visible through the message update
+        let ack = interaction
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await;
+        if let Err(e) = ack {
+            debug!("Error acknowledging Interaction. {:?}, {:?}", guild_id, e);
+        }
+
+        let event = Event::ComponentInteraction(interaction);
+        EventHandler::handle_result(self.process(guild_id, event).await);
+    }
+
     async fn resume(&self, _: Context, r: ResumedEvent) {
         //To every Guild
         let now = Instant::now();
@@ -254,6 +304,9 @@ impl SerenityEventHandler for EventHandler {
         old: Option<VoiceState>,
         new: VoiceState,
     ) {
+        //Notify subscribers, the cache is already updated at this point
+        self.voice_updates.send(new.user_id).ok();
+
         let guild_id = match guild_id {
             Some(guild_id) => guild_id,
             None => return,
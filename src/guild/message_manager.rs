@@ -1,15 +1,19 @@
 use crate::bots::Bot;
+use crate::config::{Control, GuildConfig};
 use crate::context::{Context, GuildEventHandler};
+use crate::guild::player_manager::{any_state_changed, Permission};
 use crate::guild::ReciprocityGuild;
-use crate::player::PlayState;
+use crate::player::{is_stream, PlayState, Playback, PlayerState};
 use crate::task_handle::{
-    AddMessageReactionTask, DeleteMessagePoolTask, DeleteMessageReactionTask, SendSearchMessage,
+    AddMessageReactionTask, DeleteMessagePoolTask, DeleteMessageReactionTask,
+    DeleteOwnMessageReactionTask, EditMessageTask, SendMessageTask, SendSearchMessage,
 };
-use futures::{Future, FutureExt};
-use lavalink_rs::model::Track;
+use futures::Future;
+use lavalink_rs::model::{Info, Track};
 use log::{debug, info, warn};
 use serenity::client::bridge::gateway::ShardMessenger;
 use serenity::collector::ReactionAction;
+use serenity::model::interactions::message_component::ButtonStyle;
 use serenity::model::prelude::{
     ChannelId, GuildId, Message, MessageId, Reaction, ReactionType, User, UserId,
 };
@@ -20,17 +24,37 @@ use std::convert::AsRef;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Write;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use strum_macros::AsRefStr;
 use thiserror::Error;
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 use uuid::Uuid;
 
 const DELETE_MESSAGE_DELAY: Duration = Duration::from_millis(500);
-const SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
 const SEARCH_TITLE_LIMIT: usize = 40;
-const MESSAGE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const SEARCH_PAGE_SIZE: usize = 10;
+const SEARCH_MAX_PAGES: usize = 5;
+const SEARCH_MENU_LIMIT: usize = 25;
+///How often the remaining time of a Search Message is updated
+const SEARCH_COUNTDOWN_UPDATES: u32 = 4;
+const MIN_MESSAGE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const PROGRESS_BAR_WIDTH: usize = 20;
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(60);
+const QUEUE_PAGE_SIZE: usize = 10;
+const LYRICS_TIMEOUT: Duration = Duration::from_secs(300);
+const NO_LYRICS_TIMEOUT: Duration = Duration::from_secs(10);
+const NOTICE_TIMEOUT: Duration = Duration::from_secs(10);
+///Discord allows 2000 characters, leave room for the code block
+const LYRICS_CHUNK_LIMIT: usize = 1900;
+const MESSAGE_LIMIT: usize = 2000;
+///An Emote Check runs once no Reaction changed for this long
+const EMOTE_CHECK_DEBOUNCE: Duration = Duration::from_secs(2);
+///Longest an Emote Check is put off by ongoing Reaction changes
+const EMOTE_CHECK_MAX_DELAY: Duration = Duration::from_secs(10);
+const CODE_BLOCK_END: &str = "```";
 
 ///Adds a list of emotes to a message
 async fn add_emotes(
@@ -62,120 +86,615 @@ async fn add_emotes(
     }
 }
 
-pub struct SearchMessage;
+///Waits for a selection until the deadline
+/// - Fails with Timeout, if the deadline passed or the selection was aborted
+async fn select_until<T>(
+    deadline: tokio::time::Instant,
+    selection: impl Future<Output = Option<T>>,
+) -> Result<T, MessageError> {
+    tokio::time::timeout_at(deadline, selection)
+        .await
+        .ok()
+        .flatten()
+        .ok_or(MessageError::Timeout())
+}
 
-impl SearchMessage {
-    pub async fn search(
-        tracks: Vec<Track>,
-        requester: User,
-        query: String,
-        shard_messenger: impl AsRef<ShardMessenger>,
+///Sends a Message bound to a User, replacing their previous one
+/// - Returns Deleted if the Message became irrelevant in the meantime
+async fn send_user_message(
+    context: &Context,
+    user: UserId,
+    uuid: Uuid,
+    text: String,
+    menu: Option<Vec<String>>,
+) -> Result<Message, MessageError> {
+    //Replace old Message
+    let mut messages_lock = context.search_messages.write().await;
+    if let Some((Some(old_msg), _)) = messages_lock.insert(user, (None, uuid)) {
+        context.delete_pool.lock().await.push(old_msg);
+        let task = DeleteMessagePoolTask {
+            channel: context.channel,
+            pool: context.delete_pool.clone(),
+        };
+        drop(messages_lock);
+        context.scheduler.process_enqueue(task).await.ok();
+    } else {
+        drop(messages_lock);
+    }
+
+    //Attempt sending message
+    let (send, mut rec_msg) = tokio::sync::watch::channel(None);
+    context
+        .scheduler
+        .process_enqueue(SendSearchMessage {
+            channel: context.channel,
+            text,
+            menu,
+            uuid,
+            search_messages: context.search_messages.clone(),
+            callback: send,
+        })
+        .await
+        .ok();
+    let message = if rec_msg.changed().await.is_ok() {
+        if let Some(msg) = rec_msg.borrow().deref() {
+            msg.clone()
+        } else {
+            info!("Message became irrelevant: {:?}, {:?}", context.id, uuid);
+            return Err(MessageError::Deleted());
+        }
+    } else {
+        info!("Message Sender Ended: {:?}, {:?}", context.id, uuid);
+        return Err(MessageError::Deleted());
+    };
+
+    //Insert new message Id, or delete message if uuid changed inside map
+    let mut lock = context.search_messages.write().await;
+    if lock.get(&user).map(|(_, id)| uuid.eq(id)).unwrap_or(false) {
+        let (msg, _) = lock.get_mut(&user).unwrap();
+        *msg = Some(message.id);
+        drop(lock);
+        Ok(message)
+    } else {
+        drop(lock);
+
+        info!("Message became irrelevant: {:?}, {:?}", context.id, uuid);
+        //Delete this Message
+        context.delete_pool.lock().await.push(message.id);
+        let task = DeleteMessagePoolTask {
+            channel: context.channel,
+            pool: context.delete_pool.clone(),
+        };
+        context.scheduler.process_enqueue(task).await.ok();
+
+        Err(MessageError::Deleted())
+    }
+}
+
+///Deletes a Message bound to a User and frees their slot, if it is still theirs
+async fn remove_user_message(context: &Context, user: UserId, uuid: Uuid, message: MessageId) {
+    context.delete_pool.lock().await.push(message);
+    context
+        .scheduler
+        .process_enqueue(DeleteMessagePoolTask {
+            channel: context.channel,
+            pool: context.delete_pool.clone(),
+        })
+        .await
+        .ok();
+
+    //Remove message id if message is still in map
+    let mut messages_lock = context.search_messages.write().await;
+    if let Some((_, id)) = messages_lock.get(&user) {
+        if uuid.eq(id) {
+            messages_lock.remove(&user);
+        }
+    }
+    drop(messages_lock);
+}
+
+pub struct QueueMessage;
+
+impl QueueMessage {
+    ///Posts the whole Queue of the Player in the Voice Channel
+    /// - Pages are rebuilt from the current Player State on every page change
+    /// - Shares the per User Message slot with Search Messages
+    pub async fn show(
+        voice_channel: ChannelId,
+        requester: UserId,
         context: Context,
-    ) -> Result<Track, MessageError> {
+    ) -> Result<(), MessageError> {
         let uuid = Uuid::new_v4();
         info!(
-            "New Search Message. {:?}, {:?}, {:?}, Query: {:?}",
-            context.id, requester.id, uuid, query
+            "New Queue Message. {:?}, {:?}, {:?}, {:?}",
+            context.id, requester, voice_channel, uuid
         );
+        let bot = context
+            .bots
+            .get_any_guild_bot(&context.id)
+            .await
+            .ok_or(MessageError::NoBot(context.id))?;
+        let shard = context
+            .event_handler
+            .get_shard_sender(context.id, bot.id())
+            .await
+            .ok_or_else(|| MessageError::NoShard(bot.id()))?;
 
-        //Replace old Search Message
-        let mut messages_lock = context.search_messages.write().await;
-        if let Some((Some(old_msg), _)) = messages_lock.insert(requester.id, (None, uuid)) {
-            context.delete_pool.lock().await.push(old_msg);
-            let task = DeleteMessagePoolTask {
-                channel: context.channel,
-                pool: context.delete_pool.clone(),
+        let (text, pages) = Self::content(&context, &voice_channel, 0).await;
+        let message = send_user_message(&context, requester, uuid, text, None).await?;
+
+        let mut emotes = Vec::new();
+        if pages > 1 {
+            emotes.push(EmoteAction::PagePrev());
+            emotes.push(EmoteAction::PageNext());
+        }
+        emotes.push(EmoteAction::Delete());
+        let emotes = Arc::new(emotes);
+        let emotes_1 = emotes.clone();
+
+        let filter =
+            move |r: &Arc<Reaction>| emotes.iter().any(|e| r.emoji.unicode_eq(e.unicode()));
+        let mut collector = message
+            .await_reactions(&shard)
+            .timeout(QUEUE_TIMEOUT)
+            .author_id(requester.0)
+            .removed(false)
+            .added(true)
+            .filter(filter)
+            .await;
+        tokio::spawn(add_emotes(
+            context.clone(),
+            message.id,
+            uuid,
+            requester,
+            emotes_1,
+        ));
+
+        let mut page = 0;
+        while let Some(reaction) = collector.next().await {
+            let reaction = reaction.as_inner_ref();
+            page = match EmoteAction::try_from(reaction.deref()) {
+                Ok(EmoteAction::PageNext()) => page + 1,
+                Ok(EmoteAction::PagePrev()) => page.saturating_sub(1),
+                Ok(EmoteAction::Delete()) => break,
+                _ => continue,
             };
-            drop(messages_lock);
-            context.scheduler.process_enqueue(task).await.ok();
-        } else {
-            drop(messages_lock);
+
+            //Remove reaction, so the page can be flipped again
+            context
+                .scheduler
+                .process_enqueue(DeleteMessageReactionTask {
+                    channel: reaction.channel_id,
+                    message: reaction.message_id,
+                    user: requester,
+                    reaction: reaction.emoji.clone(),
+                    forbidden: None,
+                })
+                .await
+                .ok();
+
+            //The Queue might have shrunk since the last page change
+            let (text, pages) = Self::content(&context, &voice_channel, page).await;
+            page = page.min(pages - 1);
+            debug!(
+                "Queue Message Page changed. {:?}, {:?}, Page: {}",
+                context.id, uuid, page
+            );
+            context
+                .scheduler
+                .process_enqueue(EditMessageTask {
+                    channel: message.channel_id,
+                    message: message.id,
+                    text,
+                })
+                .await
+                .ok();
+        }
+
+        remove_user_message(&context, requester, uuid, message.id).await;
+        Ok(())
+    }
+
+    ///Builds the content for a page of the Queue
+    /// - The page is clamped to the available pages, which are returned as well
+    pub async fn content(
+        context: &Context,
+        voice_channel: &ChannelId,
+        page: usize,
+    ) -> (String, usize) {
+        let playlist: Vec<Track> = match context.player_manager.get_player(voice_channel).await {
+            Some((bot, _)) => context
+                .player_manager
+                .get_all_player_states()
+                .await
+                .iter()
+                .map(|s| s.borrow().clone())
+                .find(|s| s.bot.eq(&bot))
+                .map(|s| s.playlist.iter().cloned().collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let mut content = "[Queue]\r\n".to_string();
+        if playlist.is_empty() {
+            write!(content, "The Queue is empty, add some Songs!\r\n").unwrap();
+            let content = MessageBuilder::new()
+                .push_codeblock(content, Some("cs"))
+                .build();
+            return (content, 1);
+        }
+
+        let pages = (playlist.len() + QUEUE_PAGE_SIZE - 1) / QUEUE_PAGE_SIZE;
+        let page = page.min(pages - 1);
+        for (i, track) in playlist
+            .iter()
+            .enumerate()
+            .skip(page * QUEUE_PAGE_SIZE)
+            .take(QUEUE_PAGE_SIZE)
+        {
+            write!(
+                content,
+                "[{}] {:.*}\r\n",
+                i + 1,
+                SEARCH_TITLE_LIMIT,
+                track
+                    .info
+                    .clone()
+                    .map_or("No Track Name".to_string(), |i| i.title)
+            )
+            .unwrap();
+        }
+        write!(content, "Page {}/{}\r\n", page + 1, pages).unwrap();
+
+        let content = MessageBuilder::new()
+            .push_codeblock(content, Some("cs"))
+            .build();
+        (content, pages)
+    }
+}
+
+pub struct NowPlayingMessage;
+
+impl NowPlayingMessage {
+    ///Posts a "Now playing" Message for a started Track
+    /// - Deleted once the Player moves on, or after the configured timeout
+    /// - Loop One replays of the announced Track are ignored
+    pub async fn announce(track: String, context: Context) {
+        let timeout = match context.guild_config.now_playing {
+            None => return,
+            Some(secs) => Duration::from_secs(secs),
+        };
+
+        //Find the Player that started the Track
+        let mut state_rec = match context
+            .player_manager
+            .get_all_player_states()
+            .await
+            .into_iter()
+            .find(|s| Self::is_current(&s.borrow(), &track))
+        {
+            None => return,
+            Some(state_rec) => state_rec,
+        };
+        let (bot, title, playback) = {
+            let state = state_rec.borrow();
+            let title = state
+                .current
+                .as_ref()
+                .and_then(|(_, t)| t.info.clone())
+                .map_or("No Track Name".to_string(), |i| i.title);
+            (state.bot, title, state.playback)
+        };
+
+        let previous = context.now_playing.lock().await.insert(bot, track.clone());
+        if playback == Playback::OneLoop && previous.eq(&Some(track.clone())) {
+            return;
         }
+        debug!("Announcing Track. {:?}, {:?}, {:?}", context.id, bot, title);
 
-        //Attempt sending search message
         let (send, mut rec_msg) = tokio::sync::watch::channel(None);
         context
             .scheduler
-            .process_enqueue(SendSearchMessage {
+            .process_enqueue(SendMessageTask {
                 channel: context.channel,
-                text: Self::content(tracks.as_slice(), &query, &requester),
-                uuid,
-                search_messages: context.search_messages.clone(),
+                text: MessageBuilder::new()
+                    .push("Now playing: ")
+                    .push_bold_safe(title)
+                    .build(),
+                callback: send,
+            })
+            .await
+            .ok();
+        let message = match rec_msg.changed().await {
+            Ok(_) => match rec_msg.borrow().deref() {
+                None => return,
+                Some(msg) => msg.id,
+            },
+            Err(_) => return,
+        };
+
+        //Wait until the Track is no longer current
+        let deadline = tokio::time::Instant::now() + timeout;
+        while let Ok(Ok(_)) = tokio::time::timeout_at(deadline, state_rec.changed()).await {
+            if !Self::is_current(&state_rec.borrow(), &track) {
+                break;
+            }
+        }
+
+        context.delete_pool.lock().await.push(message);
+        context
+            .scheduler
+            .process_enqueue(DeleteMessagePoolTask {
+                channel: context.channel,
+                pool: context.delete_pool.clone(),
+            })
+            .await
+            .ok();
+    }
+
+    fn is_current(state: &PlayerState, track: &str) -> bool {
+        state
+            .current
+            .as_ref()
+            .map(|(_, t)| t.track.eq(track))
+            .unwrap_or(false)
+    }
+}
+
+///Posts every text as a code block and deletes them all after the timeout
+async fn post_temporary(texts: Vec<String>, timeout: Duration, context: &Context) {
+    let mut messages = Vec::new();
+    for text in texts {
+        let (send, mut rec_msg) = tokio::sync::watch::channel(None);
+        context
+            .scheduler
+            .process_enqueue(SendMessageTask {
+                channel: context.channel,
+                text: MessageBuilder::new().push_codeblock(text, None).build(),
                 callback: send,
             })
             .await
             .ok();
-        let message = if rec_msg.changed().await.is_ok() {
+        if rec_msg.changed().await.is_ok() {
             if let Some(msg) = rec_msg.borrow().deref() {
-                msg.clone()
-            } else {
+                messages.push(msg.id);
+            }
+        }
+    }
+
+    tokio::time::sleep(timeout).await;
+    context.delete_pool.lock().await.append(&mut messages);
+    context
+        .scheduler
+        .process_enqueue(DeleteMessagePoolTask {
+            channel: context.channel,
+            pool: context.delete_pool.clone(),
+        })
+        .await
+        .ok();
+}
+
+pub struct NoticeMessage;
+
+impl NoticeMessage {
+    ///Posts a short notice for Users, which is deleted after a timeout
+    pub async fn show(text: String, context: Context) {
+        post_temporary(vec![text], NOTICE_TIMEOUT, &context).await;
+    }
+}
+
+pub struct LyricsMessage;
+
+impl LyricsMessage {
+    ///Posts the Lyrics of the current Track, split over multiple Messages if necessary
+    /// - Posts a short notice instead, if no Lyrics are available
+    /// - Every Message is deleted after a timeout
+    pub async fn show(voice_channel: ChannelId, context: Context) {
+        let (text, timeout) = match context.player_manager.lyrics(voice_channel).await {
+            Ok((title, lyrics)) => (format!("[{}]\r\n{}", title, lyrics), LYRICS_TIMEOUT),
+            Err(e) => {
                 info!(
-                    "Search Message became irrelevant: {:?}, {:?}",
-                    context.id, uuid
+                    "No Lyrics available. {:?}, {:?}, {:?}",
+                    context.id, voice_channel, e
                 );
-                return Err(MessageError::Deleted());
+                ("No Lyrics available".to_string(), NO_LYRICS_TIMEOUT)
+            }
+        };
+
+        post_temporary(Self::chunks(&text), timeout, &context).await;
+    }
+
+    ///Splits the text at line breaks into chunks below the Message limit
+    /// - Lines exceeding the limit on their own are split at character boundaries
+    fn chunks(text: &str) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut chunk = String::new();
+        for line in text.lines() {
+            if chunk.len() + line.len() + 1 > LYRICS_CHUNK_LIMIT && !chunk.is_empty() {
+                chunks.push(std::mem::take(&mut chunk));
             }
+            for c in line.chars() {
+                if chunk.len() + c.len_utf8() > LYRICS_CHUNK_LIMIT {
+                    chunks.push(std::mem::take(&mut chunk));
+                }
+                chunk.push(c);
+            }
+            chunk.push('\n');
+        }
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+}
+
+pub struct SearchMessage;
+
+impl SearchMessage {
+    pub async fn search(
+        mut tracks: Vec<Track>,
+        requester: User,
+        query: String,
+        shard_messenger: impl AsRef<ShardMessenger>,
+        context: Context,
+    ) -> Result<Track, MessageError> {
+        let uuid = Uuid::new_v4();
+        let timeout = Duration::from_secs(context.guild_config.search_timeout);
+        let deadline = Self::deadline(&context.guild_config);
+        //Reactions only have Number emotes up to 10, Select Menus allow 25 options
+        let limit = if context.guild_config.buttons {
+            SEARCH_MENU_LIMIT
         } else {
-            info!("Search Message Sender Ended: {:?}, {:?}", context.id, uuid);
-            return Err(MessageError::Deleted());
+            SEARCH_PAGE_SIZE
         };
+        let page_size = context
+            .guild_config
+            .search_results
+            .unwrap_or(limit)
+            .max(1)
+            .min(limit);
+        //The Select Menu only shows the first page, Reactions can flip through a few pages
+        // - Capped, so browsing does not eat up the rate limit
+        if context.guild_config.buttons {
+            tracks.truncate(page_size);
+        } else {
+            tracks.truncate(page_size * SEARCH_MAX_PAGES);
+        }
+        info!(
+            "New Search Message. {:?}, {:?}, {:?}, Query: {:?}",
+            context.id, requester.id, uuid, query
+        );
 
-        //Insert new message Id, or delete message if uuid changed inside map
-        let mut lock = context.search_messages.write().await;
-        if lock
-            .get(&requester.id)
-            .map(|(_, id)| uuid.eq(id))
-            .unwrap_or(false)
-        {
-            let (msg, _) = lock.get_mut(&requester.id).unwrap();
-            *msg = Some(message.id);
-            drop(lock);
+        let message = send_user_message(
+            &context,
+            requester.id,
+            uuid,
+            Self::content(tracks.as_slice(), 0, page_size, &query, &requester, timeout),
+            if context.guild_config.buttons {
+                Some(Self::menu_labels(tracks.as_slice(), page_size))
+            } else {
+                None
+            },
+        )
+        .await?;
+
+        let track = if context.guild_config.buttons {
+            Self::await_menu_selection(
+                &context, &message, &requester, tracks, &query, page_size, deadline,
+            )
+            .await
         } else {
-            drop(lock);
+            Self::await_reaction_selection(
+                &context,
+                &message,
+                &requester,
+                uuid,
+                tracks,
+                &query,
+                shard_messenger,
+                page_size,
+                timeout,
+                deadline,
+            )
+            .await
+        };
 
-            info!(
-                "Search Message became irrelevant: {:?}, {:?}",
-                context.id, uuid
-            );
-            //Delete this Message
-            context.delete_pool.lock().await.push(message.id);
-            let task = DeleteMessagePoolTask {
-                channel: context.channel,
-                pool: context.delete_pool.clone(),
-            };
-            context.scheduler.process_enqueue(task).await.ok();
+        remove_user_message(&context, requester.id, uuid, message.id).await;
+        track
+    }
 
-            return Err(MessageError::Deleted());
-        }
+    ///Waits for the requester to choose a Track from the Select Menu
+    async fn await_menu_selection(
+        context: &Context,
+        message: &Message,
+        requester: &User,
+        tracks: Vec<Track>,
+        query: &str,
+        page_size: usize,
+        deadline: tokio::time::Instant,
+    ) -> Result<Track, MessageError> {
+        let (send, mut rec) = tokio::sync::mpsc::channel(10);
+        context
+            .search_interactions
+            .write()
+            .await
+            .insert(message.id, send);
+
+        let mut countdown = Self::countdown(deadline);
+        let selection = select_until(deadline, async {
+            loop {
+                let (user, values) = tokio::select! {
+                    selection = rec.recv() => match selection {
+                        Some(selection) => selection,
+                        None => return None,
+                    },
+                    _ = countdown.tick() => {
+                        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                        let text = Self::content(&tracks, 0, page_size, query, requester, remaining);
+                        Self::edit(context, message, text).await;
+                        continue;
+                    }
+                };
+                if !user.eq(&requester.id) {
+                    continue;
+                }
+                let track = values
+                    .first()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .and_then(|i| tracks.get(i));
+                if let Some(track) = track {
+                    return Some(track.clone());
+                }
+            }
+        })
+        .await;
+        context
+            .search_interactions
+            .write()
+            .await
+            .remove(&message.id);
+        selection
+    }
+
+    ///Waits for the requester to choose a Track by reacting with a number
+    /// - Number emotes are bound to the Tracks of the current page
+    async fn await_reaction_selection(
+        context: &Context,
+        message: &Message,
+        requester: &User,
+        uuid: Uuid,
+        tracks: Vec<Track>,
+        query: &str,
+        shard_messenger: impl AsRef<ShardMessenger>,
+        page_size: usize,
+        timeout: Duration,
+        deadline: tokio::time::Instant,
+    ) -> Result<Track, MessageError> {
+        let pages = (tracks.len() + page_size - 1) / page_size;
 
         //Build emotes, that we are using with this message
-        let emotes: Arc<Vec<_>> = Arc::new(
-            (1..(tracks.len() + 1))
-                .take(10)
-                .map(EmoteAction::Number)
-                .chain(vec![EmoteAction::Delete()])
-                .collect(),
-        );
+        let mut emotes: Vec<_> = (1..(tracks.len() + 1))
+            .take(page_size)
+            .map(EmoteAction::Number)
+            .collect();
+        if pages > 1 {
+            emotes.push(EmoteAction::PagePrev());
+            emotes.push(EmoteAction::PageNext());
+        }
+        emotes.push(EmoteAction::Delete());
+        let emotes = Arc::new(emotes);
         let emotes_1 = emotes.clone();
 
         let filter =
             move |r: &Arc<Reaction>| emotes.iter().any(|e| r.emoji.unicode_eq(e.unicode()));
-        let collector = message
-            .await_reaction(&shard_messenger)
-            .timeout(SEARCH_TIMEOUT)
+        let mut collector = message
+            .await_reactions(&shard_messenger)
+            .timeout(timeout)
             .author_id(requester.id.0)
             .removed(false)
             .added(true)
             .filter(filter)
-            //Map into EmoteAction
-            .map(|r| EmoteAction::try_from(r?.as_inner_ref().deref()).ok())
-            //Map into Track
-            .map(move |e| {
-                if let Some(EmoteAction::Number(i)) = e {
-                    return tracks.get(i - 1).cloned();
-                }
-                None
-            });
+            .await;
         tokio::spawn(add_emotes(
             context.clone(),
             message.id,
@@ -183,33 +702,122 @@ impl SearchMessage {
             requester.id,
             emotes_1.clone(),
         ));
-        let track: Result<Track, MessageError> = collector.await.ok_or(MessageError::Timeout());
 
-        context.delete_pool.lock().await.push(message.id);
+        let mut page = 0;
+        let mut countdown = Self::countdown(deadline);
+        select_until(deadline, async {
+            loop {
+                let reaction = tokio::select! {
+                    reaction = collector.next() => match reaction {
+                        Some(reaction) => reaction,
+                        None => break,
+                    },
+                    _ = countdown.tick() => {
+                        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                        let text = Self::content(&tracks, page, page_size, query, requester, remaining);
+                        Self::edit(context, message, text).await;
+                        continue;
+                    }
+                };
+                let reaction = reaction.as_inner_ref();
+                let new_page = match EmoteAction::try_from(reaction.deref()) {
+                    Ok(EmoteAction::Number(i)) => {
+                        if let Some(track) = tracks.get(page * page_size + i - 1) {
+                            return Some(track.clone());
+                        }
+                        continue;
+                    }
+                    Ok(EmoteAction::PageNext()) if page + 1 < pages => page + 1,
+                    Ok(EmoteAction::PagePrev()) if page > 0 => page - 1,
+                    Ok(EmoteAction::Delete()) => break,
+                    _ => page,
+                };
+
+                //Remove reaction, so the page can be flipped again
+                context
+                    .scheduler
+                    .process_enqueue(DeleteMessageReactionTask {
+                        channel: reaction.channel_id,
+                        message: reaction.message_id,
+                        user: requester.id,
+                        reaction: reaction.emoji.clone(),
+                        forbidden: None,
+                    })
+                    .await
+                    .ok();
+                if new_page == page {
+                    continue;
+                }
+                page = new_page;
+
+                debug!(
+                    "Search Message Page changed. {:?}, {:?}, Page: {}",
+                    context.id, uuid, page
+                );
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                let text = Self::content(&tracks, page, page_size, query, requester, remaining);
+                Self::edit(context, message, text).await;
+            }
+            None
+        })
+        .await
+    }
+
+    ///When a Search Message of the Guild stops waiting for a selection
+    fn deadline(config: &GuildConfig) -> tokio::time::Instant {
+        tokio::time::Instant::now() + Duration::from_secs(config.search_timeout)
+    }
+
+    ///Ticks a few times until the deadline, for updating the remaining time
+    fn countdown(deadline: tokio::time::Instant) -> tokio::time::Interval {
+        let now = tokio::time::Instant::now();
+        let step = (deadline.saturating_duration_since(now) / SEARCH_COUNTDOWN_UPDATES)
+            .max(Duration::from_secs(1));
+        tokio::time::interval_at(now + step, step)
+    }
+
+    async fn edit(context: &Context, message: &Message, text: String) {
         context
             .scheduler
-            .process_enqueue(DeleteMessagePoolTask {
-                channel: context.channel,
-                pool: context.delete_pool.clone(),
+            .process_enqueue(EditMessageTask {
+                channel: message.channel_id,
+                message: message.id,
+                text,
             })
             .await
             .ok();
+    }
 
-        //Remove message id if message is still in map
-        let mut messages_lock = context.search_messages.write().await;
-        if let Some((_, id)) = messages_lock.get(&requester.id) {
-            if uuid.eq(id) {
-                messages_lock.remove(&requester.id);
-            }
-        }
-        drop(messages_lock);
-
-        track
+    fn menu_labels(tracks: &[Track], page_size: usize) -> Vec<String> {
+        tracks
+            .iter()
+            .take(page_size)
+            .enumerate()
+            .map(|(i, track)| {
+                format!(
+                    "{}: {:.*}",
+                    i + 1,
+                    SEARCH_TITLE_LIMIT,
+                    track
+                        .clone()
+                        .info
+                        .map_or("Missing Name".to_string(), |info| info.title)
+                )
+            })
+            .collect()
     }
 
-    fn content(tracks: &[Track], query: &str, requester: &User) -> String {
+    fn content(
+        tracks: &[Track],
+        page: usize,
+        page_size: usize,
+        query: &str,
+        requester: &User,
+        remaining: Duration,
+    ) -> String {
         let mut content = format!("[{:.*}] @{}\r\n", SEARCH_TITLE_LIMIT, query, requester.name);
-        for (i, track) in tracks.iter().enumerate().take(10) {
+        let page_tracks = tracks.iter().skip(page * page_size).take(page_size);
+        for (i, track) in page_tracks.enumerate() {
             write!(
                 content,
                 "{}: {:.*}\r\n",
@@ -222,6 +830,11 @@ impl SearchMessage {
             )
             .unwrap()
         }
+        if tracks.len() > page_size {
+            let pages = (tracks.len() + page_size - 1) / page_size;
+            write!(content, "Page {}/{}\r\n", page + 1, pages).unwrap();
+        }
+        write!(content, "Time left: {}s\r\n", remaining.as_secs()).unwrap();
 
         let content = MessageBuilder::new()
             .push_codeblock(content, Some("cs"))
@@ -233,6 +846,14 @@ impl SearchMessage {
 #[derive(Clone)]
 pub struct MainMessage {
     lock: Arc<Mutex<()>>,
+    ///Configured Controls in order
+    emotes: Arc<Vec<EmoteAction>>,
+    ///Set while an Emote Check is scheduled
+    check_pending: Arc<AtomicBool>,
+    ///When an Emote Check was last requested
+    check_requested: Arc<std::sync::Mutex<Instant>>,
+    ///Set once removing Reactions failed for missing permissions
+    reactions_forbidden: Arc<AtomicBool>,
     message: Message,
     bot: Arc<Bot>,
     shard: ShardMessenger,
@@ -240,21 +861,40 @@ pub struct MainMessage {
 }
 
 impl MainMessage {
-    const EMOTES: [EmoteAction; 7] = [
+    ///Default Reactions of the Main Message in order
+    /// - Nothing separates the Playback Controls from the Channel and Queue Controls,
+    ///   it never triggers an Action and is left out for Buttons
+    const EMOTES: [EmoteAction; 13] = [
         EmoteAction::Prev(),
         EmoteAction::PlayPause(),
         EmoteAction::Next(),
         EmoteAction::LoopOne(),
         EmoteAction::LoopAll(),
+        EmoteAction::Shuffle(),
+        EmoteAction::BassBoost(),
+        EmoteAction::Nothing(),
         EmoteAction::Join(),
         EmoteAction::Leave(),
+        EmoteAction::Queue(),
+        EmoteAction::Lyrics(),
+        EmoteAction::Clear(),
     ];
 
+    ///Configured Controls of the Guild, or the default ones
+    /// - Already checked with the Config
+    pub fn controls(config: &GuildConfig) -> Vec<EmoteAction> {
+        match &config.controls {
+            None => Self::EMOTES.to_vec(),
+            Some(controls) => controls.iter().copied().map(EmoteAction::from).collect(),
+        }
+    }
+
     pub async fn new(
         guild: ReciprocityGuild,
         context: Context,
     ) -> Result<(Self, impl Future<Output = ()>), MessageError> {
         info!("Start new Main Message. {:?}", context.id);
+        let emotes = Self::controls(&context.guild_config);
         let bot = context
             .bots
             .get_any_guild_bot(&context.id)
@@ -267,13 +907,39 @@ impl MainMessage {
             .ok_or_else(|| MessageError::NoShard(bot.id()))?;
 
         let content = Self::content(&context).await;
+        let buttons = context.guild_config.buttons;
         let message = context
             .channel
-            .send_message(bot.http(), |m| m.content(content))
+            .send_message(bot.http(), |m| {
+                m.content(content);
+                if buttons {
+                    let controls: Vec<_> = emotes.iter().filter(|e| !e.is_nothing()).collect();
+                    m.components(|c| {
+                        for row in controls.chunks(5) {
+                            c.create_action_row(|r| {
+                                for e in row {
+                                    r.create_button(|b| {
+                                        b.style(ButtonStyle::Secondary)
+                                            .emoji(e.reaction())
+                                            .custom_id(e.unicode())
+                                    });
+                                }
+                                r
+                            });
+                        }
+                        c
+                    });
+                }
+                m
+            })
             .await
             .map_err(MessageError::SerenityError)?;
         let main_message = MainMessage {
             lock: Arc::new(Mutex::new(())),
+            emotes: Arc::new(emotes),
+            check_pending: Arc::new(AtomicBool::new(false)),
+            check_requested: Arc::new(std::sync::Mutex::new(Instant::now())),
+            reactions_forbidden: Arc::new(AtomicBool::new(false)),
             message,
             bot,
             shard,
@@ -284,6 +950,10 @@ impl MainMessage {
     }
 
     pub async fn run(self, guild: ReciprocityGuild) {
+        //Buttons are handled through component interactions by the guild
+        if self.context.guild_config.buttons {
+            return;
+        }
         tokio::spawn(self.clone().emote_check());
 
         let mut collector = self
@@ -297,6 +967,12 @@ impl MainMessage {
             "Starting Message Collector for Main Message: {:?}, {:?}",
             self.message.id, self.context.id
         );
+        if !self.context.guild_config.remove_reactions {
+            info!(
+                "Reaction removal disabled, leaving Reactions in place. {:?}",
+                self.context.id
+            );
+        }
         while let Some(reaction) = collector.next().await {
             match reaction.deref() {
                 ReactionAction::Added(reaction) => {
@@ -304,8 +980,12 @@ impl MainMessage {
                         if self.context.bots.contains_id(user) {
                             continue;
                         }
-                        // Pass on Event to Guild
-                        if let Ok(emote_action) = reaction.deref().try_into() {
+                        // Pass on Event to Guild, the Separator is no Action
+                        let action: Result<EmoteAction, _> = reaction.deref().try_into();
+                        let action = action
+                            .ok()
+                            .filter(|a| !a.is_nothing() && self.emotes.contains(a));
+                        if let Some(emote_action) = action {
                             let cloned_user = *user;
                             let cloned_guild = guild.clone();
                             tokio::spawn(async move {
@@ -315,20 +995,25 @@ impl MainMessage {
                             });
                         }
 
-                        // Delete Reaction
-                        self.context
-                            .scheduler
-                            .process_enqueue(DeleteMessageReactionTask {
-                                channel: reaction.channel_id,
-                                message: reaction.message_id,
-                                user: *user,
-                                reaction: reaction.emoji.clone(),
-                            })
-                            .await
-                            .ok();
+                        // Delete Reaction, unless disabled or not permitted
+                        if self.context.guild_config.remove_reactions
+                            && !self.reactions_forbidden.load(Ordering::Relaxed)
+                        {
+                            self.context
+                                .scheduler
+                                .process_enqueue(DeleteMessageReactionTask {
+                                    channel: reaction.channel_id,
+                                    message: reaction.message_id,
+                                    user: *user,
+                                    reaction: reaction.emoji.clone(),
+                                    forbidden: Some(self.reactions_forbidden.clone()),
+                                })
+                                .await
+                                .ok();
+                        }
                     } else {
                         // Check Message
-                        tokio::spawn(self.clone().emote_check());
+                        self.schedule_emote_check();
                     }
                 }
                 ReactionAction::Removed(reaction) => {
@@ -336,11 +1021,11 @@ impl MainMessage {
                         if self.context.bots.contains_id(user) {
                             tokio::spawn(self.clone().rebuild_emotes());
                         } else {
-                            tokio::spawn(self.clone().emote_check());
+                            self.schedule_emote_check();
                         }
                     } else {
                         // Check Message
-                        tokio::spawn(self.clone().emote_check());
+                        self.schedule_emote_check();
                     }
                 }
             }
@@ -366,8 +1051,41 @@ impl MainMessage {
 
     async fn update(self) {
         let mut message = self.message;
+        let interval = Duration::from_secs(self.context.guild_config.update_interval)
+            .max(MIN_MESSAGE_UPDATE_INTERVAL);
+        let mut last_update = Instant::now();
+        let mut players_watch = self.context.player_manager.players_watch();
+        let mut states = self.context.player_manager.get_all_player_states().await;
         loop {
-            tokio::time::sleep(MESSAGE_UPDATE_INTERVAL).await;
+            let playing = states.iter().any(|s| {
+                let state = s.borrow();
+                state.play_state == PlayState::Play && state.current.is_some()
+            });
+
+            //Edits are driven by the Player States, the timer only advances the elapsed time
+            let refresh = tokio::select! {
+                changed = any_state_changed(&mut states) => !changed,
+                _ = async {
+                    if playing {
+                        tokio::time::sleep(interval).await
+                    } else {
+                        futures::future::pending().await
+                    }
+                } => false,
+                _ = players_watch.changed() => true,
+            };
+            if refresh {
+                states = self.context.player_manager.get_all_player_states().await;
+            }
+
+            //Never edit faster than the minimum
+            let since = last_update.elapsed();
+            if since < MIN_MESSAGE_UPDATE_INTERVAL {
+                tokio::time::sleep(MIN_MESSAGE_UPDATE_INTERVAL - since).await;
+            }
+            last_update = Instant::now();
+
+            //Content is built with whole seconds, so sub-second progress causes no edit
             let content = Self::content(&self.context).await;
             if message.content.eq(content.as_str()) {
                 continue;
@@ -402,7 +1120,40 @@ impl MainMessage {
         }
     }
 
+    ///Runs an Emote Check once the Reactions settled
+    /// - Checks requested while one is pending are coalesced into it and put it off
+    fn schedule_emote_check(&self) {
+        let now = Instant::now();
+        *self
+            .check_requested
+            .lock()
+            .expect("Emote Check Lock was poisoned") = now;
+        if self.check_pending.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let msg = self.clone();
+        tokio::spawn(async move {
+            let first = now;
+            loop {
+                let last = *msg
+                    .check_requested
+                    .lock()
+                    .expect("Emote Check Lock was poisoned");
+                let due = emote_check_due(first, last);
+                if Instant::now() >= due {
+                    break;
+                }
+                tokio::time::sleep_until(due.into()).await;
+            }
+            msg.check_pending.store(false, Ordering::SeqCst);
+            msg.emote_check().await
+        });
+    }
+
     pub async fn emote_check(self) {
+        if self.context.guild_config.buttons {
+            return;
+        }
         let lock = self.lock.lock().await;
 
         //Get fresh msg
@@ -422,33 +1173,54 @@ impl MainMessage {
             }
         };
 
-        //Check if any reaction is missing
-        let missing_reaction = Self::EMOTES.iter().any(|e| {
-            !msg.reactions
-                .iter()
-                .any(|r| r.reaction_type.unicode_eq(e.unicode()))
-        });
-        if !missing_reaction {
-            return;
-        }
+        //Find the first Position not matching the configured order
+        let present: Vec<_> = msg
+            .reactions
+            .iter()
+            .filter(|r| {
+                self.emotes
+                    .iter()
+                    .any(|e| r.reaction_type.unicode_eq(e.unicode()))
+            })
+            .collect();
+        let first = match first_misplaced(&self.emotes, present.iter().map(|r| &r.reaction_type)) {
+            Some(first) => first,
+            None => return,
+        };
 
         info!(
-            "Message is missing emote: {:?}, {:?}",
-            self.context.id, msg.id
+            "Message is missing emotes: {:?}, {:?}, {:?}",
+            self.context.id,
+            msg.id,
+            &self.emotes[first..]
         );
 
-        //Delete all Reactions
-        let delete_all_res = msg.delete_reactions(self.bot.cache_http()).await;
-        if let Err(e) = delete_all_res {
-            warn!(
-                "Error deleting Reactions for Message: {:?}, {:?}",
-                msg.id, e
-            );
-            return;
+        //Remove our own Reactions from there on, so they are added again behind the others
+        // - Reactions of Users keep their Position, if they can not be removed
+        for r in present.iter().filter(|r| r.me) {
+            let position = self
+                .emotes
+                .iter()
+                .position(|e| r.reaction_type.unicode_eq(e.unicode()));
+            if position.map_or(true, |p| p < first) {
+                continue;
+            }
+            let task = DeleteOwnMessageReactionTask {
+                channel: msg.channel_id,
+                message: msg.id,
+                reaction: r.reaction_type.clone(),
+            };
+            if let Err(e) = self.context.scheduler.process(task).await {
+                warn!(
+                    "Error removing Reaction from Message: {:?}, {:?}",
+                    msg.id, e
+                );
+                return;
+            }
         }
 
-        //Add Reactions one after another
-        for e in Self::EMOTES.iter() {
+        //Add the Reactions again, one after another
+        for e in &self.emotes[first..] {
             let task = AddMessageReactionTask {
                 channel: msg.channel_id,
                 message: msg.id,
@@ -465,6 +1237,9 @@ impl MainMessage {
     }
 
     pub async fn rebuild_emotes(self) {
+        if self.context.guild_config.buttons {
+            return;
+        }
         let lock = self.lock.lock().await;
 
         //Delete all Reactions
@@ -478,7 +1253,7 @@ impl MainMessage {
         }
 
         //Add Reactions one after another
-        for e in Self::EMOTES.iter() {
+        for e in self.emotes.iter() {
             let task = AddMessageReactionTask {
                 channel: self.message.channel_id,
                 message: self.message.id,
@@ -509,6 +1284,97 @@ impl MainMessage {
         msg
     }
 
+    ///Builds a fixed width progress bar
+    /// - Returns None for unknown lengths
+    fn progress_bar(elapsed: &Duration, total: &Duration) -> Option<String> {
+        if total.as_millis() == 0 {
+            return None;
+        }
+        let progress = (elapsed.as_secs_f64() / total.as_secs_f64()).min(1.0);
+        let pos = ((PROGRESS_BAR_WIDTH - 1) as f64 * progress).round() as usize;
+
+        let mut bar = "▬".repeat(pos);
+        bar.push('🔘');
+        bar.push_str(&"▬".repeat(PROGRESS_BAR_WIDTH - 1 - pos));
+        Some(bar)
+    }
+
+    ///Summary of the remaining Tracks and the total remaining playtime
+    fn queue_summary<'a>(playlist: impl IntoIterator<Item = &'a Track>) -> String {
+        let mut count = 0;
+        let mut unknown = 0;
+        let mut live = 0;
+        let mut total = Duration::from_secs(0);
+        for track in playlist {
+            count += 1;
+            match &track.info {
+                //Streams have no Length to add up
+                Some(info) if info.is_stream => live += 1,
+                Some(info) => total += Duration::from_millis(info.length),
+                None => unknown += 1,
+            }
+        }
+
+        let mut summary = String::new();
+        if count > 2 {
+            write!(summary, "+{} more | ", count - 2).unwrap();
+        }
+        write!(summary, "Queue: {}", Self::duration_fmt(&total)).unwrap();
+        if unknown > 0 {
+            write!(summary, " +{} unknown", unknown).unwrap();
+        }
+        if live > 0 {
+            write!(summary, " +{} live", live).unwrap();
+        }
+        summary
+    }
+
+    ///Title of the Track, followed by the Author if there is one
+    fn title_author(info: &Info) -> String {
+        if info.author.trim().is_empty() {
+            return info.title.clone();
+        }
+        format!("{} - {}", info.title, info.author)
+    }
+
+    ///Title and Position of the current Track, with a progress bar if the Length is known
+    fn current_track(msg: &mut String, state: &PlayerState) {
+        if let Some((_, cur)) = state.current.as_ref().filter(|(_, t)| is_stream(t)) {
+            write!(
+                msg,
+                "{:.*} [🔴 LIVE]\r\n",
+                SEARCH_TITLE_LIMIT,
+                cur.info
+                    .as_ref()
+                    .map_or("No Track Name".to_string(), Self::title_author),
+            )
+            .unwrap();
+        } else if let Some((_, cur)) = &state.current {
+            //Does not advance while paused
+            let elapsed = state.position().unwrap_or_default();
+            let elapsed = Duration::from_secs(elapsed.as_secs());
+            write!(
+                msg,
+                "{:.*} [{}/{}]\r\n",
+                SEARCH_TITLE_LIMIT,
+                cur.info
+                    .as_ref()
+                    .map_or("No Track Name".to_string(), Self::title_author),
+                Self::duration_fmt(&elapsed),
+                cur.info.clone().map_or("--:--".to_string(), |i| {
+                    Self::duration_fmt(&Duration::from_millis(i.length))
+                })
+            )
+            .unwrap();
+            if let Some(info) = &cur.info {
+                if let Some(bar) = Self::progress_bar(&elapsed, &Duration::from_millis(info.length))
+                {
+                    write!(msg, "{}\r\n", bar).unwrap();
+                }
+            }
+        }
+    }
+
     async fn content(context: &Context) -> String {
         let mut msg: String = "```cs\r\n".to_string();
         let states = context.player_manager.get_all_player_states().await;
@@ -526,33 +1392,42 @@ impl MainMessage {
                     } else {
                         write!(
                             msg,
-                            " {}{}\r\n",
+                            " {}{}{}{}\r\n",
                             state.play_state.to_string(),
-                            state.playback.to_string()
+                            state.playback.to_string(),
+                            if state.shuffle {
+                                EmoteAction::SHUFFLE
+                            } else {
+                                ""
+                            },
+                            state.filter.map_or(String::new(), |f| format!(" {}", f))
+                        )
+                        .unwrap();
+                    }
+                    if state.speed != 1.0 || state.pitch != 1.0 {
+                        write!(
+                            msg,
+                            "Speed: x{:.2} | Pitch: x{:.2}\r\n",
+                            state.speed, state.pitch
                         )
                         .unwrap();
                     }
 
-                    //For not adding elapsed value if the player is paused
-                    let elapse_mult = if state.play_state == PlayState::Play {
-                        1
-                    } else {
-                        0
-                    };
-                    if let Some(((dur, when), cur)) = &state.current {
+                    Self::current_track(&mut msg, &state);
+                    if let Some((title, reason)) = &state.failed {
                         write!(
                             msg,
-                            "{:.*} [{}/{}]\r\n",
-                            SEARCH_TITLE_LIMIT,
-                            cur.info
-                                .clone()
-                                .map_or("No Track Name".to_string(), |i| i.title),
-                            Self::duration_fmt(&((when.elapsed() * elapse_mult) + *dur)),
-                            cur.info
-                                .clone()
-                                .map_or("--:--".to_string(), |i| Self::duration_fmt(
-                                    &Duration::from_millis(i.length)
-                                ))
+                            "Skipped {:.*}: {}\r\n",
+                            SEARCH_TITLE_LIMIT, title, reason
+                        )
+                        .unwrap();
+                    }
+                    if let Some(votes) = &state.skip_votes {
+                        write!(
+                            msg,
+                            "Skip Votes: {}/{}\r\n",
+                            votes.users.len(),
+                            votes.required
                         )
                         .unwrap();
                     }
@@ -569,6 +1444,19 @@ impl MainMessage {
                         )
                         .unwrap();
                     }
+                    if !state.playlist.is_empty() {
+                        write!(msg, "{}\r\n", Self::queue_summary(&state.playlist)).unwrap();
+                    }
+                    //Enqueues are rejected until a Track leaves the Queue
+                    if state.playlist.is_full() {
+                        write!(
+                            msg,
+                            "Queue full ({}/{})\r\n",
+                            state.playlist.len(),
+                            state.playlist.capacity()
+                        )
+                        .unwrap();
+                    }
                     write!(msg, "\r\n").unwrap();
                 }
             }
@@ -579,9 +1467,24 @@ impl MainMessage {
             write!(msg, "[No active Player]").unwrap();
         }
 
-        write!(msg, "```").unwrap();
+        Self::truncate(&mut msg, MESSAGE_LIMIT - CODE_BLOCK_END.len());
+        write!(msg, "{}", CODE_BLOCK_END).unwrap();
         msg
     }
+
+    ///Cuts the content at a line break so it has at most limit characters
+    /// - Editing a Message above the Discord limit fails, which would stop every further update
+    fn truncate(msg: &mut String, limit: usize) {
+        if msg.chars().count() <= limit {
+            return;
+        }
+        let marker = "…(truncated)\r\n";
+        let keep = limit - marker.chars().count();
+        let cut = msg.char_indices().nth(keep).map_or(msg.len(), |(i, _)| i);
+        let cut = msg[..cut].rfind("\r\n").map_or(cut, |i| i + 2);
+        msg.truncate(cut);
+        msg.push_str(marker);
+    }
 }
 
 #[derive(Error, Debug)]
@@ -600,22 +1503,32 @@ pub enum MessageError {
     Deleted(),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, AsRefStr)]
 pub enum EmoteAction {
     Number(usize),
     PlayPause(),
     Next(),
     Prev(),
     Join(),
+    ///Leaves and clears the Queue, Companions can also leave and keep it
     Leave(),
     Delete(),
     LoopOne(),
     LoopAll(),
+    PagePrev(),
+    PageNext(),
+    Queue(),
+    BassBoost(),
+    Lyrics(),
+    Clear(),
+    Shuffle(),
     Nothing(),
 }
 
 impl EmoteAction {
-    const NUMBERS: [&'static str; 11] = ["0️⃣", "1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣", "6️⃣", "7️⃣", "8️⃣", "9️⃣", "🔟"];
+    const NUMBERS: [&'static str; 11] = [
+        "0️⃣", "1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣", "6️⃣", "7️⃣", "8️⃣", "9️⃣", "🔟",
+    ];
     const PLAY_PAUSE: &'static str = "⏯";
     const NEXT: &'static str = "⏭";
     const PREV: &'static str = "⏮";
@@ -624,7 +1537,31 @@ impl EmoteAction {
     const DELETE: &'static str = "❌";
     const LOOP_ONE: &'static str = "🔂";
     const LOOP_ALL: &'static str = "🔁";
+    const PAGE_PREV: &'static str = "◀";
+    const PAGE_NEXT: &'static str = "▶";
+    const QUEUE: &'static str = "📜";
+    const BASS_BOOST: &'static str = "🎛";
+    const LYRICS: &'static str = "🎤";
+    const CLEAR: &'static str = "🗑";
+    const SHUFFLE: &'static str = "🔀";
     const NOTHING: &'static str = "無";
+    ///Every Action that can be a Main Message Control
+    const CONTROLS: [EmoteAction; 14] = [
+        EmoteAction::PlayPause(),
+        EmoteAction::Next(),
+        EmoteAction::Prev(),
+        EmoteAction::Join(),
+        EmoteAction::Leave(),
+        EmoteAction::Delete(),
+        EmoteAction::LoopOne(),
+        EmoteAction::LoopAll(),
+        EmoteAction::Queue(),
+        EmoteAction::BassBoost(),
+        EmoteAction::Lyrics(),
+        EmoteAction::Clear(),
+        EmoteAction::Shuffle(),
+        EmoteAction::Nothing(),
+    ];
 
     pub fn unicode(&self) -> &str {
         match self {
@@ -637,6 +1574,13 @@ impl EmoteAction {
             EmoteAction::Delete() => Self::DELETE,
             EmoteAction::LoopOne() => Self::LOOP_ONE,
             EmoteAction::LoopAll() => Self::LOOP_ALL,
+            EmoteAction::PagePrev() => Self::PAGE_PREV,
+            EmoteAction::PageNext() => Self::PAGE_NEXT,
+            EmoteAction::Queue() => Self::QUEUE,
+            EmoteAction::BassBoost() => Self::BASS_BOOST,
+            EmoteAction::Lyrics() => Self::LYRICS,
+            EmoteAction::Clear() => Self::CLEAR,
+            EmoteAction::Shuffle() => Self::SHUFFLE,
             EmoteAction::Nothing() => Self::NOTHING,
         }
     }
@@ -644,6 +1588,73 @@ impl EmoteAction {
     pub fn reaction(&self) -> ReactionType {
         ReactionType::Unicode(self.unicode().to_string())
     }
+
+    ///Action of a lowercase Text Command, e.g. skip or loop
+    /// - Control Names work as well, e.g. playpause
+    /// - pause and resume have no Control, as they must not toggle
+    pub fn from_command(command: &str) -> Option<EmoteAction> {
+        match command {
+            "skip" => Some(EmoteAction::Next()),
+            "back" => Some(EmoteAction::Prev()),
+            "loop" => Some(EmoteAction::LoopAll()),
+            "loopone" | "repeat" => Some(EmoteAction::LoopOne()),
+            "bass" => Some(EmoteAction::BassBoost()),
+            _ => Self::CONTROLS
+                .iter()
+                .find(|e| e.as_ref().eq_ignore_ascii_case(command))
+                .copied(),
+        }
+    }
+
+    pub fn is_nothing(&self) -> bool {
+        matches!(self, EmoteAction::Nothing())
+    }
+}
+
+impl EmoteAction {
+    ///What the Emote needs permission for, None if anybody may use it
+    pub fn permission(&self) -> Option<Permission> {
+        match self {
+            EmoteAction::PlayPause() => Some(Permission::PlayPause),
+            EmoteAction::Next() => Some(Permission::Next),
+            EmoteAction::Prev() => Some(Permission::Prev),
+            EmoteAction::Join() => Some(Permission::Join),
+            EmoteAction::Leave() => Some(Permission::Leave),
+            //Delete clears the Queue as well, so it is restricted like Clear
+            EmoteAction::Delete() | EmoteAction::Clear() => Some(Permission::Clear),
+            EmoteAction::LoopOne() => Some(Permission::LoopOne),
+            EmoteAction::LoopAll() => Some(Permission::LoopAll),
+            EmoteAction::Queue() => Some(Permission::Queue),
+            EmoteAction::BassBoost() => Some(Permission::BassBoost),
+            EmoteAction::Lyrics() => Some(Permission::Lyrics),
+            EmoteAction::Shuffle() => Some(Permission::Shuffle),
+            EmoteAction::Number(_)
+            | EmoteAction::PagePrev()
+            | EmoteAction::PageNext()
+            | EmoteAction::Nothing() => None,
+        }
+    }
+}
+
+impl From<Control> for EmoteAction {
+    fn from(control: Control) -> Self {
+        match control {
+            Control::PlayPause => EmoteAction::PlayPause(),
+            Control::Next => EmoteAction::Next(),
+            Control::Prev => EmoteAction::Prev(),
+            Control::Join => EmoteAction::Join(),
+            Control::Leave => EmoteAction::Leave(),
+            Control::Delete => EmoteAction::Delete(),
+            Control::LoopOne => EmoteAction::LoopOne(),
+            Control::LoopAll => EmoteAction::LoopAll(),
+            Control::Queue => EmoteAction::Queue(),
+            Control::BassBoost => EmoteAction::BassBoost(),
+            Control::Lyrics => EmoteAction::Lyrics(),
+            Control::Clear => EmoteAction::Clear(),
+            Control::Shuffle => EmoteAction::Shuffle(),
+            Control::Nothing => EmoteAction::Nothing(),
+        }
+    }
 }
 
 impl TryFrom<&Reaction> for EmoteAction {
@@ -670,6 +1681,13 @@ impl TryFrom<&str> for EmoteAction {
             Self::DELETE => Ok(Self::Delete()),
             Self::LOOP_ONE => Ok(Self::LoopOne()),
             Self::LOOP_ALL => Ok(Self::LoopAll()),
+            Self::PAGE_PREV => Ok(Self::PagePrev()),
+            Self::PAGE_NEXT => Ok(Self::PageNext()),
+            Self::QUEUE => Ok(Self::Queue()),
+            Self::BASS_BOOST => Ok(Self::BassBoost()),
+            Self::LYRICS => Ok(Self::Lyrics()),
+            Self::CLEAR => Ok(Self::Clear()),
+            Self::SHUFFLE => Ok(Self::Shuffle()),
             Self::NOTHING => Ok(Self::Nothing()),
             _ => {
                 if let Some(i) = Self::NUMBERS.iter().position(|n| value.eq(*n)) {
@@ -681,3 +1699,154 @@ impl TryFrom<&str> for EmoteAction {
         }
     }
 }
+
+///When an Emote Check runs, given the first and last request since the previous Check
+/// - Ongoing requests put it off, but never past EMOTE_CHECK_MAX_DELAY
+fn emote_check_due(first: Instant, last: Instant) -> Instant {
+    (last + EMOTE_CHECK_DEBOUNCE).min(first + EMOTE_CHECK_MAX_DELAY)
+}
+
+///Position of the first Emote whose Reaction is missing or out of order
+/// - The Reactions have to be in the order of the Message, only containing configured Emotes
+fn first_misplaced<'a>(
+    emotes: &[EmoteAction],
+    reactions: impl IntoIterator<Item = &'a ReactionType>,
+) -> Option<usize> {
+    let mut reactions = reactions.into_iter();
+    emotes.iter().position(|e| {
+        !reactions
+            .next()
+            .map_or(false, |r| r.unicode_eq(e.unicode()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::guild_config;
+    use crate::player::tests::{player_state, track};
+
+    #[test]
+    fn truncate_stays_within_the_limit() {
+        let limit = MESSAGE_LIMIT - CODE_BLOCK_END.len();
+        let contents = [
+            //A single Line without any Line Break
+            "a".repeat(MESSAGE_LIMIT * 2),
+            //Multi Byte Characters
+            "🎵".repeat(MESSAGE_LIMIT * 2),
+            "ä\r\n".repeat(MESSAGE_LIMIT),
+            //Only a Line Break at the very start
+            format!("\r\n{}", "b".repeat(MESSAGE_LIMIT * 2)),
+            "\r\n".repeat(MESSAGE_LIMIT),
+            "x".repeat(limit + 1),
+        ];
+        for content in contents.iter() {
+            let mut msg = content.clone();
+            MainMessage::truncate(&mut msg, limit);
+            assert!(
+                msg.chars().count() <= limit,
+                "{} chars",
+                msg.chars().count()
+            );
+            assert!(msg.ends_with("…(truncated)\r\n"));
+        }
+
+        //Content within the limit stays as it is
+        let mut msg = "y".repeat(limit);
+        MainMessage::truncate(&mut msg, limit);
+        assert_eq!(msg, "y".repeat(limit));
+    }
+
+    #[test]
+    fn paused_position_is_shown() {
+        let mut state = player_state();
+        state.play_state = PlayState::Pause;
+        //Seeked to 1:30 while paused, Lavalink sends no Updates until resumed
+        state.current = Some((
+            (
+                Duration::from_secs(90),
+                Instant::now() - Duration::from_secs(5),
+            ),
+            track("a", 180_000, false),
+        ));
+        let mut msg = String::new();
+        MainMessage::current_track(&mut msg, &state);
+        assert!(
+            msg.starts_with("Title a - Author [01:30/03:00]\r\n"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn live_stream_is_shown_as_live() {
+        let mut state = player_state();
+        state.current = Some((
+            (Duration::from_secs(90), Instant::now()),
+            track("a", u64::MAX, true),
+        ));
+        let mut msg = String::new();
+        MainMessage::current_track(&mut msg, &state);
+        //No Position or progress bar, a Stream has no meaningful Length
+        assert_eq!(msg, "Title a - Author [🔴 LIVE]\r\n");
+    }
+
+    #[test]
+    fn emote_check_waits_for_the_last_request() {
+        let first = Instant::now();
+        assert_eq!(emote_check_due(first, first), first + EMOTE_CHECK_DEBOUNCE);
+
+        let last = first + Duration::from_secs(1);
+        assert_eq!(emote_check_due(first, last), last + EMOTE_CHECK_DEBOUNCE);
+
+        //Constant Reaction changes can not put off the Check forever
+        let last = first + EMOTE_CHECK_MAX_DELAY;
+        assert_eq!(emote_check_due(first, last), first + EMOTE_CHECK_MAX_DELAY);
+    }
+
+    #[test]
+    fn first_misplaced_reaction() {
+        let emotes = [
+            EmoteAction::PlayPause(),
+            EmoteAction::Next(),
+            EmoteAction::Join(),
+        ];
+        let reactions = |emotes: &[EmoteAction]| -> Vec<ReactionType> {
+            emotes.iter().map(EmoteAction::reaction).collect()
+        };
+
+        assert_eq!(first_misplaced(&emotes, &reactions(&emotes)), None);
+        assert_eq!(first_misplaced(&emotes, &reactions(&[])), Some(0));
+        //Missing in the middle, Join has to be removed and added again
+        assert_eq!(
+            first_misplaced(&emotes, &reactions(&[emotes[0], emotes[2]])),
+            Some(1)
+        );
+        //Missing at the end
+        assert_eq!(first_misplaced(&emotes, &reactions(&emotes[..2])), Some(2));
+        //Swapped
+        assert_eq!(
+            first_misplaced(&emotes, &reactions(&[emotes[1], emotes[0], emotes[2]])),
+            Some(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn lower_search_timeout_times_out_sooner() {
+        tokio::time::pause();
+        let mut waited = Vec::new();
+        for search_timeout in [60, 5] {
+            let config = guild_config(&format!("search_timeout: {}", search_timeout));
+            let start = tokio::time::Instant::now();
+            let selection = select_until(
+                SearchMessage::deadline(&config),
+                futures::future::pending::<Option<Track>>(),
+            )
+            .await;
+            assert!(matches!(selection, Err(MessageError::Timeout())));
+            waited.push(start.elapsed());
+        }
+        assert!(waited[1] < waited[0]);
+        assert!(waited[1] >= Duration::from_secs(5));
+    }
+}
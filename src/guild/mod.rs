@@ -9,26 +9,49 @@ use crate::bots::BotMap;
 use crate::config::Config;
 use crate::context::{Context, GuildEventHandler};
 use crate::event_handler::EventHandler;
-use crate::guild::message_manager::{EmoteAction, MainMessage, SearchMessage};
+use crate::guild::message_manager::{
+    EmoteAction, LyricsMessage, MainMessage, NoticeMessage, NowPlayingMessage, QueueMessage,
+    SearchMessage,
+};
 use crate::guild::player_manager::{PlayerManager, PlayerMapError, PlayerRequest};
 use crate::guild::scheduler::GuildScheduler;
+use crate::guild::slash_commands::SlashCommand;
 use crate::lavalink_handler::LavalinkEvent;
-use crate::player::Playback;
+use crate::lyrics::LyricsProvider;
+use crate::node_pool::NodePool;
+use crate::persistence::QueueStore;
+use crate::player::{
+    AudioFilter, PlayState, Playback, PlayerError, PLAYLIST_LOADED, SEARCH_RESULT,
+};
+use crate::spotify::SpotifyResolver;
 use crate::task_handle::DeleteMessagePoolTask;
 use lavalink_rs::LavalinkClient;
 use log::{debug, info, warn};
 use serenity::model::event::ResumedEvent;
-use serenity::model::prelude::{Message, VoiceState};
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+use serenity::model::interactions::message_component::{
+    InteractionMessage, MessageComponentInteraction,
+};
+use serenity::model::interactions::{
+    InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+};
+use serenity::model::prelude::{Attachment, Message, User, VoiceState};
 use serenity::FutureExt;
 use std::borrow::Borrow;
+use std::convert::TryFrom;
 use std::ops::Deref;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
-use url::Url;
+
+///Time to confirm clearing the Queue
+const CLEAR_CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+///File Extensions of Attachments, that are handed to Lavalink
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "ogg", "opus", "wav", "flac", "m4a", "aac", "webm"];
 
 pub mod message_manager;
 pub mod player_manager;
 pub mod scheduler;
+pub mod slash_commands;
 
 #[derive(Clone)]
 pub struct ReciprocityGuild(Context);
@@ -38,20 +61,32 @@ impl ReciprocityGuild {
         id: GuildId,
         bots: Arc<BotMap>,
         event_handler: EventHandler,
-        lavalink: Arc<HashMap<UserId, LavalinkClient>>,
+        lavalink: Arc<HashMap<UserId, Arc<NodePool>>>,
         config: Arc<Config>,
+        spotify: Option<Arc<SpotifyResolver>>,
+        lyrics: Option<Arc<LyricsProvider>>,
+        store: Option<Arc<QueueStore>>,
     ) -> Result<ReciprocityGuild, ReciprocityGuildError> {
         info!("Creating Guild: {}", id);
-        let channel = config
+        let guild_config = config
             .guilds
             .values()
-            .map(|guild| (guild.guild_id, ChannelId(guild.channel_id)))
-            .find(|(guild_id, _)| *guild_id == id.0)
-            .ok_or(ReciprocityGuildError::GuildNotInConfig(id))?
-            .1;
+            .find(|guild| guild.guild_id == id.0)
+            .cloned()
+            .ok_or(ReciprocityGuildError::GuildNotInConfig(id))?;
+        let channel = ChannelId(guild_config.channel_id);
 
         let scheduler = GuildScheduler::new(id, channel, bots.clone());
-        let player_manager = Arc::new(PlayerManager::new(id, bots.clone(), lavalink));
+        let player_manager = Arc::new(PlayerManager::new(
+            id,
+            guild_config.clone(),
+            bots.clone(),
+            lavalink,
+            spotify,
+            lyrics,
+            store,
+        ));
+        tokio::spawn(player_manager.clone().persist_run());
         let search_messages = Arc::new(RwLock::new(HashMap::new()));
 
         let guild = ReciprocityGuild {
@@ -61,11 +96,15 @@ impl ReciprocityGuild {
                 bots,
                 event_handler,
                 config,
+                guild_config,
                 scheduler,
                 player_manager,
                 search_messages,
+                search_interactions: Arc::new(RwLock::new(HashMap::new())),
                 main_message: Arc::new(RwLock::new(None)),
                 delete_pool: Arc::new(Mutex::new(Vec::new())),
+                now_playing: Arc::new(Mutex::new(HashMap::new())),
+                clear_request: Arc::new(Mutex::new(None)),
             },
         };
 
@@ -80,115 +119,23 @@ impl ReciprocityGuild {
         self.0.id
     }
 
-    //Delete every irrelevant message
-    async fn clear_messages(ctx: Context) {
-        info!("Starting clear_messages in {:?}", ctx.id);
-        let bot = ctx.bots.get_any_guild_bot(&ctx.id).await;
-        let bot = match bot {
-            None => {
-                warn!("Could not get a Bot for Guild: {:?}", ctx.id);
-                return;
-            }
-            Some(bot) => bot,
-        };
-
-        let mut msgs = ctx.channel.messages(bot.http(), |b| b.limit(100)).await;
-        loop {
-            let last = if let Ok(mut msgs) = msgs {
-                let last = msgs.last().cloned();
-                let s = ctx
-                    .main_message
-                    .read()
-                    .await
-                    .as_ref()
-                    .map(|(msg, _)| msg.message_id());
-                let msgs = msgs.drain(..).filter(|m| !Some(m.id).eq(&s));
-                let searches: Vec<_> = ctx.search_messages.read().await.values().copied().collect();
-                let msgs: Vec<_> = msgs
-                    .filter(|m| {
-                        !searches
-                            .iter()
-                            .any(|(msg, _)| msg.map(|msg| m.id.eq(&msg)).unwrap_or(false))
-                    })
-                    .collect();
-                if msgs.is_empty() {
-                    return;
-                }
-                let mut msgs: Vec<_> = msgs.iter().map(|m| m.id).collect();
-
-                info!(
-                    "Attempting to delete {} Messages in Guild: {:?}",
-                    msgs.len(),
-                    ctx.id
-                );
-                ctx.delete_pool.lock().await.append(&mut msgs);
-                ctx.scheduler
-                    .process_enqueue(DeleteMessagePoolTask {
-                        channel: ctx.channel,
-                        pool: ctx.delete_pool.clone(),
-                    })
-                    .await
-                    .ok();
-                last.unwrap()
-            } else {
-                return;
-            };
-            msgs = ctx
-                .channel
-                .messages(bot.http(), |b| b.limit(100).after(last))
-                .await;
-        }
-    }
-}
-
-#[derive(Debug, Error)]
-pub enum ReciprocityGuildError {
-    #[error("Guild was not found in config: {0:?}")]
-    GuildNotInConfig(GuildId),
-    //#[error("PlayerMap Error occurred: {0:?}")]
-    //PlayerMap(PlayerMapError),
-}
-
-#[async_trait]
-impl GuildEventHandler for ReciprocityGuild {
-    async fn new_message(&self, message: Message) {
-        //Ignore if the channel is wrong or the message was send by our bots
-        if !self.0.channel.eq(&message.channel_id) || self.0.bots.contains_id(&message.author.id) {
-            return;
-        }
-        info!("Received Message: {}, {}", message.id, message.author.id);
-
-        //Delete Message with small delay
-        let msg_clone = message.id;
-        let ch_clone = message.channel_id;
-        let delete_pool_clone = self.0.delete_pool.clone();
-        let scheduler_clone = self.0.scheduler.clone();
-        tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_millis(500)).await;
-            delete_pool_clone.lock().await.push(msg_clone);
-            scheduler_clone
-                .process_enqueue(DeleteMessagePoolTask {
-                    channel: ch_clone,
-                    pool: delete_pool_clone,
-                })
-                .await
-                .ok();
-        });
-
+    ///Joins the Channel of the Requester, if needed, and enqueues what the Query finds
+    /// - Searches let the Requester pick a Track first
+    async fn play(&self, requester: User, query: String, attached: bool) {
         //Get user Voice Channel
         let voice_channel = match self
             .0
             .bots
-            .get_user_voice_state(&message.author.id, &self.0.id)
+            .get_user_voice_state(&requester.id, &self.0.id)
             .await
         {
             None => {
-                warn!("No Voice Channel for User: {}", message.author.id);
+                warn!("No Voice Channel for User: {}", requester.id);
                 return;
             }
             Some(vs) => match vs.channel_id {
                 None => {
-                    warn!("No Voice Channel for User: {}", message.author);
+                    warn!("No Voice Channel for User: {}", requester);
                     return;
                 }
                 Some(channel) => channel,
@@ -208,6 +155,9 @@ impl GuildEventHandler for ReciprocityGuild {
                     }
                     _ => {
                         warn!("Join Failed, Aborting due to Error: {:?}", e);
+                        if let Some(text) = e.user_message() {
+                            tokio::spawn(NoticeMessage::show(text, self.0.clone()));
+                        }
                         return;
                     }
                 }
@@ -218,7 +168,7 @@ impl GuildEventHandler for ReciprocityGuild {
         let same_voice_state = match self
             .0
             .bots
-            .get_user_voice_state(&message.author.id, &self.0.id)
+            .get_user_voice_state(&requester.id, &self.0.id)
             .await
         {
             None => false,
@@ -236,15 +186,31 @@ impl GuildEventHandler for ReciprocityGuild {
             return;
         }
 
+        if let Err(e) = self.0.player_manager.enqueue_cooldown(requester.id).await {
+            warn!("Enqueue rejected. {:?}, {:?}", requester.id, e);
+            if let Some(text) = e.user_message() {
+                tokio::spawn(NoticeMessage::show(text, self.0.clone()));
+            }
+            return;
+        }
+
         //Start search
         let search_res = self
             .0
             .player_manager
-            .search(voice_channel, message.content.clone())
+            .search(voice_channel, query.clone())
             .await;
         let (bot, songs) = match search_res {
             Err(e) => {
                 warn!("Search Error: {:?}", e);
+                if let Some(text) = e.user_message() {
+                    tokio::spawn(NoticeMessage::show(text, self.0.clone()));
+                } else if attached {
+                    tokio::spawn(NoticeMessage::show(
+                        undecodable_attachment(),
+                        self.0.clone(),
+                    ));
+                }
                 return;
             }
             Ok((bot, songs)) => {
@@ -258,15 +224,19 @@ impl GuildEventHandler for ReciprocityGuild {
         };
 
         //Exit if no song was found
-        if songs.is_empty() {
-            warn!("No Song was found for Query: {}", message.content);
+        if songs.tracks.is_empty() {
+            warn!("No Song was found for Query: {}", query);
+            if attached {
+                tokio::spawn(NoticeMessage::show(
+                    undecodable_attachment(),
+                    self.0.clone(),
+                ));
+            }
             return;
         }
 
-        //If query was not a valid url
-        let tracks = if Url::parse(&message.content).is_err() {
-            //Get relevant stuff for the search message
-            let requester = message.author;
+        //Only let the user pick, if the query was a search
+        let tracks = if songs.load_type.eq(SEARCH_RESULT) {
             let shard = match self
                 .0
                 .event_handler
@@ -286,8 +256,7 @@ impl GuildEventHandler for ReciprocityGuild {
 
             //Run the search message for determining a track
             let search_message_res =
-                SearchMessage::search(songs, requester, message.content, shard, self.0.clone())
-                    .await;
+                SearchMessage::search(songs.tracks, requester, query, shard, self.0.clone()).await;
             match search_message_res {
                 Ok(track) => vec![track],
                 Err(e) => {
@@ -295,26 +264,289 @@ impl GuildEventHandler for ReciprocityGuild {
                     return;
                 }
             }
+        } else if songs.load_type.eq(PLAYLIST_LOADED) {
+            songs.tracks
         } else {
-            songs
+            songs.tracks.into_iter().take(1).collect()
         };
 
-        let enqueue_res = self
-            .0
-            .player_manager
-            .request(PlayerRequest::Enqueue {
-                0: tracks,
-                1: voice_channel,
-            })
-            .await;
+        let enqueue_res = self.0.player_manager.enqueue(voice_channel, tracks).await;
         match enqueue_res {
-            Ok(_) => {}
+            //Let the User know where the Tracks landed and if not everything made it
+            Ok(enqueued) => {
+                self.0.player_manager.charge_enqueue_cooldown(requester.id);
+                tokio::spawn(NoticeMessage::show(enqueued.to_string(), self.0.clone()));
+            }
             Err(e) => {
                 warn!("Error enqueuing song: {:?}", e);
+                if let Some(text) = e.user_message() {
+                    tokio::spawn(NoticeMessage::show(text, self.0.clone()));
+                }
+            }
+        }
+    }
+
+    ///Runs a Slash Command for the User and returns the Reply
+    /// - Play only starts the Search, Discord wants a Reply within 3 Seconds
+    async fn slash_command(&self, command: SlashCommand, user: User) -> String {
+        let voice_channel = match self
+            .0
+            .bots
+            .get_user_voice_state(&user.id, &self.0.id)
+            .await
+            .and_then(|vs| vs.channel_id)
+        {
+            None => return "Join a Voice Channel first".to_string(),
+            Some(channel) => channel,
+        };
+
+        if let Some(permission) = command.permission() {
+            if !self.0.player_manager.is_allowed(&user.id, permission).await {
+                return "Missing DJ Role".to_string();
+            }
+        }
+
+        let (request, done) = match command {
+            SlashCommand::Play(query) => {
+                let reply = format!("Searching for '{}'", query);
+                let guild = self.clone();
+                tokio::spawn(async move { guild.play(user, query, false).await });
+                return reply;
+            }
+            //Vote Skip like the Control
+            SlashCommand::Skip() => {
+                let skip_res = self
+                    .0
+                    .player_manager
+                    .user_skip(voice_channel, user.id, 1)
+                    .await;
+                if let Err(e) = skip_res {
+                    return slash_error(&self.0.id, &user, e);
+                }
+                let state = self.0.player_manager.get_player_state(&voice_channel).await;
+                return match state.as_ref().and_then(|s| s.skip_votes.as_ref()) {
+                    Some(votes) => format!(
+                        "Voted to skip, {} of {} Votes",
+                        votes.users.len(),
+                        votes.required
+                    ),
+                    None => "Skipped".to_string(),
+                };
+            }
+            //The first Page, the Queue Control shows every Page
+            SlashCommand::Queue() => {
+                return QueueMessage::content(&self.0, &voice_channel, 0).await.0;
+            }
+            SlashCommand::Pause() => {
+                let request = PlayerRequest::PauseResume(voice_channel);
+                if let Err(e) = self.0.player_manager.request(request).await {
+                    return slash_error(&self.0.id, &user, e);
+                }
+                let state = self.0.player_manager.get_player_state(&voice_channel).await;
+                return match state.map(|s| s.play_state) {
+                    Some(PlayState::Pause) => "Paused".to_string(),
+                    _ => "Resumed".to_string(),
+                };
+            }
+            SlashCommand::Loop(playback) => (
+                PlayerRequest::SetPlayback(playback, voice_channel),
+                "Loop Mode set".to_string(),
+            ),
+            SlashCommand::Volume(volume) => (
+                PlayerRequest::Volume(volume, voice_channel),
+                format!("Volume set to {}%", volume),
+            ),
+            SlashCommand::Seek(position) => (
+                PlayerRequest::Jump(position, voice_channel),
+                format!("Jumped to {}s", position.as_secs()),
+            ),
+        };
+        match self.0.player_manager.request(request).await {
+            Ok(_) => done,
+            Err(e) => slash_error(&self.0.id, &user, e),
+        }
+    }
+
+    ///Splits a Text Command into its lowercase Name and the Arguments
+    /// - None, if Commands are disabled or the Message is no Command
+    fn command(&self, content: &str) -> Option<(String, String)> {
+        let prefix = self.0.guild_config.command_prefix.as_deref()?;
+        let command = content.trim().strip_prefix(prefix)?;
+        let mut parts = command.splitn(2, char::is_whitespace);
+        let name = parts.next().filter(|n| !n.is_empty())?.to_lowercase();
+        let args = parts.next().unwrap_or_default().trim().to_string();
+        Some((name, args))
+    }
+
+    ///Deletes a Message of the Channel with small delay
+    fn delete_later(&self, message: MessageId) {
+        let channel = self.0.channel;
+        let delete_pool = self.0.delete_pool.clone();
+        let scheduler = self.0.scheduler.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            delete_pool.lock().await.push(message);
+            scheduler
+                .process_enqueue(DeleteMessagePoolTask {
+                    channel,
+                    pool: delete_pool,
+                })
+                .await
+                .ok();
+        });
+    }
+
+    //Delete every irrelevant message
+    async fn clear_messages(ctx: Context) {
+        info!("Starting clear_messages in {:?}", ctx.id);
+        let bot = ctx.bots.get_any_guild_bot(&ctx.id).await;
+        let bot = match bot {
+            None => {
+                warn!("Could not get a Bot for Guild: {:?}", ctx.id);
                 return;
             }
+            Some(bot) => bot,
+        };
+
+        let mut msgs = ctx.channel.messages(bot.http(), |b| b.limit(100)).await;
+        loop {
+            let last = if let Ok(mut msgs) = msgs {
+                let last = msgs.last().cloned();
+                let s = ctx
+                    .main_message
+                    .read()
+                    .await
+                    .as_ref()
+                    .map(|(msg, _)| msg.message_id());
+                let msgs = msgs.drain(..).filter(|m| !Some(m.id).eq(&s));
+                let searches: Vec<_> = ctx.search_messages.read().await.values().copied().collect();
+                let msgs: Vec<_> = msgs
+                    .filter(|m| {
+                        !searches
+                            .iter()
+                            .any(|(msg, _)| msg.map(|msg| m.id.eq(&msg)).unwrap_or(false))
+                    })
+                    .collect();
+                if msgs.is_empty() {
+                    return;
+                }
+                let mut msgs: Vec<_> = msgs.iter().map(|m| m.id).collect();
+
+                info!(
+                    "Attempting to delete {} Messages in Guild: {:?}",
+                    msgs.len(),
+                    ctx.id
+                );
+                ctx.delete_pool.lock().await.append(&mut msgs);
+                ctx.scheduler
+                    .process_enqueue(DeleteMessagePoolTask {
+                        channel: ctx.channel,
+                        pool: ctx.delete_pool.clone(),
+                    })
+                    .await
+                    .ok();
+                last.unwrap()
+            } else {
+                return;
+            };
+            msgs = ctx
+                .channel
+                .messages(bot.http(), |b| b.limit(100).after(last))
+                .await;
         }
     }
+}
+
+///Attachments are only handed to Lavalink, if they look like Audio
+fn is_audio_attachment(attachment: &Attachment) -> bool {
+    if let Some(content_type) = &attachment.content_type {
+        if content_type.starts_with("audio/") {
+            return true;
+        }
+    }
+    attachment
+        .filename
+        .rsplit('.')
+        .next()
+        .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+///Reply to a failed Slash Command, only Errors the Users can fix are explained
+fn slash_error(guild: &GuildId, user: &User, e: PlayerMapError) -> String {
+    warn!("Slash Command Error. {:?}, {:?}, {:?}", guild, user.id, e);
+    e.user_message()
+        .unwrap_or_else(|| "Something went wrong".to_string())
+}
+
+fn undecodable_attachment() -> String {
+    PlayerError::SearchFailed("Attachment could not be decoded".to_string()).to_string()
+}
+
+#[derive(Debug, Error)]
+pub enum ReciprocityGuildError {
+    #[error("Guild was not found in config: {0:?}")]
+    GuildNotInConfig(GuildId),
+    //#[error("PlayerMap Error occurred: {0:?}")]
+    //PlayerMap(PlayerMapError),
+}
+
+#[async_trait]
+impl GuildEventHandler for ReciprocityGuild {
+    async fn new_message(&self, message: Message) {
+        //Ignore if the channel is wrong or the message was send by our bots
+        if !self.0.channel.eq(&message.channel_id) || self.0.bots.contains_id(&message.author.id) {
+            return;
+        }
+        info!("Received Message: {}, {}", message.id, message.author.id);
+
+        //Text Commands, "play" goes on like any other Query
+        let mut content = message.content.clone();
+        if let Some((name, args)) = self.command(&message.content) {
+            if name != "play" {
+                self.delete_later(message.id);
+                match (name.as_str(), EmoteAction::from_command(&name)) {
+                    ("pause", _) => self.pause_command(true, message.author.id).await,
+                    ("resume", _) => self.pause_command(false, message.author.id).await,
+                    (_, Some(action)) => self.main_message_event(action, message.author.id).await,
+                    (_, None) => {
+                        info!("Unknown Command. {:?}, {:?}", self.0.id, name);
+                        tokio::spawn(NoticeMessage::show(
+                            format!("Unknown Command: {}", name),
+                            self.0.clone(),
+                        ));
+                    }
+                }
+                return;
+            }
+            content = args;
+        }
+
+        //An attached Audio File is played instead of searching for the content
+        let attached = !message.attachments.is_empty();
+        let query = if !attached {
+            content
+        } else {
+            match message.attachments.iter().find(|a| is_audio_attachment(a)) {
+                Some(attachment) => attachment.url.clone(),
+                None => {
+                    warn!("No Audio Attachment in Message: {}", message.id);
+                    tokio::spawn(NoticeMessage::show(
+                        format!(
+                            "Unsupported Attachment, use one of: {}",
+                            AUDIO_EXTENSIONS.join(", ")
+                        ),
+                        self.0.clone(),
+                    ));
+                    return;
+                }
+            }
+        };
+
+        self.delete_later(message.id);
+
+        self.play(message.author, query, attached).await;
+    }
 
     async fn deleted_message(&self, channel: ChannelId, message: MessageId) {
         //Exit if channel is irrelevant
@@ -416,23 +648,30 @@ impl GuildEventHandler for ReciprocityGuild {
         //Branch if moved user is our bot
         match self.0.bots.contains_id(&new_voice_state.user_id) {
             true => {
-                //Only do something if the new_voice_state is a channel
-                if new_voice_state.channel_id.is_some() {
-                    //And if the old voice state is not non
-                    if let Some(old_vc) = old_voice_state {
-                        if let Some(old_ch) = old_vc.channel_id {
-                            info!(
-                                "Bot was moved. {:?}, Old: {:?}, New: {:?}, Bot: {:?}",
-                                self.0.id, old_ch, new_voice_state, &new_voice_state.user_id
-                            );
-                            if let Err(e) = self.0.player_manager.leave(old_ch).await {
-                                warn!(
-                                    "Error leaving. {:?}, {:?}, {:?}",
-                                    self.0.id, voice_channel, e
-                                );
-                            }
-                        }
-                    }
+                //Mute and Deafen Updates keep the Channel
+                if new_voice_state.channel_id.eq(&Some(voice_channel)) {
+                    return;
+                }
+                //Our own Leave already removed the Player, so only Moves and Kicks are left
+                let player_bot = self
+                    .0
+                    .player_manager
+                    .get_player(&voice_channel)
+                    .await
+                    .map(|(bot, _)| bot);
+                if !player_bot.eq(&Some(new_voice_state.user_id)) {
+                    return;
+                }
+                info!(
+                    "Bot was moved or kicked. {:?}, Old: {:?}, New: {:?}, Bot: {:?}",
+                    self.0.id, voice_channel, new_voice_state.channel_id, &new_voice_state.user_id
+                );
+                //Keep the Queue, so the next Join continues where the Bot was interrupted
+                if let Err(e) = self.0.player_manager.leave_keep_queue(voice_channel).await {
+                    warn!(
+                        "Error leaving. {:?}, {:?}, {:?}",
+                        self.0.id, voice_channel, e
+                    );
                 }
             }
             false => {
@@ -496,19 +735,27 @@ impl GuildEventHandler for ReciprocityGuild {
 
     async fn lavalink(&self, event: LavalinkEvent, client: LavalinkClient) {
         debug!("Lavaplayer Event: {:?}", &event);
+        let started = match &event {
+            LavalinkEvent::Start(start) if self.0.guild_config.now_playing.is_some() => {
+                Some(start.track.clone())
+            }
+            _ => None,
+        };
         self.0
             .player_manager
             .handle_player_event(event, client)
             .await
             .ok();
-    }
 
-    async fn main_message_event(&self, event: EmoteAction, user: UserId) {
-        info!(
-            "Main Message Event occurred. {:?}, {:?}, {:?}",
-            self.0.id, user, event
-        );
+        //Announce in the background, so playback is never blocked
+        if let Some(track) = started {
+            tokio::spawn(NowPlayingMessage::announce(track, self.0.clone()));
+        }
+    }
 
+    ///Voice Channel of the User for a Control
+    /// - None, if the User is not in a Voice Channel or is missing the DJ Role
+    async fn control_channel(&self, event: &EmoteAction, user: UserId) -> Option<ChannelId> {
         //Ignore if User is not in a voice channel
         let voice_channel = match self.0.bots.get_user_voice_state(&user, &self.0.id).await {
             None => {
@@ -516,7 +763,7 @@ impl GuildEventHandler for ReciprocityGuild {
                     "Ignoring Event because User is not in Voice Channel. {:?}. {:?}",
                     self.0.id, user
                 );
-                return;
+                return None;
             }
             Some(st) => match st.channel_id {
                 None => {
@@ -524,17 +771,75 @@ impl GuildEventHandler for ReciprocityGuild {
                         "Ignoring Event because User is not in Voice Channel. {:?}. {:?}",
                         self.0.id, user
                     );
-                    return;
+                    return None;
                 }
                 Some(ch) => ch,
             },
         };
 
+        //Ignore if User is missing the DJ Role
+        if let Some(permission) = event.permission() {
+            if !self.0.player_manager.is_allowed(&user, permission).await {
+                info!(
+                    "Ignoring Event because User is missing the DJ Role. {:?}, {:?}, {:?}",
+                    self.0.id, user, event
+                );
+                return None;
+            }
+        }
+        Some(voice_channel)
+    }
+
+    ///Pauses or resumes for a Text Command, unlike the Control it never toggles
+    async fn pause_command(&self, pause: bool, user: UserId) {
+        let voice_channel = match self.control_channel(&EmoteAction::PlayPause(), user).await {
+            None => return,
+            Some(channel) => channel,
+        };
+        let request = if pause {
+            PlayerRequest::Pause(voice_channel)
+        } else {
+            PlayerRequest::Resume(voice_channel)
+        };
+        if let Err(e) = self.0.player_manager.request(request).await {
+            warn!(
+                "Error Handling User Request. {:?}, {:?}, {:?}",
+                self.0.id, user, e
+            );
+        }
+    }
+
+    async fn main_message_event(&self, event: EmoteAction, user: UserId) {
+        info!(
+            "Main Message Event occurred. {:?}, {:?}, {:?}",
+            self.0.id, user, event
+        );
+
+        let voice_channel = match self.control_channel(&event, user).await {
+            None => return,
+            Some(channel) => channel,
+        };
+
         //Build Request
         let request = match event {
+            //The Separator is only visual
+            EmoteAction::Nothing() => return,
             EmoteAction::PlayPause() => PlayerRequest::PauseResume(voice_channel),
-            EmoteAction::Next() => PlayerRequest::Skip(1, voice_channel),
-            EmoteAction::Prev() => PlayerRequest::BackSkip(1, voice_channel),
+            EmoteAction::Next() => {
+                let skip_res = self
+                    .0
+                    .player_manager
+                    .user_skip(voice_channel, user, 1)
+                    .await;
+                if let Err(e) = skip_res {
+                    warn!(
+                        "Error Handling User Request. {:?}, {:?}, {:?}, {:?}",
+                        self.0.id, user, event, e
+                    );
+                }
+                return;
+            }
+            EmoteAction::Prev() => PlayerRequest::Previous(voice_channel),
             EmoteAction::Join() => {
                 let join_res = self.0.player_manager.join(voice_channel).await;
                 match join_res {
@@ -550,6 +855,9 @@ impl GuildEventHandler for ReciprocityGuild {
                             "Error joining Voice Channel. {:?}, {:?}, {:?}, {:?}",
                             self.0.id, voice_channel, user, e
                         );
+                        if let Some(text) = e.user_message() {
+                            tokio::spawn(NoticeMessage::show(text, self.0.clone()));
+                        }
                         return;
                     }
                 }
@@ -573,9 +881,50 @@ impl GuildEventHandler for ReciprocityGuild {
                     }
                 }
             }
-            EmoteAction::Delete() => PlayerRequest::ClearQueue(voice_channel),
+            EmoteAction::Queue() => {
+                let context = self.0.clone();
+                tokio::spawn(async move {
+                    let guild = context.id;
+                    if let Err(e) = QueueMessage::show(voice_channel, user, context).await {
+                        warn!(
+                            "Queue Message Error occurred. {:?}, {:?}, {:?}",
+                            guild, user, e
+                        );
+                    }
+                });
+                return;
+            }
+            EmoteAction::Lyrics() => {
+                tokio::spawn(LyricsMessage::show(voice_channel, self.0.clone()));
+                return;
+            }
+            EmoteAction::Delete() | EmoteAction::Clear() => {
+                //Only clear on a second Clear of the same User, to avoid accidental wipes
+                let mut lock = self.0.clear_request.lock().await;
+                match lock.take() {
+                    Some((requester, when))
+                        if requester.eq(&user) && when.elapsed() < CLEAR_CONFIRM_TIMEOUT =>
+                    {
+                        PlayerRequest::ClearQueue(voice_channel)
+                    }
+                    _ => {
+                        *lock = Some((user, Instant::now()));
+                        let text = format!(
+                            "Press {} again within {} Seconds to clear the Queue",
+                            event.unicode(),
+                            CLEAR_CONFIRM_TIMEOUT.as_secs()
+                        );
+                        tokio::spawn(NoticeMessage::show(text, self.0.clone()));
+                        return;
+                    }
+                }
+            }
             EmoteAction::LoopOne() => PlayerRequest::Playback(Playback::OneLoop, voice_channel),
             EmoteAction::LoopAll() => PlayerRequest::Playback(Playback::AllLoop, voice_channel),
+            EmoteAction::Shuffle() => PlayerRequest::Shuffle(voice_channel),
+            EmoteAction::BassBoost() => {
+                PlayerRequest::ToggleFilter(AudioFilter::BassBoost, voice_channel)
+            }
             _ => {
                 info!(
                     "Received unexpected Event. {:?}, {:?}, {:?}",
@@ -655,8 +1004,116 @@ impl GuildEventHandler for ReciprocityGuild {
         }
     }
 
+    async fn component_interaction(&self, interaction: MessageComponentInteraction) {
+        //Exit if channel is irrelevant
+        if interaction.channel_id != self.0.channel {
+            return;
+        }
+
+        let message = match &interaction.message {
+            InteractionMessage::Regular(msg) => msg.id,
+            InteractionMessage::Ephemeral(msg) => msg.id,
+        };
+        let is_main_message = self
+            .0
+            .main_message
+            .read()
+            .await
+            .as_ref()
+            .map(|(msg, _)| msg.message_id().eq(&message))
+            .unwrap_or(false);
+        if !is_main_message {
+            //Pass on to the Search Message, if it belongs to one
+            let search = self
+                .0
+                .search_interactions
+                .read()
+                .await
+                .get(&message)
+                .cloned();
+            if let Some(search) = search {
+                search
+                    .send((interaction.user.id, interaction.data.values.clone()))
+                    .await
+                    .ok();
+            }
+            return;
+        }
+
+        //Custom Ids are the unicode of the Emote
+        match EmoteAction::try_from(interaction.data.custom_id.as_str()) {
+            Ok(action) => self.main_message_event(action, interaction.user.id).await,
+            Err(_) => warn!(
+                "Received unknown Component Id. {:?}, {:?}",
+                self.0.id, interaction.data.custom_id
+            ),
+        }
+    }
+
+    async fn command_interaction(&self, interaction: ApplicationCommandInteraction, bot: UserId) {
+        let bot = match self.0.bots.get_bot_by_id(bot) {
+            None => return,
+            Some(bot) => bot,
+        };
+        info!(
+            "Slash Command received. {:?}, {:?}, {:?}",
+            self.0.id, interaction.user.id, interaction.data.name
+        );
+
+        //Commands may still be registered, until the next Start cleared them
+        let reply = if !self.0.guild_config.slash_commands {
+            "Slash Commands are disabled".to_string()
+        } else {
+            match SlashCommand::parse(&interaction) {
+                Ok(command) => self.slash_command(command, interaction.user.clone()).await,
+                Err(reply) => reply,
+            }
+        };
+        let res = interaction
+            .create_interaction_response(bot.http(), |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content(reply)
+                            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                    })
+            })
+            .await;
+        if let Err(e) = res {
+            warn!("Error replying to Slash Command. {:?}, {:?}", self.0.id, e);
+        }
+    }
+
     async fn cache_ready(&self, bot: UserId) {
         info!("Cache Ready. {:?}, Bot: {:?}", self.0.id, bot);
+        //Only the Bot with the lowest Id in the Guild registers,
+        // otherwise every Command shows up once per Bot
+        let lowest = self
+            .0
+            .bots
+            .guild_bot_ids(&self.0.id)
+            .await
+            .into_iter()
+            .min();
+        if lowest == Some(bot) {
+            if let Some(bot) = self.0.bots.get_bot_by_id(bot) {
+                let guild = self.0.id;
+                let enabled = self.0.guild_config.slash_commands;
+                tokio::spawn(async move {
+                    //Commands of a previous Start are removed, once disabled
+                    if !enabled {
+                        if let Err(e) = SlashCommand::clear(bot.http(), guild).await {
+                            warn!("Error clearing Slash Commands. {:?}, {:?}", guild, e);
+                        }
+                        return;
+                    }
+                    match SlashCommand::register(bot.http(), guild).await {
+                        Ok(_) => info!("Registered Slash Commands. {:?}", guild),
+                        Err(e) => warn!("Error registering Slash Commands. {:?}, {:?}", guild, e),
+                    }
+                });
+            }
+        }
+        tokio::spawn(self.0.player_manager.clone().restore());
         tokio::spawn(Self::clear_messages(self.0.clone()));
         let cloned_guild = self.clone();
         tokio::spawn(async move { cloned_guild.check_main_message().await });
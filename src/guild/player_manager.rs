@@ -1,23 +1,45 @@
 use crate::bots::BotMap;
+use crate::config::GuildConfig;
 use crate::lavalink_handler::LavalinkEvent;
+use crate::lyrics::{LyricsError, LyricsProvider};
 use crate::multi_key_map::{HashArc, TripleHashMap};
-use crate::player::{Playback, Player, PlayerError, PlayerState};
-use lavalink_rs::model::Track;
+use crate::node_pool::NodePool;
+use crate::persistence::{PersistenceError, QueueStore, StoredPlayer, StoredTrack};
+use crate::player::{
+    AudioFilter, Enqueued, Playback, Player, PlayerError, PlayerState, PLAYLIST_LOADED,
+    TRACK_LOADED,
+};
+use crate::query::{self, QueryError};
+use crate::spotify::{SpotifyResolver, SpotifyUrl};
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use lavalink_rs::model::{Track, Tracks};
 use lavalink_rs::{LavalinkClient, LavalinkClientInner};
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use rand::prelude::SliceRandom;
-use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::model::prelude::{ChannelId, GuildId, Permissions, RoleId, UserId};
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
+use std::future::Future;
 use std::ops::DerefMut;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use strum_macros::AsRefStr;
 use thiserror::Error;
-use tokio::sync::oneshot::error::RecvError;
 use tokio::sync::watch::Receiver as WatchReceiver;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::watch::Sender as WatchSender;
+use tokio::sync::{Mutex, Notify, RwLock};
 
 pub type PlayerStates = Vec<WatchReceiver<Arc<PlayerState>>>;
+
+const PERSIST_DEBOUNCE: Duration = Duration::from_secs(10);
+///Longest the Queues go unsaved after a change, Position updates keep coming while playing
+const PERSIST_MAX_DELAY: Duration = Duration::from_secs(60);
+///Stored Tracks resolved through Lavalink at once
+const RESOLVE_CONCURRENCY: usize = 8;
+const RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
 pub type PlayerMapType = TripleHashMap<
     UserId,
     HashArc<Mutex<LavalinkClientInner>>,
@@ -28,17 +50,62 @@ pub type PlayerMapType = TripleHashMap<
 #[derive(Clone)]
 pub struct PlayerManager {
     guild: GuildId,
+    config: GuildConfig,
     bots: Arc<BotMap>,
     player_states: Arc<RwLock<PlayerStates>>,
+    players_change: Arc<WatchSender<()>>,
+    players_watch: WatchReceiver<()>,
     player: Arc<RwLock<PlayerMapType>>,
-    lavalink: Arc<HashMap<UserId, LavalinkClient>>,
+    lavalink: Arc<HashMap<UserId, Arc<NodePool>>>,
+    spotify: Option<Arc<SpotifyResolver>>,
+    lyrics: Option<Arc<LyricsProvider>>,
+    store: Option<Arc<QueueStore>>,
+    restore_started: Arc<AtomicBool>,
+    restore_done: Arc<Notify>,
+    ///Last Enqueue of every User, for the Enqueue Cooldown
+    last_enqueues: Arc<std::sync::Mutex<Cooldowns>>,
+    ///States of Players that left without clearing their Queue, continued on the next Join
+    kept_queues: Arc<std::sync::Mutex<HashMap<ChannelId, PlayerState>>>,
+    node_status: Arc<WatchSender<NodeStatus>>,
+    node_status_watch: WatchReceiver<NodeStatus>,
+}
+
+///Health of the Lavalink Connection, as seen by the Players of the Guild
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStatus {
+    ///Whether any Lavalink Node of the Bots is connected
+    pub connected: bool,
+    ///Set while a Player is being reconnected
+    pub reconnecting: bool,
+    pub last_error: Option<String>,
+}
+
+impl NodeStatus {
+    ///Health of several Guilds together, connected if any of them is
+    pub fn combine(statuses: impl IntoIterator<Item = NodeStatus>) -> NodeStatus {
+        let mut combined = NodeStatus {
+            connected: false,
+            reconnecting: false,
+            last_error: None,
+        };
+        for status in statuses {
+            combined.connected |= status.connected;
+            combined.reconnecting |= status.reconnecting;
+            combined.last_error = combined.last_error.or(status.last_error);
+        }
+        combined
+    }
 }
 
 impl PlayerManager {
     pub fn new(
         guild: GuildId,
+        config: GuildConfig,
         bots: Arc<BotMap>,
-        lavalink: Arc<HashMap<UserId, LavalinkClient>>,
+        lavalink: Arc<HashMap<UserId, Arc<NodePool>>>,
+        spotify: Option<Arc<SpotifyResolver>>,
+        lyrics: Option<Arc<LyricsProvider>>,
+        store: Option<Arc<QueueStore>>,
     ) -> Self {
         let mut player = TripleHashMap::new();
         for bot in bots.ids() {
@@ -46,16 +113,60 @@ impl PlayerManager {
         }
         let player = Arc::new(RwLock::new(player));
         let player_states = Arc::new(RwLock::new(Vec::new()));
+        let (players_change, players_watch) = tokio::sync::watch::channel(());
+        let (node_status, node_status_watch) = tokio::sync::watch::channel(NodeStatus {
+            connected: lavalink.values().any(|pool| pool.is_connected()),
+            reconnecting: false,
+            last_error: None,
+        });
 
         PlayerManager {
             guild,
+            config,
             bots,
             player,
             lavalink,
+            spotify,
+            lyrics,
             player_states,
+            players_change: Arc::new(players_change),
+            players_watch,
+            store,
+            restore_started: Arc::new(AtomicBool::new(false)),
+            restore_done: Arc::new(Notify::new()),
+            last_enqueues: Arc::new(std::sync::Mutex::new(Cooldowns::default())),
+            kept_queues: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            node_status: Arc::new(node_status),
+            node_status_watch,
+        }
+    }
+
+    ///Watch of the Lavalink Connection Health
+    pub fn node_status_watch(&self) -> WatchReceiver<NodeStatus> {
+        self.node_status_watch.clone()
+    }
+
+    ///Announces the Health of the Lavalink Connection, if it changed
+    /// - Connected is taken from the Nodes, the last Error is kept until the next one
+    fn set_node_status(&self, reconnecting: bool, error: Option<String>) {
+        let last_error = error.or_else(|| self.node_status_watch.borrow().last_error.clone());
+        let status = NodeStatus {
+            connected: self.lavalink.values().any(|pool| pool.is_connected()),
+            reconnecting,
+            last_error,
+        };
+        let changed = !status.eq(&self.node_status_watch.borrow());
+        if changed {
+            self.node_status.send(status).ok();
         }
     }
 
+    ///Watch that changes after a Player was added or removed
+    /// - Changes of a single Player are observed with its Status Watch
+    pub fn players_watch(&self) -> WatchReceiver<()> {
+        self.players_watch.clone()
+    }
+
     pub async fn bot_in_channel(&self, channel: &ChannelId) -> bool {
         self.player.read().await.contains_k2(channel)
     }
@@ -70,45 +181,485 @@ impl PlayerManager {
             .map(|(bot, player)| (*bot, player.clone()));
         let (_, player) =
             player.ok_or_else(|| PlayerMapError::NoPlayerFound(request.get_channel()))?;
+        let channel = request.get_channel();
         let mut player_lock = player.write().await;
         let player = player_lock
             .deref_mut()
             .as_mut()
-            .ok_or_else(|| PlayerMapError::NoPlayerFound(request.get_channel()))?;
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
 
-        match request {
+        let result = match request {
             PlayerRequest::Skip(i, _) => player.skip(i).await.map_err(PlayerMapError::PlayerError),
+            PlayerRequest::VoteSkip(user, required, _) => player
+                .vote_skip(user, required)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::SkipTo(index, _) => player
+                .skip_to(index)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Previous(_) => {
+                player.previous().await.map_err(PlayerMapError::PlayerError)
+            }
             PlayerRequest::BackSkip(i, _) => player
                 .back_skip(i)
                 .await
                 .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::PlayNext(index, _) => player
+                .play_next_track(index)
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::ReplayLast(_) => player
+                .replay_last()
+                .await
+                .map_err(PlayerMapError::PlayerError),
             PlayerRequest::ClearQueue(_) => {
                 player.clear_queue();
                 return Ok(());
             }
+            PlayerRequest::ClearHistory(_) => {
+                player.clear_history();
+                return Ok(());
+            }
             PlayerRequest::Playback(playback, _) => {
                 player.playback(playback);
                 return Ok(());
             }
+            PlayerRequest::SetPlayback(playback, _) => {
+                player.set_playback(playback);
+                return Ok(());
+            }
+            PlayerRequest::Shuffle(_) => {
+                player.toggle_shuffle();
+                return Ok(());
+            }
+            PlayerRequest::SetShuffle(shuffle, _) => {
+                player.set_shuffle(shuffle);
+                return Ok(());
+            }
+            PlayerRequest::Pause(_) => player.pause().await.map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Resume(_) => player.resume().await.map_err(PlayerMapError::PlayerError),
             PlayerRequest::PauseResume(_) => player
                 .dynamic_pause_resume()
                 .await
                 .map_err(PlayerMapError::PlayerError),
-            PlayerRequest::Enqueue(mut tracks, _) => player
-                .enqueue(tracks.drain(..))
-                .await
-                .map_err(PlayerMapError::PlayerError),
             PlayerRequest::Jump(pos, _) => {
                 player.jump(pos).await.map_err(PlayerMapError::PlayerError)
             }
+            PlayerRequest::JumpPercent(pct, _) => player
+                .jump_percent(pct)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::JumpFraction(fraction, _) => player
+                .jump_fraction(fraction)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Filter(Some(filter), _) => player
+                .set_filter(filter)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Filter(None, _) => player
+                .clear_filter()
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::ToggleFilter(filter, _) => player
+                .toggle_filter(filter)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Speed(speed, _) => player
+                .set_speed(speed)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Pitch(pitch, _) => player
+                .set_pitch(pitch)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+            PlayerRequest::Volume(volume, _) => player
+                .set_volume(volume)
+                .await
+                .map_err(PlayerMapError::PlayerError),
+        };
+        drop(player_lock);
+        self.supervise(channel, result).await
+    }
+
+    ///Recovers the Player, if the Result is a fatal Error
+    async fn supervise<T>(
+        &self,
+        channel: ChannelId,
+        result: Result<T, PlayerMapError>,
+    ) -> Result<T, PlayerMapError> {
+        if let Err(e) = &result {
+            if e.is_fatal() {
+                warn!(
+                    "Fatal Player Error, reconnecting. {:?}, {:?}, {:?}",
+                    self.guild, channel, e
+                );
+                self.node_closed(channel).await;
+                self.set_node_status(true, Some(e.to_string()));
+                self.reconnect(channel).await;
+            }
+        }
+        result
+    }
+
+    ///Re-establishes the Lavalink Session of a Player and resumes the current Track
+    /// - Retries with exponential backoff, the Queue is kept
+    /// - Resets the State and leaves the Channel when giving up
+    async fn reconnect(&self, channel: ChannelId) {
+        let result = retry_reconnect(RECONNECT_ATTEMPTS, RECONNECT_BACKOFF, |attempt| {
+            self.reconnect_attempt(channel, attempt)
+        })
+        .await;
+        match result {
+            Ok(attempt) => {
+                info!(
+                    "Reconnected Player. {:?}, {:?}, Attempt: {}",
+                    self.guild, channel, attempt
+                );
+                return self.set_node_status(false, None);
+            }
+            //The Player is already gone, so there is nothing left to reconnect
+            Err(PlayerMapError::NoPlayerFound(_)) => return self.set_node_status(false, None),
+            Err(_) => {}
+        }
+
+        error!("Giving up Reconnect. {:?}, {:?}", self.guild, channel);
+        self.set_node_status(false, None);
+        if let Some((_, player)) = self.get_player(&channel).await {
+            if let Some(player) = player.write().await.as_mut() {
+                player.reset();
+            }
+        }
+        if let Err(e) = self.leave(channel).await {
+            warn!(
+                "Error leaving after failed Reconnect. {:?}, {:?}, {:?}",
+                self.guild, channel, e
+            );
+        }
+    }
+
+    ///Creates a new Lavalink Session for the Player and continues the current Track
+    /// - Moves the Player to another Node, if the Attempt failed
+    async fn reconnect_attempt(
+        &self,
+        channel: ChannelId,
+        attempt: u32,
+    ) -> Result<(), PlayerMapError> {
+        let res = match self.renew_lavalink(channel).await {
+            Err(e) => Err(e),
+            Ok(player) => match player.write().await.as_mut() {
+                None => Err(PlayerMapError::NoPlayerFound(channel)),
+                Some(player) => player
+                    .reconnect()
+                    .await
+                    .map_err(PlayerMapError::PlayerError),
+            },
+        };
+        match &res {
+            Ok(_) | Err(PlayerMapError::NoPlayerFound(_)) => {}
+            Err(e) => {
+                warn!(
+                    "Reconnect Attempt failed. {:?}, {:?}, Attempt: {}, {:?}",
+                    self.guild, channel, attempt, e
+                );
+                self.fail_over(channel).await;
+                self.set_node_status(true, Some(e.to_string()));
+            }
+        }
+        res
+    }
+
+    ///Gives the Player of the Channel a connected Lavalink Client
+    /// - Sessions die with the Websocket of their Client, so a closed Client is replaced first
+    async fn renew_lavalink(
+        &self,
+        channel: ChannelId,
+    ) -> Result<Arc<RwLock<Option<Player>>>, PlayerMapError> {
+        let (bot, player) = self
+            .get_player(&channel)
+            .await
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        let pool = self
+            .lavalink
+            .get(&bot)
+            .ok_or(PlayerMapError::NoLavalink(bot))?;
+        let current = player
+            .read()
+            .await
+            .as_ref()
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?
+            .get_lavalink();
+        let renewed = pool
+            .renew(&current)
+            .await
+            .map_err(|e| PlayerMapError::PlayerError(PlayerError::Lavalink(e)))?;
+        if Arc::ptr_eq(&renewed.inner, &current.inner) {
+            return Ok(player);
+        }
+
+        let mut map_lock = self.player.write().await;
+        if let Some(p) = player.write().await.as_mut() {
+            p.set_lavalink(renewed.clone());
+            map_lock.sub_k1_k2(&bot);
+            map_lock.add_k1_k2(bot, HashArc::from(renewed.inner), channel);
+        }
+        drop(map_lock);
+        Ok(player)
+    }
+
+    ///Marks the Lavalink Node of the Player in the Channel as disconnected
+    async fn node_closed(&self, channel: ChannelId) {
+        let (bot, player) = match self.get_player(&channel).await {
+            None => return,
+            Some(player) => player,
+        };
+        if let (Some(pool), Some(player)) = (self.lavalink.get(&bot), player.read().await.as_ref())
+        {
+            pool.closed(&player.get_lavalink());
+        }
+    }
+
+    ///Moves the Player of the Channel to another Lavalink Node of its Bot
+    /// - Does nothing, if the Bot only has a single Node
+    async fn fail_over(&self, channel: ChannelId) {
+        let mut map_lock = self.player.write().await;
+        let (bot, player) = match map_lock.get_k2(&channel) {
+            None => return,
+            Some((bot, player)) => (*bot, player.clone()),
+        };
+        let pool = match self.lavalink.get(&bot) {
+            Some(pool) if pool.len() > 1 => pool,
+            _ => return,
+        };
+        let mut player_lock = player.write().await;
+        let player = match player_lock.as_mut() {
+            None => return,
+            Some(player) => player,
+        };
+        let current = player.get_lavalink();
+        pool.failed(&current);
+        let next = match pool.assign(Some(&current)) {
+            None => return,
+            Some(next) => next,
+        };
+        pool.release(&current);
+        if let Err(e) = current.destroy(self.guild).await {
+            debug!(
+                "Could not destroy Player on failed Node. {:?}, {:?}, {:?}",
+                self.guild, channel, e
+            );
+        }
+        player.set_lavalink(next.clone());
+        map_lock.sub_k1_k2(&bot);
+        map_lock.add_k1_k2(bot, HashArc::from(next.inner), channel);
+        info!(
+            "Moved Player to another Lavalink Node. {:?}, {:?}",
+            self.guild, channel
+        );
+    }
+
+    ///Returns whether the User is allowed to use the Control
+    /// - Restricted Controls require the DJ Role
+    /// - Controls reaching beyond the own Channel always do, without a DJ Role nobody may use them
+    pub async fn is_allowed(&self, user: &UserId, permission: Permission) -> bool {
+        let dj = match &self.config.dj {
+            None => return !permission.always_restricted(),
+            Some(dj) => dj,
+        };
+        if !permission.always_restricted()
+            && !dj.restricted.iter().any(|r| r.eq(permission.as_ref()))
+        {
+            return true;
+        }
+        self.is_dj(user).await
+    }
+
+    ///Whether the User has the DJ Role, false if the Guild has none
+    async fn is_dj(&self, user: &UserId) -> bool {
+        match &self.config.dj {
+            None => false,
+            Some(dj) => {
+                self.bots
+                    .user_has_role(user, &self.guild, &RoleId(dj.role_id))
+                    .await
+            }
+        }
+    }
+
+    ///Skips i Tracks for a User
+    /// - Only registers a Vote for the current Track, if Vote Skip is enabled for the Guild
+    /// - DJs skip right away
+    pub async fn user_skip(
+        &self,
+        channel: ChannelId,
+        user: UserId,
+        i: usize,
+    ) -> Result<(), PlayerMapError> {
+        let vote = match self.config.vote_skip {
+            None => None,
+            Some(fraction) => Some((
+                fraction,
+                self.bots.listener_count(&channel, &self.guild).await,
+            )),
+        };
+        let dj = vote.is_some() && self.is_dj(&user).await;
+        self.request(skip_request(channel, user, i, vote, dj)).await
+    }
+
+    ///Checks the Enqueue Cooldown of the User, without starting it
+    /// - Fails with the remaining seconds, if the User enqueued too recently
+    /// - Users with the DJ Role are exempt
+    pub async fn enqueue_cooldown(&self, user: UserId) -> Result<(), PlayerMapError> {
+        let cooldown = match self.config.enqueue_cooldown {
+            None => return Ok(()),
+            Some(cooldown) => Duration::from_secs(cooldown),
+        };
+        if self.is_dj(&user).await {
+            return Ok(());
+        }
+
+        let remaining = self
+            .last_enqueues
+            .lock()
+            .expect("Enqueue Cooldown Lock was poisoned")
+            .remaining(&user, cooldown, Instant::now());
+        match remaining {
+            None => Ok(()),
+            Some(remaining) => Err(PlayerMapError::Cooldown(
+                remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0),
+            )),
+        }
+    }
+
+    ///Starts the Enqueue Cooldown of the User, after something was enqueued
+    pub fn charge_enqueue_cooldown(&self, user: UserId) {
+        if self.config.enqueue_cooldown.is_none() {
+            return;
+        }
+        self.last_enqueues
+            .lock()
+            .expect("Enqueue Cooldown Lock was poisoned")
+            .charge(user, Instant::now());
+    }
+
+    ///Enqueues Tracks for the Player in the Channel
+    /// - Returns how many Tracks were added, skipped as Duplicates and dropped
+    pub async fn enqueue(
+        &self,
+        channel: ChannelId,
+        mut tracks: Vec<Track>,
+    ) -> Result<Enqueued, PlayerMapError> {
+        info!(
+            "Handling Enqueue. {:?}, {:?}, Tracks: {}",
+            self.guild,
+            channel,
+            tracks.len()
+        );
+        //Tracks breaking the Length limits never reach the Playlist
+        let total = tracks.len();
+        let mut rejection = None;
+        tracks.retain(|track| match check_length(&self.config, track) {
+            Ok(_) => true,
+            Err(e) => {
+                rejection = Some(e);
+                false
+            }
+        });
+        let rejected = total - tracks.len();
+        if tracks.is_empty() {
+            if let Some(e) = rejection {
+                return Err(PlayerMapError::PlayerError(e));
+            }
         }
+
+        let (_, player) = self
+            .get_player(&channel)
+            .await
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        let mut player_lock = player.write().await;
+        let player = player_lock
+            .as_mut()
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+
+        let result = player
+            .enqueue(tracks.drain(..), self.config.no_duplicates)
+            .await
+            .map(|enqueued| Enqueued {
+                rejected,
+                ..enqueued
+            })
+            .map_err(PlayerMapError::PlayerError);
+        drop(player_lock);
+        self.supervise(channel, result).await
     }
 
+    ///Searches for Tracks, the load type tells a search apart from a Track or Playlist Url
+    /// - Spotify Urls are resolved into searches, if Spotify is configured
     pub async fn search(
         &self,
         channel: ChannelId,
         query: String,
-    ) -> Result<(UserId, Vec<Track>), PlayerMapError> {
+    ) -> Result<(UserId, Tracks), PlayerMapError> {
+        let query = query::normalize(&query).map_err(PlayerMapError::Query)?;
+        if let Some(spotify) = &self.spotify {
+            if let Some(url) = SpotifyUrl::parse(&query) {
+                return self.load_spotify(channel, spotify, url).await;
+            }
+        }
+        self.load_lavalink(channel, query).await
+    }
+
+    ///Resolves a Spotify Url and uses the first match of every resolved Track
+    async fn load_spotify(
+        &self,
+        channel: ChannelId,
+        spotify: &SpotifyResolver,
+        url: SpotifyUrl,
+    ) -> Result<(UserId, Tracks), PlayerMapError> {
+        let queries = spotify.resolve(&url).await.map_err(|e| {
+            PlayerMapError::PlayerError(PlayerError::SearchFailed(format!(
+                "Spotify lookup failed: {}",
+                e
+            )))
+        })?;
+
+        let results = futures::future::join_all(
+            queries
+                .into_iter()
+                .map(|query| self.load_lavalink(channel, query)),
+        )
+        .await;
+        let mut loaded: Option<(UserId, Tracks)> = None;
+        let mut tracks = Vec::new();
+        for (bot, mut result) in results.into_iter().filter_map(Result::ok) {
+            if let Some(track) = result.tracks.drain(..).next() {
+                tracks.push(track);
+            }
+            loaded.get_or_insert((bot, result));
+        }
+
+        match loaded {
+            Some((bot, mut result)) if !tracks.is_empty() => {
+                //The Spotify Url already determined the Track, so it is no search
+                result.load_type = if url.is_track() {
+                    TRACK_LOADED.to_string()
+                } else {
+                    PLAYLIST_LOADED.to_string()
+                };
+                result.tracks = tracks;
+                Ok((bot, result))
+            }
+            _ => Err(PlayerMapError::PlayerError(PlayerError::SearchFailed(
+                format!("No match for Spotify Url: {:?}", url),
+            ))),
+        }
+    }
+
+    async fn load_lavalink(
+        &self,
+        channel: ChannelId,
+        query: String,
+    ) -> Result<(UserId, Tracks), PlayerMapError> {
         let (bot, player) = self
             .player
             .read()
@@ -116,20 +667,47 @@ impl PlayerManager {
             .get_k2(&channel)
             .map(|(bot, player)| (*bot, player.clone()))
             .ok_or(PlayerMapError::NoPlayerFound(channel))?;
-        let (send, rev) = tokio::sync::oneshot::channel();
         let player_lock = player.read().await;
-        let player = player_lock
+        let search = player_lock
             .as_ref()
-            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
-        player.search(query, |res| async { if send.send(res).is_ok() {} });
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?
+            .search(query);
         drop(player_lock);
 
-        match rev.await {
-            Ok(res) => res
-                .map_err(PlayerMapError::PlayerError)
-                .map(|tracks| (bot, tracks)),
-            Err(rec_err) => Err(PlayerMapError::SearchSenderDropped(rec_err)),
-        }
+        search
+            .await
+            .map_err(PlayerMapError::PlayerError)
+            .map(|tracks| (bot, tracks))
+    }
+
+    ///Looks up the Lyrics for the current Track of the Player in the Channel
+    /// - Returns the Title together with the Lyrics
+    pub async fn lyrics(&self, channel: ChannelId) -> Result<(String, String), PlayerMapError> {
+        let provider = self
+            .lyrics
+            .as_ref()
+            .ok_or(PlayerMapError::Lyrics(LyricsError::NotConfigured()))?;
+        let (_, player) = self
+            .get_player(&channel)
+            .await
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        let info = player
+            .read()
+            .await
+            .as_ref()
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?
+            .get_status_watch()
+            .borrow()
+            .current
+            .as_ref()
+            .and_then(|(_, track)| track.info.clone())
+            .ok_or(PlayerMapError::PlayerError(PlayerError::NoCurrentSong()))?;
+
+        let lyrics = provider
+            .lyrics(&info.title, &info.author)
+            .await
+            .map_err(PlayerMapError::Lyrics)?;
+        Ok((info.title, lyrics))
     }
 
     pub async fn join(&self, channel: ChannelId) -> Result<(), PlayerMapError> {
@@ -138,6 +716,9 @@ impl PlayerManager {
         }
 
         info!("Handling Join Request. {:?}, {:?}", self.guild, channel);
+        let mut missing_permissions = None;
+        let mut last_error = None;
+        let mut busy = 0;
         let mut bot_vec = self
             .player
             .read()
@@ -149,19 +730,39 @@ impl PlayerManager {
 
         for (bot, player) in bot_vec {
             if player.read().await.is_none() {
-                let result = self.add_player(bot, channel).await;
-                match &result {
+                let result = self.join_with(bot, channel).await;
+                match result {
                     Ok(_) => return result,
-                    Err(e) => warn!(
-                        "Join Attempt Failed. {:?}, {:?}, {:?}",
-                        self.guild, channel, e
-                    ),
+                    Err(e) => {
+                        warn!(
+                            "Join Attempt Failed. {:?}, {:?}, {:?}",
+                            self.guild, channel, e
+                        );
+                        if e.missing_permissions() {
+                            missing_permissions = Some(e);
+                        } else {
+                            last_error = Some(e);
+                        }
+                    }
                 }
+            } else {
+                busy += 1;
             }
         }
 
-        info!("Failed Join Request. {:?}, {:?}", self.guild, channel);
-        Err(PlayerMapError::NoFreeBot())
+        info!(
+            "Failed Join Request. {:?}, {:?}, Busy Bots: {}",
+            self.guild, channel, busy
+        );
+        Err(join_error(missing_permissions, last_error, busy))
+    }
+
+    ///Joins the Channel with this Bot and continues the kept Queue of the Channel
+    async fn join_with(&self, bot: UserId, channel: ChannelId) -> Result<(), PlayerMapError> {
+        self.add_player(bot, channel).await?;
+        self.set_node_status(false, None);
+        self.take_over_kept(channel).await;
+        Ok(())
     }
 
     pub async fn get_player(
@@ -197,17 +798,29 @@ impl PlayerManager {
             .guild_field(self.guild, |_| ())
             .await
             .ok_or(PlayerMapError::NoBotWithId(bot, self.guild))?;
-        let lavalink = self
+        self.check_permissions(bot, channel).await?;
+        let pool = self
             .lavalink
             .get(&bot)
-            .ok_or(PlayerMapError::NoLavalink(bot))?
-            .clone();
-        let result = Player::new(bot, channel, self.guild, songbird, lavalink.clone())
-            .await
-            .map_err(PlayerMapError::PlayerError);
+            .ok_or(PlayerMapError::NoLavalink(bot))?;
+        let lavalink = pool.assign(None).ok_or(PlayerMapError::NoLavalink(bot))?;
+        let result = Player::new(
+            bot,
+            channel,
+            self.guild,
+            songbird,
+            lavalink.clone(),
+            &self.config,
+        )
+        .await
+        .map_err(PlayerMapError::PlayerError);
 
         let (player, rec) = match result {
             Err(e) => {
+                pool.release(&lavalink);
+                if e.is_fatal() {
+                    pool.failed(&lavalink);
+                }
                 drop(map_lock);
                 drop(lock);
                 return Err(e);
@@ -219,13 +832,121 @@ impl PlayerManager {
         let mut states = self.player_states.write().await;
         states.push(rec);
         map_lock.add_k1_k2(bot, HashArc::from(lavalink.inner), channel);
+        self.players_change.send(()).ok();
+        Ok(())
+    }
+
+    ///Fails if the Bot is known to lack Permissions for joining the Channel
+    /// - Only aborts on known missing Permissions, otherwise let the Join decide
+    async fn check_permissions(
+        &self,
+        bot: UserId,
+        channel: ChannelId,
+    ) -> Result<(), PlayerMapError> {
+        let (cache, _) = self
+            .bots
+            .get_bot_cache_songbird(&bot)
+            .ok_or(PlayerMapError::NoBotWithId(bot, self.guild))?;
+        if let Some(voice_channel) = cache.guild_channel(channel).await {
+            if let Ok(permissions) = voice_channel.permissions_for_user(&cache, bot).await {
+                if !permissions.contains(Permissions::CONNECT | Permissions::SPEAK) {
+                    return Err(PlayerMapError::PlayerError(
+                        PlayerError::MissingPermissions(channel),
+                    ));
+                }
+            }
+        }
         Ok(())
     }
 
+    ///Moves the Player of the Channel a User came from to the one they are in now
+    /// - Only takes the Player if nobody listens to it anymore, so nobody loses their Music
+    pub async fn move_here(&self, from: ChannelId, to: ChannelId) -> Result<(), PlayerMapError> {
+        if from == to
+            || self.get_player(&from).await.is_none()
+            || self.bots.listener_count(&from, &self.guild).await > 0
+        {
+            return Err(PlayerMapError::NoPlayerToMove(to));
+        }
+        self.move_player(from, to).await
+    }
+
+    ///Moves the Player of a Channel to another one, the Queue continues where it was left
+    /// - The same Bot joins again, so its Permissions are checked before leaving the old Channel
+    pub async fn move_player(&self, from: ChannelId, to: ChannelId) -> Result<(), PlayerMapError> {
+        if self.bot_in_channel(&to).await {
+            return Err(PlayerMapError::BotAlreadyInChannel(to));
+        }
+        let (bot, _) = self
+            .get_player(&from)
+            .await
+            .ok_or(PlayerMapError::NoPlayerFound(from))?;
+        self.check_permissions(bot, to).await?;
+
+        info!("Moving Player. {:?}, {:?}, {:?}", self.guild, from, to);
+        self.disconnect(from, true).await?;
+        self.rekey_kept(from, to);
+        let result = self.join_with(bot, to).await;
+        if result.is_err() {
+            //Joining the old Channel again continues the Queue instead
+            self.rekey_kept(to, from);
+        }
+        result
+    }
+
+    fn rekey_kept(&self, from: ChannelId, to: ChannelId) {
+        if let Ok(mut kept) = self.kept_queues.lock() {
+            if let Some(state) = kept.remove(&from) {
+                kept.insert(to, state);
+            }
+        }
+    }
+
+    ///Continues the kept Queue of the Channel, if there is one
+    async fn take_over_kept(&self, channel: ChannelId) {
+        let kept = match self.kept_queues.lock() {
+            Ok(mut kept) => kept.remove(&channel),
+            Err(_) => None,
+        };
+        let kept = match kept {
+            None => return,
+            Some(kept) => kept,
+        };
+        let player = match self.get_player(&channel).await {
+            None => return,
+            Some((_, player)) => player,
+        };
+        let result = match player.write().await.as_mut() {
+            None => return,
+            Some(player) => player.take_over(kept).await,
+        };
+        match result {
+            Ok(_) => info!("Continued kept Queue. {:?}, {:?}", self.guild, channel),
+            Err(e) => warn!(
+                "Error continuing kept Queue. {:?}, {:?}, {:?}",
+                self.guild, channel, e
+            ),
+        }
+    }
+
+    ///Leaves the Channel and drops the Queue
     pub async fn leave(&self, channel: ChannelId) -> Result<(), PlayerMapError> {
+        if let Ok(mut kept) = self.kept_queues.lock() {
+            kept.remove(&channel);
+        }
+        self.disconnect(channel, false).await
+    }
+
+    ///Leaves the Channel, but keeps the Queue
+    /// - The next Join of the Channel continues the current Track from where it was left
+    pub async fn leave_keep_queue(&self, channel: ChannelId) -> Result<(), PlayerMapError> {
+        self.disconnect(channel, true).await
+    }
+
+    async fn disconnect(&self, channel: ChannelId, keep: bool) -> Result<(), PlayerMapError> {
         info!(
-            "Attempt Voice Channel Leave. {:?}, {:?}",
-            self.guild, channel
+            "Attempt Voice Channel Leave. {:?}, {:?}, Keep Queue: {}",
+            self.guild, channel, keep
         );
         //Get bot and player while removing channel and lavalink form the HashMap
         let (bot, player) = {
@@ -239,11 +960,18 @@ impl PlayerManager {
         };
 
         let mut player_lock = player.write().await;
-        let disconnect_res = player_lock
+        let player = player_lock
             .take()
-            .ok_or(PlayerMapError::NoPlayerFound(channel))?
-            .disconnect()
-            .await;
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        if let Some(pool) = self.lavalink.get(&bot) {
+            pool.release(&player.get_lavalink());
+        }
+        if keep {
+            if let Ok(mut kept) = self.kept_queues.lock() {
+                kept.insert(channel, player.keep());
+            }
+        }
+        let disconnect_res = player.disconnect().await;
         if let Err(e) = disconnect_res {
             warn!(
                 "Error processing player disconnect. {:?}, {:?}, {:?}",
@@ -256,13 +984,341 @@ impl PlayerManager {
             .drain(..)
             .filter(|s| !s.borrow().bot.eq(&bot))
             .collect();
+        self.players_change.send(()).ok();
         Ok(())
     }
 
+    ///Pauses every Player of the Guild
+    /// - Returns how many were paused and the Errors of the others
+    pub async fn pause_all(&self) -> (usize, Vec<(ChannelId, PlayerMapError)>) {
+        self.request_all(PlayerRequest::Pause).await
+    }
+
+    ///Resumes every Player of the Guild
+    /// - Returns how many were resumed and the Errors of the others
+    pub async fn resume_all(&self) -> (usize, Vec<(ChannelId, PlayerMapError)>) {
+        self.request_all(PlayerRequest::Resume).await
+    }
+
+    ///Sends the same Request to every Player, a failing Player does not stop the others
+    async fn request_all(
+        &self,
+        request: fn(ChannelId) -> PlayerRequest,
+    ) -> (usize, Vec<(ChannelId, PlayerMapError)>) {
+        let channels = self.active_players().await.into_iter().map(|(c, _)| c);
+
+        let mut done = 0;
+        let mut failed = Vec::new();
+        for channel in channels {
+            match self.request(request(channel)).await {
+                Ok(_) => done += 1,
+                Err(e) => {
+                    warn!(
+                        "Error in Request for all Players. {:?}, {:?}, {:?}",
+                        self.guild, channel, e
+                    );
+                    failed.push((channel, e));
+                }
+            }
+        }
+        (done, failed)
+    }
+
+    ///Channel and State of every connected Player of the Guild
+    pub async fn active_players(&self) -> Vec<(ChannelId, Arc<PlayerState>)> {
+        let players: Vec<_> = self
+            .player
+            .read()
+            .await
+            .iter()
+            .map(|(_, player)| player.clone())
+            .collect();
+        let mut active = Vec::new();
+        for player in players {
+            if let Some(player) = player.read().await.as_ref() {
+                let state = player.get_status_watch().borrow().clone();
+                active.push((player.get_channel(), state));
+            }
+        }
+        active
+    }
+
+    ///Current State of the Player in the Channel
+    pub async fn get_player_state(&self, channel: &ChannelId) -> Option<Arc<PlayerState>> {
+        let (_, player) = self.get_player(channel).await?;
+        let state = player
+            .read()
+            .await
+            .as_ref()
+            .map(|p| p.get_status_watch().borrow().clone());
+        state
+    }
+
     pub async fn get_all_player_states(&self) -> PlayerStates {
         self.player_states.read().await.clone()
     }
 
+    ///Writes every Player to the Queue Store once the Players settled, see settle
+    /// - Only starts after the stored Players were restored, to not overwrite them
+    pub async fn persist_run(self: Arc<Self>) {
+        let store = match &self.store {
+            None => return,
+            Some(store) => store.clone(),
+        };
+        self.restore_done.notified().await;
+        info!("Starting Queue Persistence. {:?}", self.guild);
+
+        let mut changes = StateChanges {
+            manager: &*self,
+            players_watch: self.players_watch(),
+            states: self.get_all_player_states().await,
+        };
+        loop {
+            settle(&mut changes).await;
+
+            let players = self.stored_players().await;
+            if let Err(e) = store.save(self.guild, players).await {
+                warn!("Error persisting Queues. {:?}, {:?}", self.guild, e);
+            }
+        }
+    }
+
+    ///Waits for any Player State to change
+    /// - Refreshes the States, if Players were added or removed
+    async fn next_change(&self, states: &mut PlayerStates, players_watch: &mut WatchReceiver<()>) {
+        let refresh = tokio::select! {
+            changed = any_state_changed(states) => !changed,
+            _ = players_watch.changed() => true,
+        };
+        if refresh {
+            *states = self.get_all_player_states().await;
+        }
+    }
+
+    async fn stored_players(&self) -> Vec<StoredPlayer> {
+        let players: Vec<_> = self
+            .player
+            .read()
+            .await
+            .iter()
+            .map(|(_, player)| player.clone())
+            .collect();
+
+        let mut stored = Vec::new();
+        for player in players {
+            let (channel, state) = match player.read().await.as_ref() {
+                None => continue,
+                Some(player) => (
+                    player.get_channel(),
+                    player.get_status_watch().borrow().clone(),
+                ),
+            };
+            let current = state.current.as_ref().and_then(|(_, track)| {
+                let position = state.position().unwrap_or_default();
+                StoredTrack::new(track, position.as_millis() as u64)
+            });
+            stored.push(StoredPlayer {
+                channel: channel.0,
+                playback: state.playback,
+                current,
+                playlist: state
+                    .playlist
+                    .iter()
+                    .filter_map(|t| StoredTrack::new(t, 0))
+                    .collect(),
+                history: state
+                    .history
+                    .iter()
+                    .filter_map(|(t, _)| StoredTrack::new(t, 0))
+                    .collect(),
+            });
+        }
+        stored
+    }
+
+    ///Re-creates the stored Players and re-resolves their Tracks
+    /// - Only runs once, Channels without listeners are skipped
+    pub async fn restore(self: Arc<Self>) {
+        let store = match &self.store {
+            None => return,
+            Some(store) => store.clone(),
+        };
+        if self.restore_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let stored = match store.load(self.guild).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("Error loading stored Queues. {:?}, {:?}", self.guild, e);
+                Vec::new()
+            }
+        };
+        info!("Restoring {} Players. {:?}", stored.len(), self.guild);
+        for player in stored {
+            if let Err(e) = self.restore_player(player).await {
+                warn!("Error restoring Player. {:?}, {:?}", self.guild, e);
+            }
+        }
+        self.restore_done.notify_one();
+    }
+
+    async fn restore_player(&self, stored: StoredPlayer) -> Result<(), PlayerMapError> {
+        let channel = ChannelId(stored.channel);
+        if self.bots.listener_count(&channel, &self.guild).await == 0 {
+            info!(
+                "Skipping Restore, because nobody is listening. {:?}, {:?}",
+                self.guild, channel
+            );
+            return Ok(());
+        }
+        self.join(channel).await?;
+
+        let current = match stored.current {
+            None => None,
+            Some(track) => {
+                let position = Duration::from_millis(track.position);
+                self.resolve_stored(channel, Some(track))
+                    .await
+                    .pop()
+                    .map(|t| (t, position))
+            }
+        };
+        let playlist = self.resolve_stored(channel, stored.playlist).await;
+        let history = self.resolve_stored(channel, stored.history).await;
+
+        let (_, player) = self
+            .get_player(&channel)
+            .await
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        let mut player_lock = player.write().await;
+        let player = player_lock
+            .as_mut()
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        player
+            .restore(stored.playback, current, playlist, history)
+            .await
+            .map_err(PlayerMapError::PlayerError)
+    }
+
+    ///Saves the Playlist of the Player in the Channel under a name for the User
+    /// - Returns the amount of saved Tracks
+    pub async fn save_playlist(
+        &self,
+        channel: ChannelId,
+        user: UserId,
+        name: String,
+    ) -> Result<usize, PlayerMapError> {
+        let store = self.store()?;
+        let (_, player) = self
+            .get_player(&channel)
+            .await
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?;
+        let state = player
+            .read()
+            .await
+            .as_ref()
+            .ok_or(PlayerMapError::NoPlayerFound(channel))?
+            .get_status_watch()
+            .borrow()
+            .clone();
+        let tracks: Vec<_> = state
+            .playlist
+            .iter()
+            .filter_map(|t| StoredTrack::new(t, 0))
+            .collect();
+        let saved = tracks.len();
+
+        info!(
+            "Saving Playlist. {:?}, {:?}, {:?}, Tracks: {}",
+            self.guild, user, name, saved
+        );
+        store
+            .save_playlist(user, name, tracks)
+            .await
+            .map_err(PlayerMapError::Persistence)?;
+        Ok(saved)
+    }
+
+    ///Enqueues a saved Playlist of the User into the Player in the Channel
+    /// - Returns the amount of added Tracks and of Tracks that failed to resolve
+    /// - Subject to the Enqueue Cooldown of the User
+    pub async fn load_playlist(
+        &self,
+        channel: ChannelId,
+        user: UserId,
+        name: &str,
+    ) -> Result<(usize, usize), PlayerMapError> {
+        self.enqueue_cooldown(user).await?;
+        let stored = self
+            .store()?
+            .load_playlist(user, name)
+            .await
+            .map_err(PlayerMapError::Persistence)?;
+        let total = stored.len();
+        let tracks = self.resolve_stored(channel, stored).await;
+        let failed = total - tracks.len();
+
+        info!(
+            "Loading Playlist. {:?}, {:?}, {:?}, Resolved: {}, Failed: {}",
+            self.guild,
+            user,
+            name,
+            tracks.len(),
+            failed
+        );
+        if tracks.is_empty() {
+            return Ok((0, failed));
+        }
+        let enqueued = self.enqueue(channel, tracks).await?;
+        self.charge_enqueue_cooldown(user);
+        Ok((enqueued.added, failed))
+    }
+
+    ///Names of the saved Playlists of the User
+    pub async fn playlists(&self, user: UserId) -> Result<Vec<String>, PlayerMapError> {
+        self.store()?
+            .playlists(user)
+            .await
+            .map_err(PlayerMapError::Persistence)
+    }
+
+    fn store(&self) -> Result<Arc<QueueStore>, PlayerMapError> {
+        self.store.clone().ok_or(PlayerMapError::Persistence(
+            PersistenceError::NotConfigured(),
+        ))
+    }
+
+    ///Resolves stored Tracks through Lavalink, skipping the ones that no longer resolve
+    /// - Resolves up to RESOLVE_CONCURRENCY Tracks at once, keeping their order
+    async fn resolve_stored(
+        &self,
+        channel: ChannelId,
+        stored: impl IntoIterator<Item = StoredTrack>,
+    ) -> Vec<Track> {
+        futures::stream::iter(stored)
+            .map(|track| async move {
+                match self.load_lavalink(channel, track.uri.clone()).await {
+                    Ok((_, mut loaded)) if !loaded.tracks.is_empty() => {
+                        Some(loaded.tracks.remove(0))
+                    }
+                    res => {
+                        warn!(
+                            "Stored Track could not be resolved. {:?}, {:?}, {:?}",
+                            self.guild,
+                            track,
+                            res.err()
+                        );
+                        None
+                    }
+                }
+            })
+            .buffered(RESOLVE_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await
+    }
+
     pub async fn handle_player_event(
         &self,
         event: LavalinkEvent,
@@ -279,7 +1335,8 @@ impl PlayerManager {
         let player = player_lock
             .as_mut()
             .ok_or(PlayerMapError::NoPlayerForBot(bot))?;
-        match event {
+        let channel = player.get_channel();
+        let result = match event {
             LavalinkEvent::Update(update) => {
                 player.update(update);
                 Ok(())
@@ -292,21 +1349,162 @@ impl PlayerManager {
                 .track_end(finish)
                 .await
                 .map_err(PlayerMapError::PlayerError),
+        };
+        drop(player_lock);
+        self.supervise(channel, result).await
+    }
+}
+
+///Retries Reconnect Attempts with exponential backoff
+/// - Returns the successful Attempt, or the last Error after giving up
+/// - Stops right away, once the Player is gone
+async fn retry_reconnect<F, Fut>(
+    attempts: u32,
+    mut backoff: Duration,
+    mut reconnect: F,
+) -> Result<u32, PlayerMapError>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<(), PlayerMapError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match reconnect(attempt).await {
+            Ok(_) => return Ok(attempt),
+            Err(e) if attempt >= attempts || matches!(e, PlayerMapError::NoPlayerFound(_)) => {
+                return Err(e)
+            }
+            Err(_) => {}
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+///Waits until any of the Player States changed
+/// - Returns false if a Player is gone and the States have to be fetched again
+///Controls that can be restricted to the DJ Role, named like in DjConfig::restricted
+#[derive(Clone, Copy, Debug, PartialEq, AsRefStr)]
+pub enum Permission {
+    PlayPause,
+    Next,
+    Prev,
+    Join,
+    Leave,
+    LoopOne,
+    LoopAll,
+    Queue,
+    BassBoost,
+    Lyrics,
+    Clear,
+    Shuffle,
+    ///Setting the Volume
+    Volume,
+    ///Jumping to a Position of the current Track
+    Seek,
+    ///Pausing or resuming every Player of the Guild
+    PauseAll,
+}
+
+impl Permission {
+    ///Whether the DJ Role is required, even if the Control is not listed as restricted
+    fn always_restricted(self) -> bool {
+        matches!(self, Permission::PauseAll)
+    }
+}
+
+///Anything whose changes can be waited for, see settle
+trait Changes {
+    fn changed(&mut self) -> BoxFuture<'_, ()>;
+}
+
+///Changes of any Player State of the Guild
+struct StateChanges<'a> {
+    manager: &'a PlayerManager,
+    players_watch: WatchReceiver<()>,
+    states: PlayerStates,
+}
+
+impl Changes for StateChanges<'_> {
+    fn changed(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(
+            self.manager
+                .next_change(&mut self.states, &mut self.players_watch),
+        )
+    }
+}
+
+///Waits for a change and then until there were no changes for PERSIST_DEBOUNCE
+/// - Ongoing changes put it off, but never past PERSIST_MAX_DELAY after the first one
+async fn settle(changes: &mut impl Changes) {
+    changes.changed().await;
+    let deadline = tokio::time::Instant::now() + PERSIST_MAX_DELAY;
+    loop {
+        tokio::select! {
+            _ = changes.changed() => {},
+            _ = tokio::time::sleep(PERSIST_DEBOUNCE) => break,
+            _ = tokio::time::sleep_until(deadline) => break,
         }
     }
 }
 
+pub async fn any_state_changed(states: &mut PlayerStates) -> bool {
+    if states.is_empty() {
+        return futures::future::pending().await;
+    }
+    let changes = states.iter_mut().map(|s| Box::pin(s.changed()));
+    let (res, _, _) = futures::future::select_all(changes).await;
+    res.is_ok()
+}
+
+///What a Skip of a User turns into
+/// - Without Vote Skip or from a DJ it skips right away, the whole Amount
+/// - Otherwise it is a single Vote for the current Track, Votes never skip several Tracks
+fn skip_request(
+    channel: ChannelId,
+    user: UserId,
+    i: usize,
+    vote: Option<(f64, usize)>,
+    dj: bool,
+) -> PlayerRequest {
+    match vote {
+        Some((fraction, listener)) if !dj => {
+            let required = ((listener as f64 * fraction).ceil() as usize).max(1);
+            PlayerRequest::VoteSkip(user, required, channel)
+        }
+        _ => PlayerRequest::Skip(i, channel),
+    }
+}
+
 #[derive(Debug)]
 pub enum PlayerRequest {
     //Join(ChannelId),
     //Leave(ChannelId),
     Skip(usize, ChannelId),
+    VoteSkip(UserId, usize, ChannelId),
+    SkipTo(usize, ChannelId),
     BackSkip(usize, ChannelId),
+    Previous(ChannelId),
+    PlayNext(usize, ChannelId),
+    ReplayLast(ChannelId),
     ClearQueue(ChannelId),
+    ClearHistory(ChannelId),
     Playback(Playback, ChannelId),
+    SetPlayback(Playback, ChannelId),
+    Shuffle(ChannelId),
+    SetShuffle(bool, ChannelId),
     PauseResume(ChannelId),
-    Enqueue(Vec<Track>, ChannelId),
+    Pause(ChannelId),
+    Resume(ChannelId),
     Jump(Duration, ChannelId),
+    JumpPercent(f32, ChannelId),
+    JumpFraction(f64, ChannelId),
+    Filter(Option<AudioFilter>, ChannelId),
+    ToggleFilter(AudioFilter, ChannelId),
+    Speed(f32, ChannelId),
+    Pitch(f32, ChannelId),
+    Volume(u16, ChannelId),
 }
 
 impl PlayerRequest {
@@ -315,12 +1513,29 @@ impl PlayerRequest {
             //PlayerRequest::Join(channel) => *channel,
             //PlayerRequest::Leave(channel) => *channel,
             PlayerRequest::Skip(_, channel) => *channel,
+            PlayerRequest::VoteSkip(_, _, channel) => *channel,
+            PlayerRequest::SkipTo(_, channel) => *channel,
             PlayerRequest::BackSkip(_, channel) => *channel,
+            PlayerRequest::Previous(channel) => *channel,
+            PlayerRequest::PlayNext(_, channel) => *channel,
+            PlayerRequest::ReplayLast(channel) => *channel,
             PlayerRequest::ClearQueue(channel) => *channel,
+            PlayerRequest::ClearHistory(channel) => *channel,
             PlayerRequest::Playback(_, channel) => *channel,
+            PlayerRequest::SetPlayback(_, channel) => *channel,
+            PlayerRequest::Shuffle(channel) => *channel,
+            PlayerRequest::SetShuffle(_, channel) => *channel,
             PlayerRequest::PauseResume(channel) => *channel,
-            PlayerRequest::Enqueue(_, channel) => *channel,
+            PlayerRequest::Pause(channel) => *channel,
+            PlayerRequest::Resume(channel) => *channel,
             PlayerRequest::Jump(_, channel) => *channel,
+            PlayerRequest::JumpPercent(_, channel) => *channel,
+            PlayerRequest::JumpFraction(_, channel) => *channel,
+            PlayerRequest::Filter(_, channel) => *channel,
+            PlayerRequest::ToggleFilter(_, channel) => *channel,
+            PlayerRequest::Speed(_, channel) => *channel,
+            PlayerRequest::Pitch(_, channel) => *channel,
+            PlayerRequest::Volume(_, channel) => *channel,
         }
     }
 }
@@ -331,12 +1546,12 @@ pub enum PlayerMapError {
     NoPlayerFound(ChannelId),
     #[error("Player was None for: {0:?}")]
     NoPlayerForBot(UserId),
-    #[error("Search Sender was dropped: {0:?}")]
-    SearchSenderDropped(RecvError),
     #[error("Player Error occurred: {0:?}")]
     PlayerError(PlayerError),
-    #[error("No free Bot available")]
-    NoFreeBot(),
+    #[error("No free Bot available, {0} Bots are busy in other Channels")]
+    NoFreeBot(usize),
+    #[error("No Bot could join the Channel")]
+    NoBotJoined(),
     #[error("Could not find Bot for ID: {0:?} in Guild: {1:?}")]
     NoBotWithId(UserId, GuildId),
     #[error("Could not find Lavalink Client in Map")]
@@ -347,6 +1562,16 @@ pub enum PlayerMapError {
     NoLavalink(UserId),
     #[error("There is already an active player for the channel: {0:?}")]
     BotAlreadyInChannel(ChannelId),
+    #[error("No Player to move, the Channel you came from has none or it still has Listeners")]
+    NoPlayerToMove(ChannelId),
+    #[error("Lyrics Error occurred: {0:?}")]
+    Lyrics(LyricsError),
+    #[error("Persistence Error occurred: {0:?}")]
+    Persistence(PersistenceError),
+    #[error("Slow down, you can enqueue again in {0} seconds")]
+    Cooldown(u64),
+    #[error("{0}")]
+    Query(QueryError),
 }
 
 impl PlayerMapError {
@@ -356,4 +1581,314 @@ impl PlayerMapError {
         }
         false
     }
+
+    pub fn missing_permissions(&self) -> bool {
+        matches!(
+            self,
+            PlayerMapError::PlayerError(PlayerError::MissingPermissions(_))
+        )
+    }
+
+    ///Readable message for Errors the Users can fix themselves
+    pub fn user_message(&self) -> Option<String> {
+        match self {
+            PlayerMapError::PlayerError(e)
+                if matches!(
+                    e,
+                    PlayerError::MissingPermissions(_)
+                        | PlayerError::UnknownLength()
+                        | PlayerError::TrackTooLong(_)
+                        | PlayerError::StreamsNotAllowed()
+                        | PlayerError::NotSeekable()
+                        | PlayerError::NoMatches(_)
+                        | PlayerError::SearchFailed(_)
+                        | PlayerError::NotFinite(_)
+                ) =>
+            {
+                Some(e.to_string())
+            }
+            PlayerMapError::PlayerError(PlayerError::PlaylistFull(_)) => {
+                Some("Playlist is full".to_string())
+            }
+            PlayerMapError::Cooldown(_)
+            | PlayerMapError::Query(_)
+            | PlayerMapError::NoFreeBot(_)
+            | PlayerMapError::NoBotJoined()
+            | PlayerMapError::NoPlayerToMove(_) => Some(self.to_string()),
+            _ => None,
+        }
+    }
+}
+
+///Error of a failed Join, the Users should get something they can act on
+/// - Errors of free Bots only win over the busy ones, if the Users can fix them
+fn join_error(
+    missing_permissions: Option<PlayerMapError>,
+    last_error: Option<PlayerMapError>,
+    busy: usize,
+) -> PlayerMapError {
+    match missing_permissions.or(last_error) {
+        Some(e) if e.user_message().is_some() => e,
+        _ if busy > 0 => PlayerMapError::NoFreeBot(busy),
+        _ => PlayerMapError::NoBotJoined(),
+    }
+}
+
+///Last Enqueue of every User, that may still be within the Cooldown
+#[derive(Debug, Default)]
+struct Cooldowns(HashMap<UserId, Instant>);
+
+impl Cooldowns {
+    ///Time the User still has to wait, expired entries are dropped
+    fn remaining(&mut self, user: &UserId, cooldown: Duration, now: Instant) -> Option<Duration> {
+        self.0
+            .retain(|_, last| now.duration_since(*last) < cooldown);
+        self.0
+            .get(user)
+            .map(|last| cooldown - now.duration_since(*last))
+    }
+
+    fn charge(&mut self, user: UserId, now: Instant) {
+        self.0.insert(user, now);
+    }
+}
+
+///Checks a Track against the Length limits of the Guild
+/// - Tracks without a Length count as Live Streams
+fn check_length(config: &GuildConfig, track: &Track) -> Result<(), PlayerError> {
+    let info = match &track.info {
+        None => return Ok(()),
+        Some(info) => info,
+    };
+    if info.is_stream || info.length == 0 {
+        if !config.allow_streams {
+            return Err(PlayerError::StreamsNotAllowed());
+        }
+        return Ok(());
+    }
+    match config.max_track_length {
+        Some(max) if info.length > max.saturating_mul(1000) => Err(PlayerError::TrackTooLong(max)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::guild_config;
+    use crate::player::tests::track;
+    use lavalink_rs::error::LavalinkError;
+
+    const BACKOFF: Duration = Duration::from_millis(1);
+
+    impl<T: Send + Sync> Changes for WatchReceiver<T> {
+        fn changed(&mut self) -> BoxFuture<'_, ()> {
+            Box::pin(async move {
+                if WatchReceiver::changed(self).await.is_err() {
+                    futures::future::pending().await
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn settle_waits_for_quiet() {
+        tokio::time::pause();
+        let (send, mut receive) = tokio::sync::watch::channel(0);
+        let start = tokio::time::Instant::now();
+        send.send(1).unwrap();
+        settle(&mut receive).await;
+        assert_eq!(start.elapsed(), PERSIST_DEBOUNCE);
+    }
+
+    #[tokio::test]
+    async fn settle_saves_while_the_position_keeps_changing() {
+        tokio::time::pause();
+        let (send, mut receive) = tokio::sync::watch::channel(0);
+        //Position Updates of a playing Track, more often than the Debounce
+        let updates = tokio::spawn(async move {
+            for position in 1.. {
+                send.send(position).ok();
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        });
+        let start = tokio::time::Instant::now();
+        settle(&mut receive).await;
+        assert_eq!(start.elapsed(), PERSIST_MAX_DELAY);
+        updates.abort();
+    }
+
+    fn closed() -> PlayerMapError {
+        PlayerMapError::PlayerError(PlayerError::Lavalink(LavalinkError::ErrorWebsocketPayload(
+            tokio_tungstenite::tungstenite::Error::ConnectionClosed,
+        )))
+    }
+
+    #[tokio::test]
+    async fn reconnect_resumes_after_fatal_error() {
+        assert!(closed().is_fatal());
+        let mut attempts = Vec::new();
+        let result = retry_reconnect(RECONNECT_ATTEMPTS, BACKOFF, |attempt| {
+            attempts.push(attempt);
+            let res = if attempt == 1 { Err(closed()) } else { Ok(()) };
+            async move { res }
+        })
+        .await;
+        assert_eq!(result.ok(), Some(2));
+        assert_eq!(attempts, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn reconnect_gives_up() {
+        let mut attempts = 0;
+        let result = retry_reconnect(3, BACKOFF, |_| {
+            attempts += 1;
+            async { Err(closed()) }
+        })
+        .await;
+        assert!(result.unwrap_err().is_fatal());
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn reconnect_stops_without_player() {
+        let mut attempts = 0;
+        let result = retry_reconnect(RECONNECT_ATTEMPTS, BACKOFF, |_| {
+            attempts += 1;
+            async { Err(PlayerMapError::NoPlayerFound(ChannelId(1))) }
+        })
+        .await;
+        assert!(matches!(result, Err(PlayerMapError::NoPlayerFound(_))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn djs_skip_past_the_vote() {
+        let (channel, user) = (ChannelId(1), UserId(2));
+        //Half of 5 Listeners have to agree
+        let vote = Some((0.5, 5));
+        assert!(matches!(
+            skip_request(channel, user, 3, vote, true),
+            PlayerRequest::Skip(3, _)
+        ));
+        assert!(matches!(
+            skip_request(channel, user, 3, vote, false),
+            PlayerRequest::VoteSkip(UserId(2), 3, _)
+        ));
+        assert!(matches!(
+            skip_request(channel, user, 3, None, false),
+            PlayerRequest::Skip(3, _)
+        ));
+        //Alone in the Channel, the own Vote is enough
+        assert!(matches!(
+            skip_request(channel, user, 1, Some((0.5, 0)), false),
+            PlayerRequest::VoteSkip(_, 1, _)
+        ));
+    }
+
+    #[test]
+    fn join_error_prefers_fixable_errors() {
+        let permissions =
+            || PlayerMapError::PlayerError(PlayerError::MissingPermissions(ChannelId(1)));
+        assert!(matches!(
+            join_error(Some(permissions()), Some(closed()), 2),
+            PlayerMapError::PlayerError(PlayerError::MissingPermissions(_))
+        ));
+        //Internal Errors of a free Bot say nothing to the Users
+        assert!(matches!(
+            join_error(None, Some(closed()), 2),
+            PlayerMapError::NoFreeBot(2)
+        ));
+        assert!(matches!(
+            join_error(None, Some(closed()), 0),
+            PlayerMapError::NoBotJoined()
+        ));
+        assert!(matches!(
+            join_error(None, None, 3),
+            PlayerMapError::NoFreeBot(3)
+        ));
+        assert!(matches!(
+            join_error(None, None, 0),
+            PlayerMapError::NoBotJoined()
+        ));
+    }
+
+    #[test]
+    fn node_status_combines_guilds() {
+        let status = |connected, reconnecting, last_error: Option<&str>| NodeStatus {
+            connected,
+            reconnecting,
+            last_error: last_error.map(str::to_string),
+        };
+        assert_eq!(NodeStatus::combine(Vec::new()), status(false, false, None));
+        assert_eq!(
+            NodeStatus::combine(vec![
+                status(true, false, None),
+                status(false, true, Some("closed"))
+            ]),
+            status(true, true, Some("closed"))
+        );
+        assert_eq!(
+            NodeStatus::combine(vec![status(false, false, None)]),
+            status(false, false, None)
+        );
+    }
+
+    #[test]
+    fn cooldown_window_boundaries() {
+        let cooldown = Duration::from_secs(5);
+        let (user, other) = (UserId(1), UserId(2));
+        let start = Instant::now();
+        let mut cooldowns = Cooldowns::default();
+
+        //Checking alone does not start the Cooldown
+        assert_eq!(cooldowns.remaining(&user, cooldown, start), None);
+        assert_eq!(cooldowns.remaining(&user, cooldown, start), None);
+
+        cooldowns.charge(user, start);
+        assert_eq!(cooldowns.remaining(&user, cooldown, start), Some(cooldown));
+        assert_eq!(
+            cooldowns.remaining(&user, cooldown, start + cooldown - Duration::from_millis(1)),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(cooldowns.remaining(&other, cooldown, start), None);
+        assert_eq!(cooldowns.remaining(&user, cooldown, start + cooldown), None);
+        assert!(cooldowns.0.is_empty());
+    }
+
+    #[test]
+    fn length_limit_boundaries() {
+        let config = guild_config("max_track_length: 600");
+        assert!(check_length(&config, &track("a", 600_000, false)).is_ok());
+        assert!(matches!(
+            check_length(&config, &track("a", 600_001, false)),
+            Err(PlayerError::TrackTooLong(600))
+        ));
+        assert!(check_length(&config, &track("a", 1, false)).is_ok());
+
+        //Limits too big for milliseconds must not overflow
+        let mut config = guild_config("");
+        config.max_track_length = Some(u64::MAX);
+        assert!(check_length(&config, &track("a", u64::MAX, false)).is_ok());
+
+        let config = guild_config("");
+        assert!(check_length(&config, &track("a", u64::MAX, false)).is_ok());
+    }
+
+    #[test]
+    fn streams_ignore_the_length_limit() {
+        let config = guild_config("max_track_length: 1\nallow_streams: false");
+        assert!(matches!(
+            check_length(&config, &track("a", 0, true)),
+            Err(PlayerError::StreamsNotAllowed())
+        ));
+        //Without a Length it counts as Live Stream
+        assert!(matches!(
+            check_length(&config, &track("a", 0, false)),
+            Err(PlayerError::StreamsNotAllowed())
+        ));
+
+        let config = guild_config("max_track_length: 1");
+        assert!(check_length(&config, &track("a", 0, true)).is_ok());
+    }
 }
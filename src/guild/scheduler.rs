@@ -132,7 +132,7 @@ impl RouteScheduler {
                     //Drop Lock so others can get more tasks
                     drop(receive_lock);
                     //Execute Task and ignore outcome
-                    task.run(bot.http().clone()).await.ok();
+                    task.run(bot.http().clone(), target_route).await.ok();
                 }
             });
         }
@@ -0,0 +1,178 @@
+use crate::guild::player_manager::Permission;
+use crate::player::Playback;
+use serenity::http::Http;
+use serenity::model::id::GuildId;
+use serenity::model::interactions::application_command::{
+    ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+    ApplicationCommandOptionType,
+};
+use serenity::prelude::SerenityError;
+use std::time::Duration;
+
+///Slash Commands of a Guild, handled like the Controls of the Main Message
+#[derive(Debug, Clone)]
+pub enum SlashCommand {
+    Play(String),
+    Skip(),
+    Queue(),
+    Pause(),
+    Loop(Playback),
+    Volume(u16),
+    Seek(Duration),
+}
+
+impl SlashCommand {
+    ///Registers every Command for the Guild, replacing the ones registered before
+    pub async fn register(http: &Http, guild: GuildId) -> Result<(), SerenityError> {
+        guild
+            .set_application_commands(http, |commands| {
+                commands
+                    .create_application_command(|c| {
+                        c.name("play")
+                            .description("Search for a Track or play a Url")
+                            .create_option(|o| {
+                                o.name("query")
+                                    .description("Search Terms or Url")
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+                    .create_application_command(|c| {
+                        c.name("skip").description("Skip the current Track")
+                    })
+                    .create_application_command(|c| c.name("queue").description("Show the Queue"))
+                    .create_application_command(|c| {
+                        c.name("pause").description("Pause or resume the Player")
+                    })
+                    .create_application_command(|c| {
+                        c.name("loop")
+                            .description("Set the Loop Mode")
+                            .create_option(|o| {
+                                o.name("mode")
+                                    .description("What to repeat")
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                                    .add_string_choice("Off", "off")
+                                    .add_string_choice("Queue", "all")
+                                    .add_string_choice("Track", "one")
+                            })
+                    })
+                    .create_application_command(|c| {
+                        c.name("volume")
+                            .description("Set the Volume")
+                            .create_option(|o| {
+                                o.name("percent")
+                                    .description("100 is unchanged, at most 1000")
+                                    .kind(ApplicationCommandOptionType::Integer)
+                                    .required(true)
+                            })
+                    })
+                    .create_application_command(|c| {
+                        c.name("seek")
+                            .description("Jump to a Position in the current Track")
+                            .create_option(|o| {
+                                o.name("position")
+                                    .description("e.g. 1:30 or 90")
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+            })
+            .await
+            .map(|_| ())
+    }
+
+    ///Removes every Command of the Guild, e.g. after Slash Commands were disabled
+    pub async fn clear(http: &Http, guild: GuildId) -> Result<(), SerenityError> {
+        guild
+            .set_application_commands(http, |commands| commands)
+            .await
+            .map(|_| ())
+    }
+
+    ///What the Command needs permission for, same DJ Restrictions as the Controls
+    pub fn permission(&self) -> Option<Permission> {
+        match self {
+            SlashCommand::Skip() => Some(Permission::Next),
+            SlashCommand::Queue() => Some(Permission::Queue),
+            SlashCommand::Pause() => Some(Permission::PlayPause),
+            SlashCommand::Loop(_) => Some(Permission::LoopAll),
+            SlashCommand::Volume(_) => Some(Permission::Volume),
+            SlashCommand::Seek(_) => Some(Permission::Seek),
+            //Like posting a Query in the Channel, only limited by the Enqueue Cooldown
+            SlashCommand::Play(_) => None,
+        }
+    }
+
+    ///Reads the Command and its Options from the Interaction
+    /// - Fails with a Message for the User
+    pub fn parse(interaction: &ApplicationCommandInteraction) -> Result<SlashCommand, String> {
+        let option = |name: &str| {
+            interaction
+                .data
+                .options
+                .iter()
+                .find(|o| o.name.eq(name))
+                .and_then(|o| o.resolved.clone())
+        };
+        let string = |name: &str| match option(name) {
+            Some(ApplicationCommandInteractionDataOptionValue::String(s)) => Ok(s),
+            _ => Err(format!("Missing Option: {}", name)),
+        };
+
+        match interaction.data.name.as_str() {
+            "play" => Ok(SlashCommand::Play(string("query")?)),
+            "skip" => Ok(SlashCommand::Skip()),
+            "queue" => Ok(SlashCommand::Queue()),
+            "pause" => Ok(SlashCommand::Pause()),
+            "loop" => match string("mode")?.as_str() {
+                "off" => Ok(SlashCommand::Loop(Playback::Normal)),
+                "all" => Ok(SlashCommand::Loop(Playback::AllLoop)),
+                "one" => Ok(SlashCommand::Loop(Playback::OneLoop)),
+                mode => Err(format!("Unknown Loop Mode: {}", mode)),
+            },
+            "volume" => match option("percent") {
+                Some(ApplicationCommandInteractionDataOptionValue::Integer(v)) if v >= 0 => {
+                    Ok(SlashCommand::Volume(v.min(u16::MAX as i64) as u16))
+                }
+                _ => Err("The Volume has to be a positive Number".to_string()),
+            },
+            "seek" => {
+                let position = string("position")?;
+                parse_position(&position)
+                    .map(SlashCommand::Seek)
+                    .ok_or_else(|| format!("Invalid Position: {}", position))
+            }
+            name => Err(format!("Unknown Command: {}", name)),
+        }
+    }
+}
+
+///Reads Positions like 90, 1:30 or 1:02:30
+fn parse_position(position: &str) -> Option<Duration> {
+    position
+        .trim()
+        .split(':')
+        .try_fold(0, |secs: u64, part| {
+            secs.checked_mul(60)?.checked_add(part.parse().ok()?)
+        })
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_and_seek_have_own_permissions() {
+        assert_eq!(
+            SlashCommand::Volume(50).permission(),
+            Some(Permission::Volume)
+        );
+        assert_eq!(
+            SlashCommand::Seek(Duration::from_secs(90)).permission(),
+            Some(Permission::Seek)
+        );
+        assert_eq!(SlashCommand::Play("query".to_string()).permission(), None);
+    }
+}
@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use log::info;
+use log::{info, warn};
 use serenity::model::id::{GuildId, UserId};
 use serenity::prelude::SerenityError;
 use thiserror::Error;
@@ -14,9 +14,12 @@ use crate::config::Config;
 use crate::event_handler::EventHandler;
 use crate::guild::{ReciprocityGuild, ReciprocityGuildError};
 use crate::lavalink_handler::LavalinkHandler;
+use crate::lyrics::LyricsProvider;
 use crate::net::CompanionCommunicationHandler;
+use crate::node_pool::NodePool;
+use crate::persistence::{PersistenceError, QueueStore};
+use crate::spotify::SpotifyResolver;
 use lavalink_rs::error::LavalinkError;
-use lavalink_rs::LavalinkClient;
 
 mod bots;
 pub mod config;
@@ -24,9 +27,15 @@ mod context;
 mod event_handler;
 pub mod guild;
 mod lavalink_handler;
+mod lyrics;
 mod multi_key_map;
 mod net;
+mod node_pool;
+mod persistence;
 mod player;
+mod query;
+mod rate_limit;
+mod spotify;
 mod task_handle;
 
 pub struct ReciprocityBot {}
@@ -40,21 +49,38 @@ impl ReciprocityBot {
 
         //Build LavalinkEventHandler and LavalinkSupervisor using the EventHandler
         let lavalink_event_handler = LavalinkHandler::new();
-        let mut lavalink: HashMap<UserId, LavalinkClient> = HashMap::new();
-        info!("Starting Lavalink for each Bot");
+        let mut lavalink: HashMap<UserId, Arc<NodePool>> = HashMap::new();
+        let mut nodes = vec![config.lavalink.clone()];
+        nodes.extend(config.lavalink_nodes.iter().cloned());
+        info!("Starting {} Lavalink Nodes for each Bot", nodes.len());
         for bot in bots.ids() {
-            let client = LavalinkClient::builder(bot)
-                .set_host(&config.lavalink.address)
-                .set_password(&config.lavalink.password)
-                //.set_is_ssl(true)
-                .build(lavalink_event_handler.clone())
+            let pool = NodePool::new(bot, &nodes, lavalink_event_handler.clone())
                 .await
                 .map_err(ReciprocityError::Lavalink)?;
-            lavalink.insert(bot, client);
+            lavalink.insert(bot, Arc::new(pool));
         }
         let lavalink = Arc::new(lavalink);
         let mut player_manager = HashMap::new();
 
+        //Spotify Urls are only resolved with configured Credentials
+        let spotify = config.spotify.clone().map(|cfg| {
+            info!("Enabling Spotify Url Resolution");
+            Arc::new(SpotifyResolver::new(cfg))
+        });
+        let lyrics = config.lyrics.clone().map(|cfg| {
+            info!("Enabling Lyrics");
+            Arc::new(LyricsProvider::new(cfg))
+        });
+        let store = match config.persistence.clone() {
+            None => None,
+            Some(cfg) => {
+                info!("Enabling Queue Persistence. {:?}", cfg.path);
+                Some(Arc::new(
+                    QueueStore::new(cfg).map_err(ReciprocityError::Persistence)?,
+                ))
+            }
+        };
+
         //Build every Guild
         info!("Starting {} Guilds", config.guilds.len());
         for guild in config.guilds.values() {
@@ -66,6 +92,9 @@ impl ReciprocityBot {
                 event_handler.clone(),
                 lavalink.clone(),
                 config.clone(),
+                spotify.clone(),
+                lyrics.clone(),
+                store.clone(),
             )
             .map_err(|e| ReciprocityError::Guild(e, id))?;
             player_manager.insert(r_guild.get_id(), r_guild.get_player_manager());
@@ -77,19 +106,54 @@ impl ReciprocityBot {
                 .await;
         }
 
-        if let Some(net_cfg) = config.net.clone() {
+        let net_handler = if let Some(net_cfg) = config.net.clone() {
             info!("Starting Network Companion Handler");
-            let _net_handler =
-                CompanionCommunicationHandler::new(net_cfg, bots.clone(), Arc::new(player_manager));
-        }
+            Some(CompanionCommunicationHandler::new(
+                net_cfg,
+                bots.clone(),
+                Arc::new(player_manager),
+            ))
+        } else {
+            None
+        };
 
         info!("Started Everything");
-        let (res, _, _) = futures::future::select_all(join_handles).await;
-        match res {
-            Ok(res) => res.map_err(ReciprocityError::Serenity),
-            Err(err) => Err(ReciprocityError::JoinErrorClient(err)),
+        tokio::select! {
+            (res, _, _) = futures::future::select_all(join_handles) => match res {
+                Ok(res) => res.map_err(ReciprocityError::Serenity),
+                Err(err) => Err(ReciprocityError::JoinErrorClient(err)),
+            },
+            _ = shutdown_signal() => {
+                info!("Received Shutdown Signal");
+                if let Some(net_handler) = net_handler {
+                    net_handler.shutdown().await;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+///Resolves on SIGINT or SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("Could not listen for SIGTERM: {:?}", e);
+                tokio::signal::ctrl_c().await.ok();
+            }
         }
     }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.ok();
 }
 
 #[derive(Error, Debug)]
@@ -106,6 +170,8 @@ pub enum ReciprocityError {
     BotCreateError(BotError),
     #[error("Lavalink Error occured: {0:?}")]
     Lavalink(LavalinkError),
+    #[error("Persistence Error occurred: {0:?}")]
+    Persistence(PersistenceError),
 }
 
 ///Builds and starts bots from token and with EventHandler
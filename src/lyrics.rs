@@ -0,0 +1,66 @@
+use crate::config::LyricsConfig;
+use log::info;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use thiserror::Error;
+
+///Looks up Lyrics from the configured endpoint
+/// - Requests: GET <endpoint>?title=<title>&artist=<artist>
+/// - Expects a JSON Response like {"lyrics": "..."}, a 404 means no match
+pub struct LyricsProvider {
+    client: Client,
+    config: LyricsConfig,
+}
+
+impl LyricsProvider {
+    pub fn new(config: LyricsConfig) -> Self {
+        LyricsProvider {
+            client: Client::new(),
+            config,
+        }
+    }
+
+    pub async fn lyrics(&self, title: &str, artist: &str) -> Result<String, LyricsError> {
+        info!(
+            "Looking up Lyrics. Title: {:?}, Artist: {:?}",
+            title, artist
+        );
+        let mut req = self
+            .client
+            .get(&self.config.endpoint)
+            .query(&[("title", title), ("artist", artist)]);
+        if let Some(token) = &self.config.token {
+            req = req.bearer_auth(token);
+        }
+
+        let res = req.send().await.map_err(LyricsError::Request)?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Err(LyricsError::NoMatch());
+        }
+        if !res.status().is_success() {
+            return Err(LyricsError::Status(res.status().as_u16()));
+        }
+        let lyrics: LyricsResponse = res.json().await.map_err(LyricsError::Request)?;
+        if lyrics.lyrics.trim().is_empty() {
+            return Err(LyricsError::NoMatch());
+        }
+        Ok(lyrics.lyrics)
+    }
+}
+
+#[derive(Deserialize)]
+struct LyricsResponse {
+    lyrics: String,
+}
+
+#[derive(Error, Debug)]
+pub enum LyricsError {
+    #[error("Lyrics are not configured")]
+    NotConfigured(),
+    #[error("No Lyrics found")]
+    NoMatch(),
+    #[error("Lyrics Request failed: {0:?}")]
+    Request(reqwest::Error),
+    #[error("Lyrics Provider responded with Status: {0}")]
+    Status(u16),
+}
@@ -1,32 +1,62 @@
-use crate::bots::BotMap;
+use crate::bots::{Bot, BotMap};
 use crate::config::NetConfig;
-use crate::guild::player_manager::{PlayerManager, PlayerRequest};
-use crate::player::Player;
+use crate::guild::player_manager::{
+    NodeStatus, Permission, PlayerManager, PlayerMapError, PlayerRequest,
+};
+use crate::player::{Player, PlayerError, PLAYLIST_LOADED};
+use crate::rate_limit::TokenBucket;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
 use log::{debug, error, info, warn};
+use rand::Rng;
 use reciprocity_communication::host::*;
 use reciprocity_communication::messages::oauth2::AccessToken;
 use reciprocity_communication::messages::{
-    Auth, AuthMessage, BotInfo, ClientRequest, Message, PlayMode, PlayerControl,
-    PlayerControlResult, PlayerState, State, Track, Unexpected, User, VoiceState,
+    AudioFilter, Auth, AuthMessage, BotInfo, ChannelMember, ClientRequest, HistoryReason,
+    HistoryTrack, Message, ParseErrorDetail, ParseErrorKind, PlayMode, PlayerControl,
+    PlayerControlResult, PlayerState, PlayerSummary, QueueOp, State, Track, Unexpected, User,
+    VoiceState,
 };
 use serenity::model::prelude::{ChannelId, GuildId, UserId};
-use serenity::model::user::CurrentUser;
 use std::collections::HashMap;
-use std::net::{SocketAddr, SocketAddrV4};
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufReader, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{Mutex, RwLock};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError as BroadcastRecvError;
+use tokio::sync::broadcast::{Receiver as BroadcastReceiver, Sender as BroadcastSender};
+use tokio::sync::{Mutex, Notify, OwnedSemaphorePermit, RwLock, Semaphore};
 use tokio::task::JoinHandle;
+use tokio_rustls::rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use tokio_rustls::rustls::{NoClientAuth, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::header::ORIGIN;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message as TungMessage;
-use tokio_tungstenite::{accept_async, WebSocketStream};
+use tokio_tungstenite::{accept_hdr_async, WebSocketStream};
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct CompanionCommunicationHandler {
+    cfg: Arc<NetConfig>,
     players: Arc<HashMap<GuildId, Arc<PlayerManager>>>,
     bots: Arc<BotMap>,
+    shutdown: BroadcastSender<()>,
+    active: Arc<AtomicUsize>,
+    connections: Arc<Semaphore>,
+    ///Authenticated connections per User
+    users: Arc<std::sync::Mutex<HashMap<UserId, usize>>>,
 }
 
 impl CompanionCommunicationHandler {
@@ -35,15 +65,63 @@ impl CompanionCommunicationHandler {
         bots: Arc<BotMap>,
         players: Arc<HashMap<GuildId, Arc<PlayerManager>>>,
     ) -> Self {
-        let comp = CompanionCommunicationHandler { players, bots };
+        let (shutdown, _) = tokio::sync::broadcast::channel(1);
+        let connections = Arc::new(Semaphore::new(cfg.max_connections));
+        let comp = CompanionCommunicationHandler {
+            cfg: Arc::new(cfg),
+            players,
+            bots,
+            shutdown,
+            active: Arc::new(AtomicUsize::new(0)),
+            connections,
+            users: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        };
 
-        tokio::spawn(comp.clone().run(cfg));
+        tokio::spawn(comp.clone().run());
         comp
     }
 
-    async fn run(self, cfg: NetConfig) {
+    ///Notifies every Companion about the Shutdown and waits for them to disconnect
+    /// - Waits at most drain_timeout
+    pub async fn shutdown(&self) {
+        let active = self.active.load(Ordering::SeqCst);
+        info!("Shutting down Companion Connections: {}", active);
+        self.shutdown.send(()).ok();
+
+        let drain = async {
+            while self.active.load(Ordering::SeqCst) > 0 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        };
+        let timeout = Duration::from_secs(self.cfg.drain_timeout);
+        if tokio::time::timeout(timeout, drain).await.is_err() {
+            warn!(
+                "Drain Timeout reached. Remaining Connections: {}",
+                self.active.load(Ordering::SeqCst)
+            );
+        }
+    }
+
+    async fn run(self) {
+        let cfg = self.cfg.clone();
         info!("Starting Net Receiver Loop. {:?}", cfg);
-        let addr = SocketAddrV4::new(cfg.address, cfg.port);
+        let addr = SocketAddr::new(cfg.address, cfg.port);
+        let acceptor = match (&cfg.cert, &cfg.key) {
+            (Some(cert), Some(key)) => match load_tls_acceptor(cert, key) {
+                Ok(a) => Some(a),
+                Err(e) => {
+                    let msg = format!("Error building TLS Acceptor. {:?}", e);
+                    error!("{}", msg);
+                    panic!("{}", msg);
+                }
+            },
+            (None, None) => None,
+            _ => {
+                let msg = "TLS requires both cert and key to be set".to_string();
+                error!("{}", msg);
+                panic!("{}", msg);
+            }
+        };
         let lis_res = TcpListener::bind(addr).await;
         let listener = match lis_res {
             Ok(l) => l,
@@ -53,7 +131,12 @@ impl CompanionCommunicationHandler {
                 panic!("{}", msg);
             }
         };
-        info!("Listening now: {:?}", addr);
+        let family = if addr.is_ipv6() { "IPv6" } else { "IPv4" };
+        if acceptor.is_some() {
+            info!("Listening now (TLS, {}): {:?}", family, addr);
+        } else {
+            info!("Listening now (Plaintext, {}): {:?}", family, addr);
+        }
 
         while let Ok((stream, _)) = listener.accept().await {
             let peer_res = stream.peer_addr();
@@ -64,9 +147,46 @@ impl CompanionCommunicationHandler {
                     continue;
                 }
             };
+            if let Some(allowed) = &cfg.allowed_ips {
+                //IPv4 mapped IPv6 peers of a dual-stack socket count as their IPv4 address
+                let mapped = match peer.ip() {
+                    IpAddr::V6(ip) => ip.to_ipv4_mapped().map(IpAddr::V4),
+                    IpAddr::V4(_) => None,
+                };
+                if !allowed.contains(&peer.ip())
+                    && !mapped.map_or(false, |ip| allowed.contains(&ip))
+                {
+                    warn!("Rejected Peer, not in IP Allowlist. {:?}", peer);
+                    continue;
+                }
+            }
             info!("Connection from Peer: {:?}", peer);
 
-            tokio::spawn(self.clone().handle_connection(peer, stream));
+            //Taken before any Handshake and held for the whole Connection
+            let permit = self.connections.clone().try_acquire_owned().ok();
+            //TLS and WebSocket Handshake together
+            let deadline = tokio::time::Instant::now() + HANDSHAKE_TIMEOUT;
+            match acceptor.clone() {
+                None => {
+                    tokio::spawn(
+                        self.clone()
+                            .handle_connection(peer, stream, permit, deadline),
+                    );
+                }
+                Some(acceptor) => {
+                    let s = self.clone();
+                    tokio::spawn(async move {
+                        match tokio::time::timeout_at(deadline, acceptor.accept(stream)).await {
+                            Ok(Ok(tls_stream)) => {
+                                s.handle_connection(peer, tls_stream, permit, deadline)
+                                    .await
+                            }
+                            Ok(Err(e)) => warn!("TLS Handshake Error. {:?}, {:?}", peer, e),
+                            Err(_) => warn!("TLS Handshake timed out. {:?}", peer),
+                        }
+                    });
+                }
+            }
         }
 
         let msg = format!("Tcp Listener Ended. {:?}", addr);
@@ -74,42 +194,230 @@ impl CompanionCommunicationHandler {
         panic!("{}", msg);
     }
 
-    async fn handle_connection(self, peer: SocketAddr, stream: TcpStream) {
-        let ws_stream_res = accept_async(stream).await;
-        let ws_stream = match ws_stream_res {
-            Ok(ws) => ws,
-            Err(e) => {
+    ///Serves a Connection, Peers without a Permit only get the Close Frame
+    async fn handle_connection<S>(
+        self,
+        peer: SocketAddr,
+        stream: S,
+        permit: Option<OwnedSemaphorePermit>,
+        deadline: tokio::time::Instant,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let stream: Box<dyn CompanionStream> = Box::new(stream);
+        let allowed_origins = self.cfg.allowed_origins.clone();
+        let check_origin = move |req: &Request, res: Response| {
+            let allowed = match allowed_origins {
+                None => return Ok(res),
+                Some(allowed) => allowed,
+            };
+            let origin = req
+                .headers()
+                .get(ORIGIN)
+                .and_then(|o| o.to_str().ok())
+                .unwrap_or_default();
+            if allowed.iter().any(|a| a.eq(origin)) {
+                return Ok(res);
+            }
+            warn!(
+                "Rejected Handshake, Origin not allowed. {:?}, {:?}",
+                peer, origin
+            );
+            let mut reject = ErrorResponse::new(Some("Origin not allowed".to_string()));
+            *reject.status_mut() = StatusCode::FORBIDDEN;
+            Err(reject)
+        };
+        let ws_stream_res =
+            tokio::time::timeout_at(deadline, accept_hdr_async(stream, check_origin)).await;
+        let mut ws_stream = match ws_stream_res {
+            Ok(Ok(ws)) => ws,
+            Ok(Err(e)) => {
                 error!(
                     "Error getting WebSocketStream for Peer. {:?}, {:?}",
                     peer, e
                 );
                 return;
             }
+            Err(_) => {
+                warn!("WebSocket Handshake timed out. {:?}", peer);
+                return;
+            }
         };
+        if permit.is_none() {
+            warn!("Too many Connections, rejecting Peer. {:?}", peer);
+            let frame = CloseReason::TooManyConnections.frame();
+            tokio::time::timeout(Duration::from_secs(1), ws_stream.close(Some(frame)))
+                .await
+                .ok();
+            return;
+        }
         info!("Got WebSocket connection: {:?}", peer);
+        let active = self.active.clone();
+        active.fetch_add(1, Ordering::SeqCst);
         ClientConnection::run(ws_stream, peer, self).await;
+        active.fetch_sub(1, Ordering::SeqCst);
         info!("WebSocket connection ended: {:?}", peer);
     }
 }
 
-type WsStream = WebSocketStream<TcpStream>;
+///Why the Server closed a Connection, sent in the Close Frame so Clients can react
+/// - Application Codes are from the 4000-4999 range for private use
+#[derive(Copy, Clone, Debug)]
+enum CloseReason {
+    ///The Client sent an End Request
+    Ended,
+    ServerShutdown,
+    ProtocolError,
+    HeartbeatTimeout,
+    TooManyConnections,
+    TooManyUserConnections,
+    AuthTimeout,
+}
+
+impl CloseReason {
+    fn code(self) -> CloseCode {
+        match self {
+            CloseReason::Ended => CloseCode::Normal,
+            CloseReason::ServerShutdown => CloseCode::from(4000),
+            CloseReason::ProtocolError => CloseCode::from(4001),
+            CloseReason::HeartbeatTimeout => CloseCode::from(4002),
+            CloseReason::TooManyConnections => CloseCode::from(4003),
+            CloseReason::TooManyUserConnections => CloseCode::from(4004),
+            CloseReason::AuthTimeout => CloseCode::from(4005),
+        }
+    }
+
+    fn reason(self) -> &'static str {
+        match self {
+            CloseReason::Ended => "Connection ended",
+            CloseReason::ServerShutdown => "Server is shutting down",
+            CloseReason::ProtocolError => "WebSocket Protocol Error",
+            CloseReason::HeartbeatTimeout => "No Pong received in time",
+            CloseReason::TooManyConnections => "Too many Connections",
+            CloseReason::TooManyUserConnections => "Too many Connections for this User",
+            CloseReason::AuthTimeout => "Not authenticated in time",
+        }
+    }
+
+    fn frame(self) -> CloseFrame<'static> {
+        CloseFrame {
+            code: self.code(),
+            reason: self.reason().into(),
+        }
+    }
+}
+
+fn user_id(user: &User) -> Option<UserId> {
+    user.id.parse::<u64>().ok().map(UserId)
+}
+
+const SEARCH_RESULT_TTL: Duration = Duration::from_secs(60);
+///Longest a Peer may take for the TLS and WebSocket Handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const VOICE_STATE_RESYNC_INTERVAL: Duration = Duration::from_secs(30);
+///Channel Members are sent at most this often
+const CHANNEL_MEMBERS_INTERVAL: Duration = Duration::from_secs(2);
+///Crowded Channels only send this many Members
+const CHANNEL_MEMBERS_LIMIT: usize = 50;
+///Most Players listed for Admins, the busiest ones first
+const LIST_PLAYERS_LIMIT: usize = 100;
+///First and longest wait between looking for the Player of a Channel, doubling in between
+const PLAYER_WAIT_MIN: Duration = Duration::from_millis(500);
+const PLAYER_WAIT_MAX: Duration = Duration::from_secs(30);
+///Stop looking for a Player after this, until the Voice State changes or the Client asks for a Resync
+const PLAYER_WAIT_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+///Any Stream a Companion can be connected with (Plaintext or TLS)
+trait CompanionStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> CompanionStream for S {}
+
+fn load_tls_acceptor(cert: &str, key: &str) -> Result<TlsAcceptor, String> {
+    let cert_chain = certs(&mut BufReader::new(
+        File::open(cert).map_err(|e| format!("{}, file: {}", e, cert))?,
+    ))
+    .map_err(|_| format!("Invalid Certificate, file: {}", cert))?;
+
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(
+        File::open(key).map_err(|e| format!("{}, file: {}", e, key))?,
+    ))
+    .map_err(|_| format!("Invalid Key, file: {}", key))?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut BufReader::new(
+            File::open(key).map_err(|e| format!("{}, file: {}", e, key))?,
+        ))
+        .map_err(|_| format!("Invalid Key, file: {}", key))?;
+    }
+    let key = keys
+        .drain(..)
+        .next()
+        .ok_or_else(|| format!("No Key found, file: {}", key))?;
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(cert_chain, key)
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+type WsStream = WebSocketStream<Box<dyn CompanionStream>>;
 type ArcPlayer = Arc<RwLock<Option<Player>>>;
+type SearchResults = (Instant, Vec<lavalink_rs::model::Track>);
 
 #[derive(Clone)]
 struct ClientConnection {
     write: Arc<Mutex<SplitSink<WsStream, TungMessage>>>,
     com: Arc<CompanionCommunicationHandler>,
-    peer: SocketAddr,
+    id: ConnectionId,
     user: Arc<RwLock<Option<(User, AccessToken)>>>,
     voice_state: Arc<RwLock<Option<(GuildId, ChannelId)>>>,
+    ///Voice Channel the User was in before, its Player may follow the User with Move Here
+    previous_voice_state: Arc<RwLock<Option<(GuildId, ChannelId)>>>,
     player_state_sender: Arc<Mutex<Option<JoinHandle<()>>>>,
     voice_state_sender: Arc<Mutex<Option<JoinHandle<()>>>>,
+    search_results: Arc<RwLock<Option<SearchResults>>>,
+    ping_sender: Arc<Mutex<Option<JoinHandle<()>>>>,
+    last_pong: Arc<Mutex<Instant>>,
+    dead: Arc<Notify>,
+    control_bucket: Arc<std::sync::Mutex<TokenBucket>>,
+    search_bucket: Arc<std::sync::Mutex<TokenBucket>>,
+    selected_guild: Arc<RwLock<Option<GuildId>>>,
+    guild_selected: Arc<Notify>,
+    encoding: Arc<std::sync::Mutex<Option<Encoding>>>,
+    kicked: Arc<Notify>,
+    state_versions: Arc<StateVersions>,
+    ///Asks the Player State Sender for a Full State
+    resync: Arc<Notify>,
+    ///Whether the Compression of a Full State was logged already
+    compression_logged: Arc<AtomicBool>,
+}
+
+///Identifies a Connection in Logs, the Peer alone collides behind NAT
+#[derive(Copy, Clone)]
+struct ConnectionId {
+    uuid: Uuid,
+    peer: SocketAddr,
+}
+
+impl Debug for ConnectionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Connection({}, {:?})", self.uuid, self.peer)
+    }
+}
+
+///Frame Encoding of a Companion, Binary is MessagePack
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Encoding {
+    Binary,
+    Json,
 }
 
 impl ClientConnection {
     pub async fn run(ws: WsStream, peer: SocketAddr, com: CompanionCommunicationHandler) {
         let (tx, rx) = ws.split();
         let handler = Self::new(tx, peer, com);
+        info!("Starting Connection. {:?}", handler.id);
+        *handler.ping_sender.lock().await = Some(tokio::spawn(handler.clone().ping_run()));
         handler.receive_run(rx).await
     }
 
@@ -118,100 +426,286 @@ impl ClientConnection {
         peer: SocketAddr,
         com: CompanionCommunicationHandler,
     ) -> Self {
+        let control_bucket = TokenBucket::new(com.cfg.control_rate, com.cfg.control_burst);
+        let search_bucket = TokenBucket::new(com.cfg.search_rate, com.cfg.search_burst);
         ClientConnection {
             write: Arc::new(Mutex::new(send)),
             com: Arc::new(com),
-            peer,
+            id: ConnectionId {
+                uuid: Uuid::new_v4(),
+                peer,
+            },
             user: Arc::new(RwLock::new(None)),
             voice_state: Arc::new(RwLock::new(None)),
+            previous_voice_state: Arc::new(RwLock::new(None)),
             player_state_sender: Arc::new(Mutex::new(None)),
             voice_state_sender: Arc::new(Mutex::new(None)),
+            search_results: Arc::new(RwLock::new(None)),
+            ping_sender: Arc::new(Mutex::new(None)),
+            last_pong: Arc::new(Mutex::new(Instant::now())),
+            dead: Arc::new(Notify::new()),
+            control_bucket: Arc::new(std::sync::Mutex::new(control_bucket)),
+            search_bucket: Arc::new(std::sync::Mutex::new(search_bucket)),
+            selected_guild: Arc::new(RwLock::new(None)),
+            guild_selected: Arc::new(Notify::new()),
+            encoding: Arc::new(std::sync::Mutex::new(None)),
+            kicked: Arc::new(Notify::new()),
+            state_versions: Arc::new(StateVersions::default()),
+            resync: Arc::new(Notify::new()),
+            compression_logged: Arc::new(AtomicBool::new(false)),
         }
     }
 
     async fn receive_run(self, mut receive: SplitStream<WsStream>) {
-        while let Some(res) = receive.next().await {
+        let mut shutdown = self.com.shutdown.subscribe();
+        //Authenticated Connections are covered by the Heartbeat instead
+        let auth_timeout = tokio::time::sleep(Duration::from_secs(self.com.cfg.auth_timeout));
+        tokio::pin!(auth_timeout);
+        loop {
+            let unauthenticated = self.user.read().await.is_none();
+            let res = tokio::select! {
+                res = receive.next() => match res {
+                    Some(res) => res,
+                    None => break,
+                },
+                _ = &mut auth_timeout, if unauthenticated => {
+                    info!("Connection did not authenticate in time. Closing. {:?}", self.id);
+                    self.send_close(CloseReason::AuthTimeout).await;
+                    break;
+                }
+                _ = self.kicked.notified() => {
+                    info!("Connection was closed by the Server. {:?}", self.id);
+                    break;
+                }
+                _ = self.dead.notified() => {
+                    warn!("Connection is dead. Closing. {:?}", self.id);
+                    self.send_close(CloseReason::HeartbeatTimeout).await;
+                    break;
+                }
+                _ = shutdown.recv() => {
+                    info!("Server is shutting down. Closing. {:?}", self.id);
+                    self.clone().sync_respond(Message::Shutdown()).await;
+                    self.send_close(CloseReason::ServerShutdown).await;
+                    break;
+                }
+            };
             let msg = match res {
                 Ok(m) => m,
                 Err(e) => {
-                    warn!("WebSocket Receive Error. {:?}, {:?}", self.peer, e);
+                    warn!("WebSocket Receive Error. {:?}, {:?}", self.id, e);
+                    self.send_close(CloseReason::ProtocolError).await;
                     break;
                 }
             };
-            let bin = match msg {
-                TungMessage::Binary(b) => b,
+            let (bin, encoding) = match msg {
+                TungMessage::Binary(b) => (b, Encoding::Binary),
+                TungMessage::Text(t) => (t.into_bytes(), Encoding::Json),
                 TungMessage::Close(c) => {
-                    info!("Received Close WebSocket Message. {:?}, {:?}", self.peer, c);
+                    info!("Received Close WebSocket Message. {:?}, {:?}", self.id, c);
                     break;
                 }
+                TungMessage::Pong(_) => {
+                    *self.last_pong.lock().await = Instant::now();
+                    continue;
+                }
                 _ => continue,
             };
-            let msg_res = Message::parse(bin.as_slice());
+            if self.detect_encoding(encoding) {
+                //Lets Users report Issues with the matching Id, sent once the Encoding is known
+                self.clone()
+                    .sync_respond(Message::ConnectionId(self.id.uuid.to_string()))
+                    .await;
+            }
+            let msg_res = match encoding {
+                Encoding::Binary => Message::parse(bin.as_slice()).map_err(|e| e.to_string()),
+                Encoding::Json => {
+                    serde_json::from_slice::<Message>(bin.as_slice()).map_err(|e| e.to_string())
+                }
+            };
             let msg = match msg_res {
                 Ok(m) => m,
                 Err(e) => {
-                    warn!("Message Parse Error. {:?}, {:?}", self.peer, e);
-                    self.respond(Message::Unexpected(Unexpected::ParseError(
-                        bin,
-                        format!("{:?}", e),
-                    )));
+                    warn!("Message Parse Error. {:?}, {:?}", self.id, e);
+                    let detail = parse_error_detail(bin.len(), &e);
+                    self.respond(Message::Unexpected(Unexpected::ParseError(bin, detail)));
                     continue;
                 }
             };
             if let Message::ClientRequest(req) = msg {
                 match req {
                     ClientRequest::Authenticate(a) => self.auth(a).await,
+                    ClientRequest::AuthenticateRefresh(r) => self.auth_refresh(r).await,
                     ClientRequest::AuthStatus() => self.send_auth_status(),
                     ClientRequest::Control(uuid, con) => self.handle_control_req(uuid, con),
+                    ClientRequest::Search(query) => self.handle_search_req(query),
+                    ClientRequest::Resync() => self.handle_resync_req(),
+                    ClientRequest::Lyrics() => self.handle_lyrics_req(),
+                    ClientRequest::NowPlaying() => self.handle_now_playing_req(),
+                    ClientRequest::History(max) => self.handle_history_req(max),
+                    ClientRequest::SavePlaylist(name) => self.handle_save_playlist_req(name),
+                    ClientRequest::LoadPlaylist(name) => self.handle_load_playlist_req(name),
+                    ClientRequest::Playlists() => self.handle_playlists_req(),
+                    ClientRequest::SelectGuild(guild) => self.select_guild(guild).await,
+                    ClientRequest::ListPlayers() => self.handle_list_players_req(),
                     ClientRequest::End() => {
-                        info!("Received End Request. {:?}", self.peer);
+                        info!("Received End Request. {:?}", self.id);
+                        self.send_close(CloseReason::Ended).await;
                         break;
                     }
                 }
             } else {
-                warn!("Received Unexpected Message. {:?}, {:?}", self.peer, msg);
+                warn!("Received Unexpected Message. {:?}, {:?}", self.id, msg);
                 self.respond(Message::Unexpected(Unexpected::MessageType(
                     msg.to_string(),
                 )));
             }
         }
         //Closing other threads because receive failed
+        if let Some(ps) = self.ping_sender.lock().await.take() {
+            debug!("Ending Ping Sender. {:?}", self.id);
+            ps.abort();
+        }
         if let Some(vss) = self.voice_state_sender.lock().await.take() {
-            debug!("Ending Voice State Sender. {:?}", self.peer);
+            debug!("Ending Voice State Sender. {:?}", self.id);
             vss.abort();
         }
         if let Some(pss) = self.player_state_sender.lock().await.take() {
-            debug!("Ending Player State Sender. {:?}", self.peer);
+            debug!("Ending Player State Sender. {:?}", self.id);
             pss.abort();
         }
+        if let Some(user) = self
+            .user
+            .read()
+            .await
+            .as_ref()
+            .and_then(|(u, _)| user_id(u))
+        {
+            self.unregister_user(user);
+        }
+    }
+
+    ///Closes the Connection with a Reason, the receive loop ends right after
+    async fn close(&self, reason: CloseReason) {
+        self.send_close(reason).await;
+        self.kicked.notify_one();
+    }
+
+    ///Sends the Close Frame and flushes it, before the Writer gets dropped
+    async fn send_close(&self, reason: CloseReason) {
+        debug!("Sending Close Frame. {:?}, {:?}", self.id, reason);
+        let mut write = self.write.lock().await;
+        let res = tokio::time::timeout(Duration::from_secs(1), async {
+            write.send(TungMessage::Close(Some(reason.frame()))).await?;
+            write.close().await
+        })
+        .await;
+        if let Ok(Err(e)) = res {
+            debug!("Error sending Close Frame. {:?}, {:?}", self.id, e);
+        }
+    }
+
+    ///Counts the Connection for the User
+    /// - Returns false, if the User already has too many Connections
+    fn register_user(&self, user: UserId) -> bool {
+        let mut users = self.com.users.lock().unwrap();
+        let count = users.entry(user).or_insert(0);
+        if *count >= self.com.cfg.max_user_connections {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    fn unregister_user(&self, user: UserId) {
+        let mut users = self.com.users.lock().unwrap();
+        if let Some(count) = users.get_mut(&user) {
+            *count -= 1;
+            if *count == 0 {
+                users.remove(&user);
+            }
+        }
+    }
+
+    async fn ping_run(self) {
+        let interval = Duration::from_secs(self.com.cfg.ping_interval);
+        let timeout = Duration::from_secs(self.com.cfg.ping_timeout);
+        loop {
+            tokio::time::sleep(interval).await;
+            if self.last_pong.lock().await.elapsed() > timeout {
+                warn!("Ping Timeout. {:?}, {:?}", self.id, timeout);
+                self.dead.notify_one();
+                return;
+            }
+
+            let res = self
+                .write
+                .lock()
+                .await
+                .send(TungMessage::Ping(Vec::new()))
+                .await;
+            if let Err(e) = res {
+                warn!("Send Ping Error. {:?}, {:?}", self.id, e);
+            }
+        }
+    }
+
+    ///Takes a Token from the responsible Bucket
+    /// - Returns false, if the request should be rejected
+    fn rate_limit(&self, search: bool) -> bool {
+        let bucket = if search {
+            &self.search_bucket
+        } else {
+            &self.control_bucket
+        };
+        bucket.lock().map(|mut b| b.try_take()).unwrap_or(false)
     }
 
     fn handle_control_req(&self, uuid: String, con: PlayerControl) {
-        info!("Handling Control Request. {:?}, {:?}", self.peer, con);
+        info!("Handling Control Request. {:?}, {:?}", self.id, con);
+        let search = matches!(
+            con,
+            PlayerControl::Enqueue(_) | PlayerControl::EnqueuePlaylist(_)
+        );
+        if !self.rate_limit(search) {
+            warn!("Control Request was rate limited. {:?}, {:?}", self.id, con);
+            self.respond(Message::ClientControlResult(PlayerControlResult {
+                uuid,
+                req: con,
+                res: Err("rate limited".to_string()),
+                info: None,
+            }));
+            return;
+        }
         let s = self.clone();
         tokio::spawn(async move {
             let mut control_result = PlayerControlResult {
                 uuid,
                 req: con.clone(),
                 res: Ok(()),
+                info: None,
             };
 
-            let vs_op = *s.voice_state.read().await;
-            let (guild, channel) = match vs_op {
-                None => {
-                    warn!("There is no player to control. {:?}, {:?}", s.peer, con);
-                    control_result.res = Err("No Bot in Channel".to_string());
+            let actual = async {
+                match s.user_id().await {
+                    Some(user) => s.user_voice_channel(&user).await,
+                    None => None,
+                }
+            };
+            let (guild, channel) = match control_channel(&s.voice_state, actual).await {
+                Ok(vs) => vs,
+                Err(e) => {
+                    warn!("Can not control a Player. {:?}, {:?}, {:?}", s.id, con, e);
+                    control_result.res = Err(e);
                     s.sync_respond(Message::ClientControlResult(control_result))
                         .await;
                     return;
                 }
-                Some(vs) => vs,
             };
 
             let player_manager_op = s.com.players.get(&guild);
             let player_manager = match player_manager_op {
                 None => {
-                    error!("Got no Player Manager for Guild. {:?}, {:?}", s.peer, guild);
+                    error!("Got no Player Manager for Guild. {:?}, {:?}", s.id, guild);
                     control_result.res = Err("Internal Error".to_string());
                     s.sync_respond(Message::ClientControlResult(control_result))
                         .await;
@@ -220,20 +714,70 @@ impl ClientConnection {
                 Some(pm) => pm.clone(),
             };
 
+            //Check DJ Role for restricted Controls
+            if let Some(permission) = control_permission(&con) {
+                let allowed = match s.user_id().await {
+                    //Admins may stop every Player of a Guild, even without a DJ Role
+                    Some(user)
+                        if permission == Permission::PauseAll
+                            && s.com.cfg.admins.contains(&user.0) =>
+                    {
+                        true
+                    }
+                    Some(user) => player_manager.is_allowed(&user, permission).await,
+                    None => false,
+                };
+                if !allowed {
+                    warn!("Missing DJ Role. {:?}, {:?}", s.id, con);
+                    control_result.res = Err("Missing DJ Role".to_string());
+                    s.sync_respond(Message::ClientControlResult(control_result))
+                        .await;
+                    return;
+                }
+            }
+
+            //Enqueue Cooldown of the User
+            let enqueue = matches!(
+                con,
+                PlayerControl::Enqueue(_)
+                    | PlayerControl::EnqueuePlaylist(_)
+                    | PlayerControl::EnqueueIndex(_)
+            );
+            if enqueue {
+                let cooldown = match s.user_id().await {
+                    Some(user) => player_manager.enqueue_cooldown(user).await,
+                    None => Ok(()),
+                };
+                if let Err(e) = cooldown {
+                    warn!("Enqueue rejected. {:?}, {:?}", s.id, e);
+                    control_result.res = Err(e.to_string());
+                    s.sync_respond(Message::ClientControlResult(control_result))
+                        .await;
+                    return;
+                }
+            }
+
             let res = match con {
                 PlayerControl::Resume() => {
-                    player_manager
-                        .request(PlayerRequest::PauseResume(channel))
-                        .await
+                    player_manager.request(PlayerRequest::Resume(channel)).await
                 }
                 PlayerControl::Pause() => {
-                    player_manager
-                        .request(PlayerRequest::PauseResume(channel))
-                        .await
+                    player_manager.request(PlayerRequest::Pause(channel)).await
                 }
                 PlayerControl::Skip(i) => {
+                    let user = s.user_id().await;
+                    match user {
+                        Some(user) => player_manager.user_skip(channel, user, i).await,
+                        _ => {
+                            player_manager
+                                .request(PlayerRequest::Skip(i, channel))
+                                .await
+                        }
+                    }
+                }
+                PlayerControl::SkipTo(index) => {
                     player_manager
-                        .request(PlayerRequest::Skip(i, channel))
+                        .request(PlayerRequest::SkipTo(index, channel))
                         .await
                 }
                 PlayerControl::BackSkip(i) => {
@@ -241,72 +785,504 @@ impl ClientConnection {
                         .request(PlayerRequest::BackSkip(i, channel))
                         .await
                 }
+                PlayerControl::PlayNext(index) => {
+                    player_manager
+                        .request(PlayerRequest::PlayNext(index, channel))
+                        .await
+                }
+                PlayerControl::ReplayLast() => {
+                    player_manager
+                        .request(PlayerRequest::ReplayLast(channel))
+                        .await
+                }
                 PlayerControl::SetTime(pos) => {
                     player_manager
                         .request(PlayerRequest::Jump(pos, channel))
                         .await
                 }
+                PlayerControl::SeekPercent(pct) => {
+                    player_manager
+                        .request(PlayerRequest::JumpPercent(pct, channel))
+                        .await
+                }
+                PlayerControl::SeekFraction(fraction) => {
+                    player_manager
+                        .request(PlayerRequest::JumpFraction(fraction, channel))
+                        .await
+                }
                 PlayerControl::PlayMode(mode) => {
                     player_manager
-                        .request(PlayerRequest::Playback(parse_mode(mode), channel))
+                        .request(PlayerRequest::SetPlayback(parse_mode(mode), channel))
                         .await
                 }
                 PlayerControl::Enqueue(url) => {
+                    let res = player_manager.search(channel, url.into()).await;
+                    match res {
+                        Ok((_, mut tracks)) => player_manager
+                            .enqueue(channel, tracks.tracks.drain(..).take(1).collect())
+                            .await
+                            .map(|enqueued| control_result.info = Some(enqueued.to_string())),
+                        Err(e) => Err(e),
+                    }
+                }
+                PlayerControl::EnqueuePlaylist(url) => {
                     let res = player_manager.search(channel, url.into()).await;
                     match res {
                         Ok((_, mut tracks)) => {
+                            //Only enqueue everything, if we actually got a playlist
+                            let tracks: Vec<_> = if tracks.load_type.eq(PLAYLIST_LOADED) {
+                                tracks.tracks.drain(..).collect()
+                            } else {
+                                tracks.tracks.drain(..).take(1).collect()
+                            };
                             player_manager
-                                .request(PlayerRequest::Enqueue(
-                                    tracks.drain(..).take(1).collect(),
-                                    channel,
-                                ))
+                                .enqueue(channel, tracks)
                                 .await
+                                .map(|enqueued| control_result.info = Some(enqueued.to_string()))
                         }
                         Err(e) => Err(e),
                     }
                 }
+                PlayerControl::EnqueueIndex(index) => {
+                    let results = s.search_results.read().await.clone();
+                    match results {
+                        Some((when, tracks)) if when.elapsed() < SEARCH_RESULT_TTL => {
+                            match tracks.get(index) {
+                                Some(track) => player_manager
+                                    .enqueue(channel, vec![track.clone()])
+                                    .await
+                                    .map(|enqueued| {
+                                        control_result.info = Some(enqueued.to_string())
+                                    }),
+                                None => {
+                                    control_result.res =
+                                        Err("Invalid Search Result Index".to_string());
+                                    s.sync_respond(Message::ClientControlResult(control_result))
+                                        .await;
+                                    return;
+                                }
+                            }
+                        }
+                        _ => {
+                            control_result.res = Err("Search Results expired".to_string());
+                            s.sync_respond(Message::ClientControlResult(control_result))
+                                .await;
+                            return;
+                        }
+                    }
+                }
+                PlayerControl::SetShuffle(shuffle) => {
+                    player_manager
+                        .request(PlayerRequest::SetShuffle(shuffle, channel))
+                        .await
+                }
+                PlayerControl::SetFilter(filter) => {
+                    player_manager
+                        .request(PlayerRequest::Filter(filter.map(parse_filter), channel))
+                        .await
+                }
+                PlayerControl::SetSpeed(speed) => {
+                    player_manager
+                        .request(PlayerRequest::Speed(speed, channel))
+                        .await
+                }
+                PlayerControl::SetPitch(pitch) => {
+                    player_manager
+                        .request(PlayerRequest::Pitch(pitch, channel))
+                        .await
+                }
+                PlayerControl::Clear() => {
+                    player_manager
+                        .request(PlayerRequest::ClearQueue(channel))
+                        .await
+                }
+                PlayerControl::PauseAll() => {
+                    let (paused, failed) = player_manager.pause_all().await;
+                    control_result.info = Some(format!(
+                        "Paused {} Players, {} failed",
+                        paused,
+                        failed.len()
+                    ));
+                    Ok(())
+                }
+                PlayerControl::ResumeAll() => {
+                    let (resumed, failed) = player_manager.resume_all().await;
+                    control_result.info = Some(format!(
+                        "Resumed {} Players, {} failed",
+                        resumed,
+                        failed.len()
+                    ));
+                    Ok(())
+                }
                 PlayerControl::Leave() => player_manager.leave(channel).await,
+                PlayerControl::LeaveKeepQueue() => player_manager.leave_keep_queue(channel).await,
                 PlayerControl::Join() => player_manager.join(channel).await,
+                PlayerControl::MoveHere() => {
+                    let previous = *s.previous_voice_state.read().await;
+                    match previous {
+                        Some((previous_guild, from)) if previous_guild == guild => {
+                            player_manager.move_here(from, channel).await
+                        }
+                        _ => Err(PlayerMapError::NoPlayerToMove(channel)),
+                    }
+                }
             };
+            //Only a successful Enqueue starts the Cooldown
+            if enqueue && res.is_ok() {
+                if let Some(user) = s.user_id().await {
+                    player_manager.charge_enqueue_cooldown(user);
+                }
+            }
             if let Err(e) = res {
                 warn!(
                     "Player Control Error. {:?}, {:?}, {:?}, {:?}",
-                    s.peer, guild, channel, e
+                    s.id, guild, channel, e
                 );
-                control_result.res = Err(format!("{:?}", e));
+                control_result.res = Err(e.user_message().unwrap_or_else(|| format!("{:?}", e)));
             }
             s.sync_respond(Message::ClientControlResult(control_result))
                 .await;
         });
     }
 
+    fn handle_search_req(&self, query: String) {
+        info!("Handling Search Request. {:?}, {:?}", self.id, query);
+        if !self.rate_limit(true) {
+            warn!(
+                "Search Request was rate limited. {:?}, {:?}",
+                self.id, query
+            );
+            self.respond(Message::SearchResults(Err("rate limited".to_string())));
+            return;
+        }
+        let s = self.clone();
+        tokio::spawn(async move {
+            let vs_op = *s.voice_state.read().await;
+            let (guild, channel) = match vs_op {
+                None => {
+                    warn!("There is no player to search with. {:?}", s.id);
+                    s.sync_respond(Message::SearchResults(Err(
+                        "Not in a Voice Channel".to_string()
+                    )))
+                    .await;
+                    return;
+                }
+                Some(vs) => vs,
+            };
+
+            let player_manager_op = s.com.players.get(&guild);
+            let player_manager = match player_manager_op {
+                None => {
+                    error!("Got no Player Manager for Guild. {:?}, {:?}", s.id, guild);
+                    s.sync_respond(Message::SearchResults(Err("No Player".to_string())))
+                        .await;
+                    return;
+                }
+                Some(pm) => pm.clone(),
+            };
+
+            let search = player_manager.search(channel, query).await;
+            if let Err(e) = &search {
+                warn!(
+                    "Search Error. {:?}, {:?}, {:?}, {:?}",
+                    s.id, guild, channel, e
+                );
+            }
+            let tracks = match search_tracks(search) {
+                Ok(tracks) => tracks,
+                Err(e) => {
+                    s.sync_respond(Message::SearchResults(Err(e))).await;
+                    return;
+                }
+            };
+            //Drop Tracks without info, so indices match with what the client received
+            let tracks: Vec<_> = tracks.into_iter().filter(|t| t.info.is_some()).collect();
+            let results = tracks.iter().map(|t| parse_track(t)).flatten().collect();
+            *s.search_results.write().await = Some((Instant::now(), tracks));
+
+            s.sync_respond(Message::SearchResults(Ok(results))).await;
+        });
+    }
+
+    fn handle_lyrics_req(&self) {
+        info!("Handling Lyrics Request. {:?}", self.id);
+        if !self.rate_limit(true) {
+            warn!("Lyrics Request was rate limited. {:?}", self.id);
+            self.respond(Message::Lyrics(Err("rate limited".to_string())));
+            return;
+        }
+        let s = self.clone();
+        tokio::spawn(async move {
+            let res = match *s.voice_state.read().await {
+                None => Err("Not in a Voice Channel".to_string()),
+                Some((guild, channel)) => match s.com.players.get(&guild) {
+                    None => Err("No Player".to_string()),
+                    Some(pm) => pm
+                        .lyrics(channel)
+                        .await
+                        .map(|(_, lyrics)| lyrics)
+                        .map_err(|e| format!("{}", e)),
+                },
+            };
+            s.sync_respond(Message::Lyrics(res)).await;
+        });
+    }
+
+    ///Responds with only the current Track of the Player in the Users Channel
+    fn handle_now_playing_req(&self) {
+        info!("Handling Now Playing Request. {:?}", self.id);
+        let s = self.clone();
+        tokio::spawn(async move {
+            let player = match *s.voice_state.read().await {
+                None => None,
+                Some((guild, channel)) => match s.com.players.get(&guild) {
+                    None => None,
+                    Some(pm) => pm.get_player(&channel).await,
+                },
+            };
+            let watch = match player {
+                None => None,
+                Some((_, player)) => player.read().await.as_ref().map(|p| p.get_status_watch()),
+            };
+            let current = watch.and_then(|watch| {
+                let ps = watch.borrow().clone();
+                let (_, track) = ps.current.as_ref()?;
+                parse_cur_track(ps.position()?, track)
+            });
+            s.sync_respond(Message::NowPlaying(current)).await;
+        });
+    }
+
+    ///Responds with the most recently played Tracks, newest first
+    /// - At most max Tracks, empty if there is no Player
+    fn handle_history_req(&self, max: usize) {
+        info!("Handling History Request. {:?}, Max: {}", self.id, max);
+        let s = self.clone();
+        tokio::spawn(async move {
+            let player = match *s.voice_state.read().await {
+                None => None,
+                Some((guild, channel)) => match s.com.players.get(&guild) {
+                    None => None,
+                    Some(pm) => pm.get_player(&channel).await,
+                },
+            };
+            let watch = match player {
+                None => None,
+                Some((_, player)) => player.read().await.as_ref().map(|p| p.get_status_watch()),
+            };
+            let history = match watch {
+                None => Vec::new(),
+                Some(watch) => watch
+                    .borrow()
+                    .history
+                    .iter()
+                    .take(max)
+                    .filter_map(|(t, reason)| {
+                        Some(HistoryTrack {
+                            track: parse_track(t)?,
+                            reason: (*reason).into(),
+                        })
+                    })
+                    .collect(),
+            };
+            s.sync_respond(Message::History(history)).await;
+        });
+    }
+
+    fn handle_save_playlist_req(&self, name: String) {
+        info!("Handling Save Playlist Request. {:?}, {:?}", self.id, name);
+        let s = self.clone();
+        tokio::spawn(async move {
+            let res = match (s.user_id().await, *s.voice_state.read().await) {
+                (Some(user), Some((guild, channel))) => match s.com.players.get(&guild) {
+                    None => Err("No Player".to_string()),
+                    Some(pm) => pm
+                        .save_playlist(channel, user, name)
+                        .await
+                        .map(|saved| format!("Saved {} Tracks", saved))
+                        .map_err(|e| format!("{}", e)),
+                },
+                (None, _) => Err("Not authenticated".to_string()),
+                (_, None) => Err("Not in a Voice Channel".to_string()),
+            };
+            s.sync_respond(Message::PlaylistResult(res)).await;
+        });
+    }
+
+    fn handle_load_playlist_req(&self, name: String) {
+        info!("Handling Load Playlist Request. {:?}, {:?}", self.id, name);
+        if !self.rate_limit(true) {
+            warn!("Load Playlist Request was rate limited. {:?}", self.id);
+            self.respond(Message::PlaylistResult(Err("rate limited".to_string())));
+            return;
+        }
+        let s = self.clone();
+        tokio::spawn(async move {
+            let res = match (s.user_id().await, *s.voice_state.read().await) {
+                (Some(user), Some((guild, channel))) => match s.com.players.get(&guild) {
+                    None => Err("No Player".to_string()),
+                    Some(pm) => pm
+                        .load_playlist(channel, user, &name)
+                        .await
+                        .map(|(added, failed)| {
+                            format!("Added {} Tracks, {} failed to resolve", added, failed)
+                        })
+                        .map_err(|e| format!("{}", e)),
+                },
+                (None, _) => Err("Not authenticated".to_string()),
+                (_, None) => Err("Not in a Voice Channel".to_string()),
+            };
+            s.sync_respond(Message::PlaylistResult(res)).await;
+        });
+    }
+
+    ///Responds with a Summary of every active Player across all Guilds
+    /// - Only for Users on the Admin list
+    fn handle_list_players_req(&self) {
+        info!("Handling List Players Request. {:?}", self.id);
+        let s = self.clone();
+        tokio::spawn(async move {
+            let admin = match s.user_id().await {
+                Some(user) => s.com.cfg.admins.contains(&user.0),
+                None => false,
+            };
+            if !admin {
+                warn!("List Players Request from non Admin. {:?}", s.id);
+                s.sync_respond(Message::Players(Err("Not an Admin".to_string())))
+                    .await;
+                return;
+            }
+
+            let mut players = Vec::new();
+            for (guild, pm) in s.com.players.iter() {
+                for (channel, state) in pm.active_players().await {
+                    players.push(PlayerSummary {
+                        guild: guild.0,
+                        channel: channel.0,
+                        bot: state.bot.0,
+                        current: state
+                            .current
+                            .as_ref()
+                            .zip(state.position())
+                            .and_then(|((_, track), pos)| parse_cur_track(pos, track)),
+                        queue: state.playlist.len(),
+                        listeners: s.com.bots.listener_count(&channel, guild).await,
+                    });
+                }
+            }
+            players.sort_by(|a, b| b.listeners.cmp(&a.listeners));
+            players.truncate(LIST_PLAYERS_LIMIT);
+            s.sync_respond(Message::Players(Ok(players))).await;
+        });
+    }
+
+    fn handle_playlists_req(&self) {
+        info!("Handling Playlists Request. {:?}", self.id);
+        let s = self.clone();
+        tokio::spawn(async move {
+            //Playlists belong to the User, so any Guild works for looking them up
+            let pm = match *s.voice_state.read().await {
+                Some((guild, _)) => s.com.players.get(&guild).cloned(),
+                None => s.com.players.values().next().cloned(),
+            };
+            let names = match (s.user_id().await, pm) {
+                (Some(user), Some(pm)) => pm.playlists(user).await.unwrap_or_else(|e| {
+                    warn!("Error listing Playlists. {:?}, {:?}", s.id, e);
+                    Vec::new()
+                }),
+                _ => Vec::new(),
+            };
+            s.sync_respond(Message::Playlists(names)).await;
+        });
+    }
+
+    ///Resyncs through the Player State Sender, so the Full State is ordered with its Patches
+    /// - Without a Sender there is no Channel and therefore no Player
+    fn handle_resync_req(&self) {
+        info!("Handling Resync Request. {:?}", self.id);
+        let s = self.clone();
+        tokio::spawn(async move {
+            if s.player_state_sender.lock().await.is_some() {
+                s.resync.notify_one();
+            } else {
+                s.send_state(None).await;
+            }
+        });
+    }
+
     async fn auth(&self, auth: Auth) {
         //Exchange Token
         let token_res = get_token(auth).await;
         let (access_token, refresh_token) = match token_res {
             Ok(token) => token,
             Err(e) => {
-                warn!("Auth Error. {:?}, {:?}", self.peer, e);
+                warn!("Auth Error. {:?}, {:?}", self.id, e);
+                self.clone()
+                    .sync_respond(Message::Auth(AuthMessage::AuthError()))
+                    .await;
+                return;
+            }
+        };
+        self.authenticated(access_token, refresh_token).await
+    }
+
+    async fn auth_refresh(&self, refresh: String) {
+        //Exchange Refresh Token
+        let token_res = refresh_token(refresh).await;
+        let (access_token, refresh_token) = match token_res {
+            Ok(token) => token,
+            Err(e) => {
+                warn!("Auth Refresh Error. {:?}, {:?}", self.id, e);
                 self.clone()
                     .sync_respond(Message::Auth(AuthMessage::AuthError()))
                     .await;
                 return;
             }
         };
+        self.authenticated(access_token, refresh_token).await
+    }
+
+    ///Finishes Authentication after a Token was acquired
+    async fn authenticated(&self, access_token: AccessToken, refresh_token: String) {
         //Get User
         let user_res = get_user_id(access_token.clone()).await;
         let user = match user_res {
             Ok(u) => u,
             Err(e) => {
-                warn!("Get Client Id Error. {:?}, {:?}", self.peer, e);
+                warn!("Get Client Id Error. {:?}, {:?}", self.id, e);
                 self.clone()
                     .sync_respond(Message::Auth(AuthMessage::AuthError()))
                     .await;
                 return;
             }
-        };
+        };
+        //Count the Connection, unless it is a Refresh for the same User
+        let new_id = match user_id(&user) {
+            Some(id) => id,
+            None => {
+                warn!("Invalid User Id. {:?}, {:?}", self.id, user);
+                self.clone()
+                    .sync_respond(Message::Auth(AuthMessage::AuthError()))
+                    .await;
+                return;
+            }
+        };
+        let mut user_lock = self.user.write().await;
+        let old_id = user_lock.as_ref().and_then(|(u, _)| user_id(u));
+        if old_id != Some(new_id) {
+            if !self.register_user(new_id) {
+                drop(user_lock);
+                warn!("Too many Connections for User. {:?}, {:?}", self.id, new_id);
+                self.close(CloseReason::TooManyUserConnections).await;
+                return;
+            }
+            if let Some(old_id) = old_id {
+                self.unregister_user(old_id);
+            }
+        }
         //Insert into own Struct
-        *self.user.write().await = Some((user.clone(), access_token));
+        *user_lock = Some((user.clone(), access_token));
+        drop(user_lock);
 
         //Send positive response
         self.clone()
@@ -315,7 +1291,7 @@ impl ClientConnection {
                 refresh_token,
             )))
             .await;
-        info!("Authenticated User: {:?}, {:?}", self.peer, self.user);
+        info!("Authenticated User: {:?}, {:?}", self.id, self.user);
 
         //remove old voice state sender if it exists
         let mut lock = self.voice_state_sender.lock().await;
@@ -342,59 +1318,117 @@ impl ClientConnection {
         tokio::spawn(self.clone().sync_respond(msg));
     }
 
+    ///Sticks with the Encoding of the first Message for the whole Connection
+    /// - Returns true, if this was the first Message
+    fn detect_encoding(&self, encoding: Encoding) -> bool {
+        let mut lock = self.encoding.lock().unwrap();
+        if lock.is_some() {
+            return false;
+        }
+        info!("Detected Encoding. {:?}, {:?}", self.id, encoding);
+        *lock = Some(encoding);
+        true
+    }
+
+    ///Encoding for Responses, Binary until the Client sent something
+    fn encoding(&self) -> Encoding {
+        self.encoding.lock().unwrap().unwrap_or(Encoding::Binary)
+    }
+
+    ///Sends a Player State, numbered so Clients can spot a missed one
+    async fn send_state(self, state: Option<State>) {
+        let msg = self.state_versions.message(state);
+        self.sync_respond(msg).await
+    }
+
     async fn sync_respond(self, msg: Message) {
-        let gen_res = msg.generate();
-        let bin = match gen_res {
-            Ok(b) => b,
+        let gen_res = match self.encoding() {
+            Encoding::Binary => msg
+                .generate()
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|bin| self.compress(&msg, bin))
+                .map(TungMessage::Binary),
+            Encoding::Json => serde_json::to_string(&msg)
+                .map(TungMessage::Text)
+                .map_err(|e| format!("{:?}", e)),
+        };
+        let frame = match gen_res {
+            Ok(f) => f,
             Err(e) => {
-                error!("Error Parsing Message. {:?}, {:?}, {:?}", self.peer, msg, e);
+                error!("Error Parsing Message. {:?}, {:?}, {:?}", self.id, msg, e);
                 return;
             }
         };
-
-        let res = self.write.lock().await.send(TungMessage::Binary(bin)).await;
+        let res = self.write.lock().await.send(frame).await;
         if let Err(e) = res {
-            warn!("Send Message Error. {:?}, {:?}", self.peer, e);
+            warn!("Send Message Error. {:?}, {:?}", self.id, e);
+        }
+    }
+
+    ///Deflates a Binary Message, if Compression is enabled
+    /// - Logs the Ratio of the first Full State, the largest Messages there are
+    fn compress(&self, msg: &Message, bin: Vec<u8>) -> Result<Vec<u8>, String> {
+        if !self.com.cfg.compression {
+            return Ok(bin);
+        }
+        let compressed = deflate(&bin).map_err(|e| format!("{:?}", e))?;
+        if let Message::PlayerState(_, Some(State::FullState(_))) = msg {
+            if !self.compression_logged.swap(true, Ordering::Relaxed) {
+                debug!(
+                    "Compressed Full State. {:?}, Bytes: {}, Compressed: {}, Ratio: {:.2}",
+                    self.id,
+                    bin.len(),
+                    compressed.len(),
+                    compressed.len() as f64 / bin.len().max(1) as f64
+                );
+            }
         }
+        Ok(compressed)
     }
 
     async fn voice_state_sender_run(self, user: User) {
-        info!("Starting Voice State Sender Run. {:?}", self.peer);
+        info!("Starting Voice State Sender Run. {:?}", self.id);
         let user_id_res = user.id.parse::<u64>();
         let user_id = match user_id_res {
             Ok(id) => UserId(id),
             Err(e) => {
-                error!("Parse User ID Error. {:?}, {:?}", self.peer, e);
+                error!("Parse User ID Error. {:?}, {:?}", self.id, e);
                 return;
             }
         };
 
         //Make sure, player sender is cleared
+        // and send the Node Status right away, until a Channel is joined
         let mut lock = self.player_state_sender.lock().await;
         if let Some(pss) = lock.take() {
-            info!("Stopping Player State Sender. {:?}", self.peer);
+            info!("Stopping Player State Sender. {:?}", self.id);
             pss.abort();
         }
+        *lock = Some(tokio::spawn(self.clone().node_status_sender_run(None)));
         drop(lock);
 
+        let mut updates = self.com.bots.voice_updates();
         let mut last_check = None;
 
         loop {
             //If nothing changed: continue
-            let new = self.com.bots.get_any_user_voice_channel(&user_id).await;
+            let new = self.user_voice_channel(&user_id).await;
             if new.eq(&last_check) {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+                self.wait_voice_update(&mut updates, &user_id).await;
                 continue;
             }
 
             //Something changed so we drop the current Player State Sender, if it exists
             let mut lock_state_sender = self.player_state_sender.lock().await;
             if let Some(pss) = lock_state_sender.take() {
-                info!("Stopping Player State Sender. {:?}", self.peer);
+                info!("Stopping Player State Sender. {:?}", self.id);
                 pss.abort();
             }
 
             //Replace last check channel, locally and behind the lock
+            if last_check.is_some() {
+                *self.previous_voice_state.write().await = last_check;
+            }
             last_check = new;
             *self.voice_state.write().await = new;
 
@@ -403,8 +1437,10 @@ impl ClientConnection {
                 None => {
                     //New Channel is none, so we just continue but send the voice_state first
                     self.send_voice_state(None);
+                    *lock_state_sender =
+                        Some(tokio::spawn(self.clone().node_status_sender_run(None)));
                     drop(lock_state_sender);
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    self.wait_voice_update(&mut updates, &user_id).await;
                     continue;
                 }
                 Some(ch) => ch,
@@ -413,14 +1449,103 @@ impl ClientConnection {
             //Send new VoiceState
             self.send_voice_state(Some((guild, new_channel)));
 
-            //Starting Player State Sender
-            *lock_state_sender = Some(tokio::spawn(
+            //Starting Player State Sender, together with the Node Status Sender of the Guild
+            // and the Channel Members Sender
+            let (player_state, node_status, members) = (
                 self.clone().player_state_sender_run(guild, new_channel),
-            ));
+                self.clone().node_status_sender_run(Some(guild)),
+                self.clone().channel_members_sender_run(guild, new_channel),
+            );
+            *lock_state_sender = Some(tokio::spawn(async move {
+                tokio::join!(player_state, node_status, members);
+            }));
 
             drop(lock_state_sender);
-            tokio::time::sleep(Duration::from_secs(1)).await;
+            self.wait_voice_update(&mut updates, &user_id).await;
+        }
+    }
+
+    ///Waits until the VoiceState of the User changed
+    /// - Returns after VOICE_STATE_RESYNC_INTERVAL regardless, so a missed Event can not strand us
+    /// - Also returns when another Guild was selected
+    async fn wait_voice_update(&self, updates: &mut BroadcastReceiver<UserId>, user: &UserId) {
+        let wait = async {
+            loop {
+                let update = tokio::select! {
+                    update = updates.recv() => update,
+                    _ = self.guild_selected.notified() => return,
+                };
+                match update {
+                    Ok(id) if id.eq(user) => return,
+                    Ok(_) => continue,
+                    Err(BroadcastRecvError::Lagged(_)) => return,
+                    Err(BroadcastRecvError::Closed) => futures::future::pending::<()>().await,
+                }
+            }
+        };
+        tokio::time::timeout(VOICE_STATE_RESYNC_INTERVAL, wait)
+            .await
+            .ok();
+    }
+
+    ///Id of the authenticated User
+    async fn user_id(&self) -> Option<UserId> {
+        let user = self.user.read().await.clone()?;
+        user.0.id.parse::<u64>().ok().map(UserId)
+    }
+
+    ///Voice Channel of the User, respecting the selected Guild
+    /// - Auto detects if no Guild was selected
+    async fn user_voice_channel(&self, user: &UserId) -> Option<(GuildId, ChannelId)> {
+        let selected = *self.selected_guild.read().await;
+        match selected {
+            None => self.com.bots.get_any_user_voice_channel(user).await,
+            Some(guild) => self
+                .com
+                .bots
+                .get_user_voice_state(user, &guild)
+                .await
+                .and_then(|vs| vs.channel_id)
+                .map(|ch| (guild, ch)),
+        }
+    }
+
+    async fn select_guild(&self, guild: u64) {
+        let guild = GuildId(guild);
+        info!("Handling Select Guild Request. {:?}, {:?}", self.id, guild);
+        let user_id = match self.user_id().await {
+            Some(id) => id,
+            None => {
+                warn!("Select Guild without Authentication. {:?}", self.id);
+                self.respond(Message::SelectGuildResult(Err(
+                    "Not Authenticated".to_string()
+                )));
+                return;
+            }
+        };
+
+        //Validate the User is actually in a Voice Channel of the Guild
+        let in_channel = self
+            .com
+            .bots
+            .get_user_voice_state(&user_id, &guild)
+            .await
+            .and_then(|vs| vs.channel_id)
+            .is_some();
+        if !in_channel || !self.com.players.contains_key(&guild) {
+            warn!(
+                "User is not in a Voice Channel of the Guild. {:?}, {:?}",
+                self.id, guild
+            );
+            self.respond(Message::SelectGuildResult(Err(
+                "Not in a Voice Channel of this Guild".to_string(),
+            )));
+            return;
         }
+
+        *self.selected_guild.write().await = Some(guild);
+        self.guild_selected.notify_one();
+        self.respond(Message::SelectGuildResult(Ok(guild.0)));
     }
 
     fn send_voice_state(&self, voice: Option<(GuildId, ChannelId)>) {
@@ -439,7 +1564,7 @@ impl ClientConnection {
             let bot = match bot_op {
                 Some(b) => b,
                 None => {
-                    error!("Could not find Bot for Guild. {:?}, {:?}", s.peer, guild);
+                    error!("Could not find Bot for Guild. {:?}, {:?}", s.id, guild);
                     return;
                 }
             };
@@ -450,7 +1575,7 @@ impl ClientConnection {
                 None => {
                     error!(
                         "Could not find Channel for Guild. {:?}, Bot: {:?}, {:?}, {:?}",
-                        s.peer,
+                        s.id,
                         bot.id(),
                         guild,
                         channel
@@ -468,132 +1593,308 @@ impl ClientConnection {
                     .map(|c| c.name)
                     .unwrap_or_else(|| channel.to_string()),
             };
-            debug!("Sending Voice State. {:?}, {:?}, {:?}", s.peer, guild, vs);
+            debug!("Sending Voice State. {:?}, {:?}, {:?}", s.id, guild, vs);
             s.respond(Message::UserVoiceState(Some(vs)));
         });
     }
 
+    ///Sends the non-bot Members of the Voice Channel, once right away and then on every change
+    /// - Voice Updates are coalesced, so a busy Guild does not flood the Companion
+    async fn channel_members_sender_run(self, guild: GuildId, channel: ChannelId) {
+        let mut updates = self.com.bots.voice_updates();
+        let mut last = None;
+        loop {
+            let members: Vec<_> = self
+                .com
+                .bots
+                .channel_members(&channel, &guild, CHANNEL_MEMBERS_LIMIT)
+                .await
+                .into_iter()
+                .map(|m| ChannelMember {
+                    id: m.user.id.0,
+                    avatar: m.user.face(),
+                    name: m.nick.unwrap_or(m.user.name),
+                })
+                .collect();
+            if last.as_ref() != Some(&members) {
+                debug!(
+                    "Sending Channel Members. {:?}, {:?}, Members: {}",
+                    self.id,
+                    channel,
+                    members.len()
+                );
+                self.clone()
+                    .sync_respond(Message::ChannelMembers(members.clone()))
+                    .await;
+                last = Some(members);
+            }
+
+            if let Err(BroadcastRecvError::Closed) = updates.recv().await {
+                return;
+            }
+            tokio::time::sleep(CHANNEL_MEMBERS_INTERVAL).await;
+            while updates.try_recv().is_ok() {}
+        }
+    }
+
+    ///Sends the Health of the Lavalink Connection, once right away and then on every change
+    /// - Without a Guild, the Health of every Guild is combined
+    async fn node_status_sender_run(self, guild: Option<GuildId>) {
+        let mut watches: Vec<_> = self
+            .com
+            .players
+            .iter()
+            .filter(|(g, _)| guild.map_or(true, |guild| guild.eq(g)))
+            .map(|(_, pm)| pm.node_status_watch())
+            .collect();
+        if watches.is_empty() {
+            return;
+        }
+        loop {
+            let status = NodeStatus::combine(watches.iter().map(|w| w.borrow().clone()));
+            debug!("Sending Node Status. {:?}, {:?}", self.id, status);
+            self.clone()
+                .sync_respond(Message::NodeStatus {
+                    connected: status.connected,
+                    reconnecting: status.reconnecting,
+                    last_error: status.last_error,
+                })
+                .await;
+            let changes = watches.iter_mut().map(|w| Box::pin(w.changed()));
+            let (res, _, _) = futures::future::select_all(changes).await;
+            if res.is_err() {
+                return;
+            }
+        }
+    }
+
     async fn player_state_sender_run(self, guild: GuildId, channel: ChannelId) {
-        info!("Starting Player State Sender Run. {:?}", self.peer);
+        info!("Starting Player State Sender Run. {:?}", self.id);
         //Get Player Manager for Guild
         let player_manager_op = self.com.players.get(&guild);
         let player_manager = match player_manager_op {
             None => {
                 error!(
                     "Got no Player Manager for Guild. {:?}, {:?}",
-                    self.peer, guild
+                    self.id, guild
                 );
-                return;
+                //Still answers Resyncs, nobody else would
+                loop {
+                    self.clone().send_state(None).await;
+                    self.resync.notified().await;
+                }
             }
             Some(pm) => pm,
         };
 
+        let interval = Duration::from_millis(self.com.cfg.state_interval);
+
         //Send initial empty state
         if player_manager.get_player(&channel).await.is_none() {
-            self.clone().sync_respond(Message::PlayerState(None)).await;
+            self.clone().send_state(None).await;
         }
 
+        let mut players = player_manager.players_watch();
+
         //Main loop in player state sender run
         'main: loop {
             //Loop until we got a player for our channel
-            let (bot, player) = loop {
+            // - Backs off, but wakes up right away when the Players of the Guild change
+            let waiting_since = Instant::now();
+            let mut attempt = 0;
+            let (bot, mut watch) = loop {
                 //Get Player for Channel
                 let player_op = player_manager.get_player(&channel).await;
-                if let Some(pair) = player_op {
-                    info!(
-                        "Got Player for Channel. {:?}, {:?}, {:?}",
-                        self.peer, guild, channel
-                    );
-                    break pair;
-                }
-                tokio::time::sleep(Duration::from_secs(5)).await;
-            };
-            let bot_op = self.com.bots.get_bot_by_id(bot);
-            let bot = match bot_op {
-                None => {
-                    error!(
-                        "Could not find Bot. {:?}, {:?}, {:?}",
-                        self.peer, guild, bot
-                    );
-                    continue;
+                if let Some((bot, player)) = player_op {
+                    let bot_op = self.com.bots.get_bot_by_id(bot);
+                    //Get watch for player state
+                    let watch_op = player.read().await.as_ref().map(|p| p.get_status_watch());
+                    match (bot_op, watch_op) {
+                        (Some(bot), Some(watch)) => {
+                            info!(
+                                "Got Player for Channel. {:?}, {:?}, {:?}",
+                                self.id, guild, channel
+                            );
+                            break (bot, watch);
+                        }
+                        (None, _) => {
+                            error!("Could not find Bot. {:?}, {:?}, {:?}", self.id, guild, bot)
+                        }
+                        (_, None) => warn!(
+                            "First Player read was empty. Starting over. {:?}, {:?}, {:?}",
+                            self.id, guild, channel
+                        ),
+                    }
                 }
-                Some(b) => b.cache().current_user().await,
-            };
 
-            //Get watch for player state
-            let watch_op = player.read().await.as_ref().map(|p| p.get_status_watch());
-            let mut watch = match watch_op {
-                Some(p) => p,
-                None => {
-                    warn!(
-                        "First Player read was empty. Starting over. {:?}, {:?}, {:?}",
-                        self.peer, guild, channel
-                    );
-                    continue;
+                let wait = match player_wait(attempt, waiting_since.elapsed()) {
+                    None => {
+                        info!(
+                            "No Player appeared, parking Player State Sender. {:?}, {:?}, {:?}",
+                            self.id, guild, channel
+                        );
+                        self.clone().send_state(None).await;
+                        //Parked instead of stopped, so a Resync still finds someone to answer it
+                        // and looks for the Player again
+                        self.resync.notified().await;
+                        if player_manager.get_player(&channel).await.is_none() {
+                            self.clone().send_state(None).await;
+                        }
+                        continue 'main;
+                    }
+                    Some(wait) => wait,
+                };
+                attempt += 1;
+                //Jitter, so connections of the same Channel do not look in lockstep
+                let sleep = wait.mul_f64(rand::thread_rng().gen_range(0.5..=1.0));
+                tokio::select! {
+                    changed = tokio::time::timeout(sleep, players.changed()) => {
+                        if let Ok(Err(_)) = changed {
+                            tokio::time::sleep(sleep).await;
+                        }
+                    }
+                    //There is still no Player to sync
+                    _ = self.resync.notified() => self.clone().send_state(None).await,
                 }
             };
 
-            //Initialize first state
-            let mut last_state = gen_player_state(bot.clone(), watch.borrow().clone());
-            //And send it synced
-            self.clone()
-                .sync_respond(Message::PlayerState(Some(State::FullState(
-                    last_state.clone(),
-                ))))
-                .await;
+            //Initialize first state and send it synced
+            let (mut sync, full) = StateSync::new(gen_player_state(
+                bot_info(&bot, guild).await,
+                watch.borrow().clone(),
+            ));
+            self.clone().send_state(Some(full)).await;
+            let mut last_sent = Instant::now();
 
             loop {
-                let watch_res = watch.changed().await;
-                if let Err(e) = watch_res {
-                    info!(
-                        "Player Watch Ended. {:?}, {:?}, {:?}, {:?}",
-                        self.peer, guild, channel, e
-                    );
-                    self.clone()
-                        .sync_respond(Message::PlayerState(Some(State::EmptyState())))
-                        .await;
-                    continue 'main;
+                let resync = tokio::select! {
+                    watch_res = watch.changed() => {
+                        if let Err(e) = watch_res {
+                            info!(
+                                "Player Watch Ended. {:?}, {:?}, {:?}, {:?}",
+                                self.id, guild, channel, e
+                            );
+                            self.clone().send_state(Some(State::EmptyState())).await;
+                            continue 'main;
+                        }
+                        false
+                    }
+                    _ = self.resync.notified() => true,
+                };
+
+                //Coalesce changes arriving faster than the interval into a single patch
+                let since = last_sent.elapsed();
+                if !resync && since < interval {
+                    tokio::time::sleep(interval - since).await;
                 }
 
                 //Get new State
-                let new_state = gen_player_state(bot.clone(), watch.borrow().clone());
-                //If State did not change, wait for next change
-                if new_state.eq(&last_state) {
-                    continue;
-                }
-                //Generate Patch
-                let patch_res = Message::generate_patch(&last_state, &new_state);
-                let patch = match patch_res {
-                    Ok(p) => p,
+                //The Nickname may have changed or only now be cached
+                let new_state =
+                    gen_player_state(bot_info(&bot, guild).await, watch.borrow().clone());
+                //Patches are MessagePack, which JSON Clients can not apply
+                let patches = self.encoding() == Encoding::Binary;
+                let state = match sync.next(new_state, resync, patches) {
+                    //State did not change, wait for next change
+                    Ok(None) => continue,
+                    Ok(Some(state)) => state,
                     Err(e) => {
                         error!(
                             "Error Generating Patch. {:?}, {:?}, {:?}, {:?}",
-                            self.peer, guild, channel, e
+                            self.id, guild, channel, e
                         );
                         continue;
                     }
                 };
-                last_state = new_state;
 
-                debug!("Sending Patch. {:?}, {:?}, {:?}", self.peer, guild, channel);
-                self.clone()
-                    .sync_respond(Message::PlayerState(Some(State::UpdateState(patch))))
-                    .await;
+                debug!("Sending State. {:?}, {:?}, {:?}", self.id, guild, channel);
+                self.clone().send_state(Some(state)).await;
+                last_sent = Instant::now();
             }
         }
     }
 }
 
-fn gen_player_state(bot: CurrentUser, ps: Arc<crate::player::PlayerState>) -> Box<PlayerState> {
+///Numbers the Player States of a Connection
+/// - Every State and Patch gets the next Version
+/// - A Patch only applies on top of the Version right before it, otherwise Clients should Resync
+#[derive(Default)]
+struct StateVersions(AtomicU64);
+
+impl StateVersions {
+    fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    ///Player State Message with the next Version
+    fn message(&self, state: Option<State>) -> Message {
+        Message::PlayerState(self.next(), state)
+    }
+}
+
+///What the Player State Sender sends for a Player, a Full State first and Patches after
+/// - Patches always apply on top of the last sent State, unchanged States are not sent at all
+struct StateSync {
+    last: Box<PlayerState>,
+}
+
+impl StateSync {
+    ///Starts with the first State, which is sent in full
+    fn new(first: Box<PlayerState>) -> (StateSync, State) {
+        let full = State::FullState(first.clone());
+        (StateSync { last: first }, full)
+    }
+
+    ///What to send for the new State, None if it did not change
+    /// - A Resync replaces whatever the Client has, so it is always a Full State
+    /// - Without Patches every change is a Full State too
+    fn next(
+        &mut self,
+        new: Box<PlayerState>,
+        resync: bool,
+        patches: bool,
+    ) -> Result<Option<State>, String> {
+        if !resync && new.eq(&self.last) {
+            return Ok(None);
+        }
+        let state = if resync || !patches {
+            State::FullState(new.clone())
+        } else {
+            state_patch(&self.last, &new)?
+        };
+        self.last = new;
+        Ok(Some(state))
+    }
+}
+
+///Name and Avatar of the Bot, as shown in the Guild
+/// - Uses the Nickname, falls back to the Username if there is none or the Member is not cached yet
+async fn bot_info(bot: &Bot, guild: GuildId) -> BotInfo {
+    let user = bot.cache().current_user().await;
+    let name = bot
+        .cache()
+        .member(guild, user.id)
+        .await
+        .and_then(|m| m.nick)
+        .unwrap_or_else(|| user.name.clone());
+    BotInfo {
+        name,
+        avatar: user
+            .avatar_url()
+            .unwrap_or_else(|| user.default_avatar_url()),
+    }
+}
+
+fn gen_player_state(bot: BotInfo, ps: Arc<crate::player::PlayerState>) -> Box<PlayerState> {
     let current = ps
         .current
         .as_ref()
-        .map(|((pos, when), track)| parse_cur_track(pos, when, track))
-        .flatten();
+        .zip(ps.position())
+        .and_then(|((_, track), pos)| parse_cur_track(pos, track));
     let history: Vec<_> = ps
         .history
         .iter()
-        .map(|t| parse_track(t))
+        .map(|(t, _)| parse_track(t))
         .flatten()
         .collect();
     let playlist: Vec<_> = ps
@@ -604,12 +1905,15 @@ fn gen_player_state(bot: CurrentUser, ps: Arc<crate::player::PlayerState>) -> Bo
         .collect();
 
     let new_ps = PlayerState {
-        bot: BotInfo {
-            name: bot.name.clone(),
-            avatar: bot.avatar_url().unwrap_or_else(|| bot.default_avatar_url()),
-        },
+        bot,
         paused: ps.play_state.is_paused(),
         mode: ps.playback.into(),
+        filter: ps.filter.map(AudioFilter::from),
+        speed: ps.speed,
+        pitch: ps.pitch,
+        volume: ps.volume,
+        shuffle: ps.shuffle,
+        queue_limit: ps.playlist.capacity(),
         current,
         history,
         queue: playlist,
@@ -617,33 +1921,276 @@ fn gen_player_state(bot: CurrentUser, ps: Arc<crate::player::PlayerState>) -> Bo
     Box::new(new_ps)
 }
 
+///Compresses a Binary Message with zlib, see NetConfig::compression
+fn deflate(bin: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bin)?;
+    encoder.finish()
+}
+
+///Wait before looking for the Player of a Channel again, without Jitter
+/// - Doubles from PLAYER_WAIT_MIN with every Attempt, up to PLAYER_WAIT_MAX
+/// - None once PLAYER_WAIT_LIFETIME has passed since the first look
+fn player_wait(attempt: u32, waited: Duration) -> Option<Duration> {
+    if waited > PLAYER_WAIT_LIFETIME {
+        return None;
+    }
+    let wait = 2u32
+        .checked_pow(attempt)
+        .and_then(|factor| PLAYER_WAIT_MIN.checked_mul(factor))
+        .unwrap_or(PLAYER_WAIT_MAX);
+    Some(wait.min(PLAYER_WAIT_MAX))
+}
+
+///Channel a Player Control goes to, the stored Voice State checked against the actual one
+/// - Voice Events can arrive after the Control, so the User could have moved or left already
+/// - On a mismatch the actual Voice State is stored and the Error for the Client returned
+async fn control_channel(
+    stored: &RwLock<Option<(GuildId, ChannelId)>>,
+    actual: impl Future<Output = Option<(GuildId, ChannelId)>>,
+) -> Result<(GuildId, ChannelId), String> {
+    let expected = match *stored.read().await {
+        None => return Err("No Bot in Channel".to_string()),
+        Some(vs) => vs,
+    };
+    match actual.await {
+        Some(actual) if actual == expected => Ok(expected),
+        actual => {
+            *stored.write().await = actual;
+            Err("Not in the Channel anymore".to_string())
+        }
+    }
+}
+
 fn parse_track(t: &lavalink_rs::model::Track) -> Option<Track> {
     let info = t.info.clone()?;
 
     Some(Track {
-        len: Duration::from_millis(info.length),
+        len: track_length(&info),
         pos: Duration::from_millis(info.position),
+        stream: info.is_stream,
+        thumbnail: thumbnail(&info),
+        author: author(&info),
         title: info.title,
         uri: info.uri,
     })
 }
 
-fn parse_cur_track(pos: &Duration, when: &Instant, t: &lavalink_rs::model::Track) -> Option<Track> {
+///Patch from the last to the new State, whichever is smaller of a plain Patch and Queue Operations
+/// - A plain Patch diffs the Queue by Index, so a Track inserted or removed in the middle
+///   changes every later one, Operations only carry the Track itself
+fn state_patch(last: &PlayerState, new: &PlayerState) -> Result<State, String> {
+    let plain = Message::generate_patch(last, new)
+        .map(State::UpdateState)
+        .map_err(|e| format!("{:?}", e))?;
+    let ops = if last.queue == new.queue {
+        None
+    } else {
+        queue_ops(&last.queue, &new.queue)
+    };
+    let ops = match ops {
+        None => return Ok(plain),
+        Some(ops) => ops,
+    };
+    let mut rest = new.clone();
+    rest.queue = last.queue.clone();
+    let with_ops = Message::generate_patch(last, &rest)
+        .map(|p| State::UpdateQueueState(p, ops))
+        .map_err(|e| format!("{:?}", e))?;
+    if patch_size(&with_ops) < patch_size(&plain) {
+        Ok(with_ops)
+    } else {
+        Ok(plain)
+    }
+}
+
+///Tracks of a Search, no Matches are no Error but an empty Result
+/// - Failed Searches stay Errors, so Clients can tell them apart from finding nothing
+fn search_tracks(
+    search: Result<(UserId, lavalink_rs::model::Tracks), PlayerMapError>,
+) -> Result<Vec<lavalink_rs::model::Track>, String> {
+    match search {
+        Ok((_, tracks)) => Ok(tracks.tracks),
+        Err(PlayerMapError::PlayerError(PlayerError::NoMatches(_))) => Ok(Vec::new()),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+///Bytes a Patch takes as MessagePack, Patches are never sent as JSON
+fn patch_size(patch: &State) -> usize {
+    Message::PlayerState(0, Some(patch.clone()))
+        .generate()
+        .map_or(usize::MAX, |bin| bin.len())
+}
+
+///Operations turning the old Queue into the new one, applied in order
+/// - Tracks that are gone get removed, others are moved to their new Index or inserted
+/// - None, if the Operations would not be smaller than sending the whole Queue, e.g. after a Shuffle
+fn queue_ops(old: &[Track], new: &[Track]) -> Option<Vec<QueueOp>> {
+    let limit = new.len() / 2 + 1;
+    let mut queue = old.to_vec();
+    let mut ops = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        if ops.len() > limit {
+            return None;
+        }
+        match queue.get(i) {
+            Some(track) if track == &new[i] => i += 1,
+            Some(track) if !new[i..].contains(track) => {
+                queue.remove(i);
+                ops.push(QueueOp::Remove(i));
+            }
+            _ => match queue[i..].iter().position(|t| t == &new[i]) {
+                Some(from) => {
+                    let track = queue.remove(i + from);
+                    queue.insert(i, track);
+                    ops.push(QueueOp::Move(i + from, i));
+                    i += 1;
+                }
+                None => {
+                    queue.insert(i, new[i].clone());
+                    ops.push(QueueOp::Insert(i, new[i].clone()));
+                    i += 1;
+                }
+            },
+        }
+    }
+    while queue.len() > new.len() {
+        queue.pop();
+        ops.push(QueueOp::Remove(queue.len()));
+    }
+    Some(ops).filter(|ops| ops.len() <= limit)
+}
+
+///Converts the current Track, the Position is taken from PlayerState::position
+/// - It only advances while playing
+fn parse_cur_track(pos: Duration, t: &lavalink_rs::model::Track) -> Option<Track> {
     let info = t.info.clone()?;
 
     Some(Track {
-        len: Duration::from_millis(info.length),
-        pos: *pos + when.elapsed(),
+        len: track_length(&info),
+        pos,
+        stream: info.is_stream,
+        thumbnail: thumbnail(&info),
+        author: author(&info),
         title: info.title,
         uri: info.uri,
     })
 }
 
-fn parse_mode(pm: PlayMode) -> crate::player::Playback {
-    match pm {
-        PlayMode::Normal => crate::player::Playback::Normal,
-        PlayMode::LoopAll => crate::player::Playback::AllLoop,
-        PlayMode::LoopOne => crate::player::Playback::OneLoop,
+///Streams report a huge Length, Clients get zero instead
+fn track_length(info: &lavalink_rs::model::Info) -> Duration {
+    if info.is_stream {
+        return Duration::from_secs(0);
+    }
+    Duration::from_millis(info.length)
+}
+
+fn author(info: &lavalink_rs::model::Info) -> Option<String> {
+    Some(info.author.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_string())
+}
+
+///Artwork Url of a Track, Lavalink only gives us enough for YouTube
+fn thumbnail(info: &lavalink_rs::model::Info) -> Option<String> {
+    let host = url::Url::parse(&info.uri).ok()?.host_str()?.to_string();
+    let youtube = ["youtube.com", "youtu.be"]
+        .iter()
+        .any(|h| host.eq(h) || host.ends_with(&format!(".{}", h)));
+    if !youtube || info.identifier.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "https://i.ytimg.com/vi/{}/hqdefault.jpg",
+        info.identifier
+    ))
+}
+
+///What a Control needs permission for, None if anybody may use it
+/// - Lists every Control, so new ones can not slip past the DJ Role unnoticed
+fn control_permission(con: &PlayerControl) -> Option<Permission> {
+    match con {
+        PlayerControl::Resume() | PlayerControl::Pause() => Some(Permission::PlayPause),
+        //Reaches Players in other Channels
+        PlayerControl::PauseAll() | PlayerControl::ResumeAll() => Some(Permission::PauseAll),
+        //Everything changing what plays next
+        PlayerControl::Skip(_) | PlayerControl::SkipTo(_) | PlayerControl::PlayNext(_) => {
+            Some(Permission::Next)
+        }
+        //Everything going back to the History
+        PlayerControl::BackSkip(_) | PlayerControl::ReplayLast() => Some(Permission::Prev),
+        PlayerControl::SetTime(_)
+        | PlayerControl::SeekPercent(_)
+        | PlayerControl::SeekFraction(_) => Some(Permission::Seek),
+        PlayerControl::PlayMode(PlayMode::LoopOne) => Some(Permission::LoopOne),
+        PlayerControl::PlayMode(PlayMode::Normal) | PlayerControl::PlayMode(PlayMode::LoopAll) => {
+            Some(Permission::LoopAll)
+        }
+        PlayerControl::Leave() | PlayerControl::LeaveKeepQueue() => Some(Permission::Leave),
+        PlayerControl::Join() | PlayerControl::MoveHere() => Some(Permission::Join),
+        PlayerControl::Clear() => Some(Permission::Clear),
+        PlayerControl::SetShuffle(_) => Some(Permission::Shuffle),
+        PlayerControl::SetFilter(_) | PlayerControl::SetSpeed(_) | PlayerControl::SetPitch(_) => {
+            Some(Permission::BassBoost)
+        }
+        //Like posting a Query in the Channel, only limited by the Enqueue Cooldown
+        PlayerControl::Enqueue(_)
+        | PlayerControl::EnqueueIndex(_)
+        | PlayerControl::EnqueuePlaylist(_) => None,
+    }
+}
+
+///Describes why a Message could not be parsed, so Clients can show what went wrong
+/// - Field or Variant names are taken from the backticks in serde's Error messages
+fn parse_error_detail(length: usize, reason: &str) -> ParseErrorDetail {
+    let kind = if reason.contains("unknown variant") {
+        ParseErrorKind::UnknownVariant
+    } else if reason.contains("missing field") {
+        ParseErrorKind::MissingField
+    } else if reason.contains("invalid type") || reason.contains("invalid value") {
+        ParseErrorKind::InvalidType
+    } else if reason.contains("invalid length") || reason.contains("fill whole buffer") {
+        ParseErrorKind::InvalidLength
+    } else {
+        ParseErrorKind::Other
+    };
+    let name = reason.split('`').nth(1).map(|n| n.to_string());
+    ParseErrorDetail {
+        length,
+        kind,
+        name,
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_filter(filter: AudioFilter) -> crate::player::AudioFilter {
+    match filter {
+        AudioFilter::BassBoost => crate::player::AudioFilter::BassBoost,
+        AudioFilter::Nightcore => crate::player::AudioFilter::Nightcore,
+        AudioFilter::Rotation => crate::player::AudioFilter::Rotation,
+    }
+}
+
+impl From<crate::player::AudioFilter> for AudioFilter {
+    fn from(f: crate::player::AudioFilter) -> Self {
+        match f {
+            crate::player::AudioFilter::BassBoost => AudioFilter::BassBoost,
+            crate::player::AudioFilter::Nightcore => AudioFilter::Nightcore,
+            crate::player::AudioFilter::Rotation => AudioFilter::Rotation,
+        }
+    }
+}
+
+impl From<crate::player::HistoryReason> for HistoryReason {
+    fn from(r: crate::player::HistoryReason) -> Self {
+        match r {
+            crate::player::HistoryReason::Finished => HistoryReason::Finished,
+            crate::player::HistoryReason::Skipped => HistoryReason::Skipped,
+            crate::player::HistoryReason::Removed => HistoryReason::Removed,
+            crate::player::HistoryReason::Error => HistoryReason::Error,
+        }
     }
 }
 
@@ -656,3 +2203,331 @@ impl From<crate::player::Playback> for PlayMode {
         }
     }
 }
+
+///Inverse of the Playback to PlayMode conversion above, both have to map the same pairs
+/// - Companions always set the Mode they sent, a Toggle would flip back to Normal
+fn parse_mode(pm: PlayMode) -> crate::player::Playback {
+    match pm {
+        PlayMode::Normal => crate::player::Playback::Normal,
+        PlayMode::LoopAll => crate::player::Playback::AllLoop,
+        PlayMode::LoopOne => crate::player::Playback::OneLoop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::tests::track;
+    use crate::player::Playback;
+
+    #[test]
+    fn play_mode_round_trip() {
+        let pairs = [
+            (PlayMode::Normal, Playback::Normal),
+            (PlayMode::LoopAll, Playback::AllLoop),
+            (PlayMode::LoopOne, Playback::OneLoop),
+        ];
+        for (mode, playback) in pairs {
+            assert_eq!(parse_mode(mode), playback);
+            assert_eq!(parse_mode(PlayMode::from(playback)), playback);
+        }
+    }
+
+    #[test]
+    fn streams_are_flagged_without_length() {
+        let stream = parse_track(&track("a", u64::MAX, true)).unwrap();
+        assert!(stream.stream);
+        assert_eq!(stream.len, Duration::from_secs(0));
+
+        let normal = parse_track(&track("b", 180_000, false)).unwrap();
+        assert!(!normal.stream);
+        assert_eq!(normal.len, Duration::from_secs(180));
+    }
+
+    fn queue(uris: &[&str]) -> Vec<Track> {
+        uris.iter()
+            .map(|uri| parse_track(&track(uri, 1000, false)).unwrap())
+            .collect()
+    }
+
+    ///Applies the Operations like a Client does
+    fn apply(old: &[Track], ops: Vec<QueueOp>) -> Vec<Track> {
+        let mut queue = old.to_vec();
+        for op in ops {
+            match op {
+                QueueOp::Remove(i) => {
+                    queue.remove(i);
+                }
+                QueueOp::Move(from, to) => {
+                    let track = queue.remove(from);
+                    queue.insert(to, track);
+                }
+                QueueOp::Insert(i, track) => queue.insert(i, track),
+            }
+        }
+        queue
+    }
+
+    ///Companion State of a Player with a current Track and a Playlist
+    fn player(uris: &[&str]) -> Box<PlayerState> {
+        let mut state = crate::player::tests::player_state();
+        state.current = Some((
+            (Duration::from_secs(30), Instant::now()),
+            track("current", 180_000, false),
+        ));
+        for uri in uris {
+            state
+                .playlist
+                .push_back(track(uri, 180_000, false))
+                .unwrap();
+        }
+        let bot = BotInfo {
+            name: "Bot".to_string(),
+            avatar: "https://example.com/avatar.png".to_string(),
+        };
+        gen_player_state(bot, Arc::new(state))
+    }
+
+    ///Size of the Patch sent before there were Queue Operations
+    fn plain_patch_size(last: &PlayerState, new: &PlayerState) -> usize {
+        patch_size(&State::UpdateState(
+            Message::generate_patch(last, new).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn queue_ops_shrink_patches() {
+        let uris: Vec<String> = (0..50)
+            .map(|i| format!("https://example.com/{}", i))
+            .collect();
+        let uris: Vec<&str> = uris.iter().map(String::as_str).collect();
+        let last = player(&uris);
+
+        //Enqueued as next Track, or anywhere while shuffling
+        let mut enqueued = uris.clone();
+        enqueued.insert(0, "https://example.com/new");
+        let mut removed = uris.clone();
+        removed.remove(1);
+        for new in [enqueued, removed].iter() {
+            let new = player(new);
+            let patch = state_patch(&last, &new).unwrap();
+            assert!(matches!(patch, State::UpdateQueueState(_, _)));
+            assert!(patch_size(&patch) * 4 < plain_patch_size(&last, &new));
+        }
+
+        //Appended, a plain Patch is about as small already
+        let mut appended = uris.clone();
+        appended.push("https://example.com/new");
+        let new = player(&appended);
+        let patch = state_patch(&last, &new).unwrap();
+        assert!(patch_size(&patch) <= plain_patch_size(&last, &new));
+
+        //Shuffled, Operations would not be smaller
+        let shuffled: Vec<&str> = uris.iter().rev().copied().collect();
+        let new = player(&shuffled);
+        let patch = state_patch(&last, &new).unwrap();
+        assert!(matches!(patch, State::UpdateState(_)));
+        assert_eq!(patch_size(&patch), plain_patch_size(&last, &new));
+    }
+
+    #[test]
+    fn queue_ops_for_small_changes() {
+        let old = queue(&["a", "b", "c", "d"]);
+
+        let new = queue(&["a", "b", "c", "d", "e"]);
+        let ops = queue_ops(&old, &new).unwrap();
+        assert!(matches!(ops.as_slice(), [QueueOp::Insert(4, _)]));
+        assert!(apply(&old, ops) == new);
+
+        let new = queue(&["a", "c", "d"]);
+        let ops = queue_ops(&old, &new).unwrap();
+        assert!(matches!(ops.as_slice(), [QueueOp::Remove(1)]));
+        assert!(apply(&old, ops) == new);
+
+        let new = queue(&["c", "a", "b", "d"]);
+        let ops = queue_ops(&old, &new).unwrap();
+        assert!(matches!(ops.as_slice(), [QueueOp::Move(2, 0)]));
+        assert!(apply(&old, ops) == new);
+    }
+
+    #[test]
+    fn queue_ops_with_duplicate_tracks() {
+        let cases = [
+            (vec!["a", "a", "b"], vec!["a", "b"]),
+            (vec!["a", "b"], vec!["a", "b", "a"]),
+            (vec!["a", "b", "a"], vec!["b", "a", "a"]),
+            (vec!["a", "b", "a", "b"], vec!["b", "a", "b"]),
+        ];
+        for (old, new) in cases.iter() {
+            let (old, new) = (queue(old), queue(new));
+            //Equal Tracks are interchangeable, as long as the Client ends up with the same Queue
+            let ops = queue_ops(&old, &new).unwrap();
+            assert!(apply(&old, ops) == new);
+        }
+    }
+
+    #[test]
+    fn shuffled_queue_is_sent_whole() {
+        let uris: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let uris: Vec<&str> = uris.iter().map(String::as_str).collect();
+        let old = queue(&uris);
+        let reversed: Vec<Track> = old.iter().rev().cloned().collect();
+        assert!(queue_ops(&old, &reversed).is_none());
+    }
+
+    #[test]
+    fn player_wait_doubles_up_to_the_cap() {
+        let start = Duration::from_secs(0);
+        assert_eq!(player_wait(0, start), Some(PLAYER_WAIT_MIN));
+        assert_eq!(player_wait(1, start), Some(PLAYER_WAIT_MIN * 2));
+        assert_eq!(player_wait(2, start), Some(PLAYER_WAIT_MIN * 4));
+        assert_eq!(player_wait(5, start), Some(Duration::from_secs(16)));
+        //500ms doubled 6 times would be 32s
+        assert_eq!(player_wait(6, start), Some(PLAYER_WAIT_MAX));
+        //Huge Attempts would overflow instead of capping
+        assert_eq!(player_wait(40, start), Some(PLAYER_WAIT_MAX));
+        assert_eq!(player_wait(u32::MAX, start), Some(PLAYER_WAIT_MAX));
+
+        //Gives up once the Lifetime is over
+        assert_eq!(
+            player_wait(3, PLAYER_WAIT_LIFETIME),
+            Some(PLAYER_WAIT_MIN * 8)
+        );
+        let over = PLAYER_WAIT_LIFETIME + Duration::from_millis(1);
+        assert_eq!(player_wait(0, over), None);
+        assert_eq!(player_wait(20, over), None);
+    }
+
+    #[test]
+    fn deflated_messages_inflate_again() {
+        use flate2::read::ZlibDecoder;
+        use std::io::Read;
+
+        //Full States of long Queues repeat a lot, e.g. Authors and Urls
+        let uris: Vec<String> = (0..100)
+            .map(|i| format!("https://example.com/{}", i))
+            .collect();
+        let uris: Vec<&str> = uris.iter().map(String::as_str).collect();
+        let bin = serde_json::to_vec(&queue(&uris)).unwrap();
+
+        let compressed = deflate(&bin).unwrap();
+        assert!(compressed.len() < bin.len() / 2);
+        let mut inflated = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut inflated)
+            .unwrap();
+        assert_eq!(inflated, bin);
+    }
+
+    #[test]
+    fn pause_all_is_not_a_single_pause() {
+        for con in [PlayerControl::PauseAll(), PlayerControl::ResumeAll()] {
+            assert_eq!(control_permission(&con), Some(Permission::PauseAll));
+        }
+        for con in [PlayerControl::Pause(), PlayerControl::Resume()] {
+            assert_eq!(control_permission(&con), Some(Permission::PlayPause));
+        }
+    }
+
+    #[test]
+    fn playlists_are_enqueued_like_tracks() {
+        let enqueue = control_permission(&PlayerControl::Enqueue("query".to_string()));
+        let playlist = PlayerControl::EnqueuePlaylist("playlist".to_string());
+        assert_eq!(control_permission(&playlist), enqueue);
+    }
+
+    #[tokio::test]
+    async fn moved_user_is_not_controlling() {
+        let stored = (GuildId(1), ChannelId(10));
+        let voice_state = RwLock::new(Some(stored));
+        let control = |actual| control_channel(&voice_state, async move { actual });
+        assert_eq!(control(Some(stored)).await, Ok(stored));
+
+        //Moved to another Channel before the Voice Event arrived, the Control is rejected
+        // and the Voice State catches up right away
+        let moved = (GuildId(1), ChannelId(11));
+        let rejected = Err("Not in the Channel anymore".to_string());
+        assert_eq!(control(Some(moved)).await, rejected);
+        assert_eq!(*voice_state.read().await, Some(moved));
+        //Moved to a Channel of another Guild
+        let other = (GuildId(2), ChannelId(11));
+        assert_eq!(control(Some(other)).await, rejected);
+        assert_eq!(*voice_state.read().await, Some(other));
+
+        //Left Voice entirely, later Controls have no Channel at all
+        assert_eq!(control(None).await, rejected);
+        assert_eq!(*voice_state.read().await, None);
+        let no_channel = Err("No Bot in Channel".to_string());
+        assert_eq!(control(Some(stored)).await, no_channel);
+    }
+
+    #[test]
+    fn failed_searches_are_not_empty_results() {
+        let failed = |e| Err(PlayerMapError::PlayerError(e));
+        assert!(matches!(
+            search_tracks(failed(PlayerError::NoMatches("query".to_string()))),
+            Ok(tracks) if tracks.is_empty()
+        ));
+        assert!(search_tracks(failed(PlayerError::SearchFailed("429".to_string()))).is_err());
+        assert!(search_tracks(Err(PlayerMapError::NoPlayerFound(ChannelId(1)))).is_err());
+    }
+
+    #[test]
+    fn sent_states_build_on_each_other() {
+        let uris: Vec<String> = (0..50)
+            .map(|i| format!("https://example.com/{}", i))
+            .collect();
+        let mut uris: Vec<&str> = uris.iter().map(String::as_str).collect();
+        let versions = StateVersions::default();
+        let (mut sync, full) = StateSync::new(player(&uris));
+        let mut sent = vec![versions.message(Some(full))];
+
+        //Unchanged States are not sent and take no Version
+        let same = sync.last.clone();
+        assert!(sync.next(same, false, true).unwrap().is_none());
+
+        //Removed and enqueued as next Track, each Patch on top of the one before
+        uris.remove(3);
+        sent.push(versions.message(sync.next(player(&uris), false, true).unwrap()));
+        uris.insert(0, "https://example.com/new");
+        sent.push(versions.message(sync.next(player(&uris), false, true).unwrap()));
+        //A Resync is a Full State, even without a change
+        let same = sync.last.clone();
+        sent.push(versions.message(sync.next(same, true, true).unwrap()));
+        //JSON Clients get Full States only
+        uris.remove(0);
+        sent.push(versions.message(sync.next(player(&uris), false, false).unwrap()));
+
+        //Replays the Messages like a Client, which only applies the Version right after its own
+        let mut client: Option<(u64, Vec<Track>)> = None;
+        let mut kinds = Vec::new();
+        for msg in sent {
+            let (version, state) = match msg {
+                Message::PlayerState(version, Some(state)) => (version, state),
+                other => panic!("Unexpected Message. {:?}", other),
+            };
+            let base = client.take();
+            if let Some((base, _)) = &base {
+                assert_eq!(version, base + 1);
+            }
+            let queue = match state {
+                State::FullState(state) => {
+                    kinds.push("full");
+                    state.queue
+                }
+                State::UpdateQueueState(_, ops) => {
+                    kinds.push("ops");
+                    let (_, queue) = base.expect("Patch without a Full State before");
+                    apply(&queue, ops)
+                }
+                other => panic!("Unexpected State. {:?}", other),
+            };
+            client = Some((version, queue));
+        }
+        assert_eq!(kinds, ["full", "ops", "ops", "full", "full"]);
+        //The unchanged State was not sent, so it took no Version
+        let (version, queue) = client.unwrap();
+        assert_eq!(version, 5);
+        assert_eq!(queue, self::queue(&uris));
+    }
+}
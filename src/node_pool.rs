@@ -0,0 +1,206 @@
+use crate::config::LavalinkConfig;
+use crate::lavalink_handler::LavalinkHandler;
+use lavalink_rs::error::LavalinkError;
+use lavalink_rs::{LavalinkClient, LavalinkClientInner};
+use log::{info, warn};
+use serenity::model::id::UserId;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+///A failed Node only gets new Players again after this
+const NODE_FAILURE_COOLDOWN: Duration = Duration::from_secs(60);
+
+///Lavalink Clients of a single Bot, one for every configured Node
+/// - New Players go to the healthy Node with the fewest Players
+/// - With a single Node, every Player ends up on it
+pub struct NodePool {
+    bot: UserId,
+    handler: LavalinkHandler,
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    config: LavalinkConfig,
+    ///Replaced by a new Client, once the Websocket of the Client closed
+    client: std::sync::Mutex<LavalinkClient>,
+    ///Replaced Clients, Players keep using them until they reconnect
+    retired: std::sync::Mutex<Vec<Weak<Mutex<LavalinkClientInner>>>>,
+    ///Held while the Client is replaced, so it is only replaced once
+    renewing: Mutex<()>,
+    players: AtomicUsize,
+    ///Cleared once the Websocket of the current Client closed or the Node failed
+    connected: AtomicBool,
+    ///When the Node last failed
+    failed: std::sync::Mutex<Option<Instant>>,
+}
+
+impl Node {
+    fn client(&self) -> LavalinkClient {
+        self.client
+            .lock()
+            .expect("Lavalink Client Lock was poisoned")
+            .clone()
+    }
+
+    ///Whether the Client is the current one of the Node
+    fn is(&self, client: &LavalinkClient) -> bool {
+        Arc::ptr_eq(&self.client().inner, &client.inner)
+    }
+
+    ///Whether the Client is the current or a replaced one of the Node
+    fn owns(&self, client: &LavalinkClient) -> bool {
+        self.is(client)
+            || self
+                .retired
+                .lock()
+                .map(|retired| {
+                    retired
+                        .iter()
+                        .any(|r| std::ptr::eq(r.as_ptr(), Arc::as_ptr(&client.inner)))
+                })
+                .unwrap_or(false)
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.failed
+            .lock()
+            .map(|failed| failed.map_or(true, |at| at.elapsed() > NODE_FAILURE_COOLDOWN))
+            .unwrap_or(true)
+    }
+}
+
+impl NodePool {
+    pub async fn new(
+        bot: UserId,
+        configs: &[LavalinkConfig],
+        handler: LavalinkHandler,
+    ) -> Result<Self, LavalinkError> {
+        let mut nodes = Vec::new();
+        for config in configs {
+            let client = connect(bot, config, handler.clone()).await?;
+            nodes.push(Node {
+                config: config.clone(),
+                client: std::sync::Mutex::new(client),
+                retired: std::sync::Mutex::new(Vec::new()),
+                renewing: Mutex::new(()),
+                players: AtomicUsize::new(0),
+                connected: AtomicBool::new(true),
+                failed: std::sync::Mutex::new(None),
+            });
+        }
+        Ok(NodePool {
+            bot,
+            handler,
+            nodes,
+        })
+    }
+
+    ///Connects a new Client to the Node of the given one, whose Websocket closed
+    /// - Sessions of the closed Client are gone, so every Player has to create a new one
+    /// - Returns the Client that already replaced the given one, if another Player was faster
+    pub async fn renew(&self, client: &LavalinkClient) -> Result<LavalinkClient, LavalinkError> {
+        let node = match self.nodes.iter().find(|n| n.owns(client)) {
+            None => return Ok(client.clone()),
+            Some(node) => node,
+        };
+        let _renewing = node.renewing.lock().await;
+        if !node.is(client) {
+            return Ok(node.client());
+        }
+
+        let renewed = connect(self.bot, &node.config, self.handler.clone()).await?;
+        let retired = std::mem::replace(
+            &mut *node
+                .client
+                .lock()
+                .expect("Lavalink Client Lock was poisoned"),
+            renewed.clone(),
+        );
+        if let Ok(mut retired_clients) = node.retired.lock() {
+            retired_clients.retain(|r| r.strong_count() > 0);
+            retired_clients.push(Arc::downgrade(&retired.inner));
+        }
+        node.connected.store(true, Ordering::Relaxed);
+        info!("Renewed Lavalink Client. Node: {}", node.config.address);
+        Ok(renewed)
+    }
+
+    ///Assigns a Player to the healthy Node with the fewest Players
+    /// - Recently failed Nodes are only used, if there is nothing else
+    /// - The Node of the excluded Client is never used, for failing over
+    pub fn assign(&self, exclude: Option<&LavalinkClient>) -> Option<LavalinkClient> {
+        let node = self
+            .nodes
+            .iter()
+            .filter(|n| exclude.map_or(true, |c| !n.owns(c)))
+            .min_by_key(|n| (!n.is_healthy(), n.players.load(Ordering::Relaxed)))?;
+        let players = node.players.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(
+            "Assigned Player to Lavalink Node. Node: {}, Players: {}",
+            node.config.address, players
+        );
+        Some(node.client())
+    }
+
+    ///Frees the Slot of a Player on the Node of the Client
+    pub fn release(&self, client: &LavalinkClient) {
+        if let Some(node) = self.nodes.iter().find(|n| n.owns(client)) {
+            let players = node
+                .players
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |p| {
+                    Some(p.saturating_sub(1))
+                })
+                .unwrap_or(0);
+            info!(
+                "Released Player from Lavalink Node. Node: {}, Players: {}",
+                node.config.address,
+                players.saturating_sub(1)
+            );
+        }
+    }
+
+    ///Marks the Node of the Client as failed, so new Players avoid it for a while
+    pub fn failed(&self, client: &LavalinkClient) {
+        if let Some(node) = self.nodes.iter().find(|n| n.owns(client)) {
+            warn!("Lavalink Node failed. Node: {}", node.config.address);
+            node.connected.store(false, Ordering::Relaxed);
+            if let Ok(mut failed) = node.failed.lock() {
+                *failed = Some(Instant::now());
+            }
+        }
+    }
+
+    ///Marks the Node of the Client as disconnected, once its Websocket closed
+    /// - Only the current Client counts, replaced ones are closed anyway
+    pub fn closed(&self, client: &LavalinkClient) {
+        if let Some(node) = self.nodes.iter().find(|n| n.is(client)) {
+            node.connected.store(false, Ordering::Relaxed);
+        }
+    }
+
+    ///Whether any Node has a connected Client
+    pub fn is_connected(&self) -> bool {
+        self.nodes
+            .iter()
+            .any(|n| n.connected.load(Ordering::Relaxed))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+async fn connect(
+    bot: UserId,
+    config: &LavalinkConfig,
+    handler: LavalinkHandler,
+) -> Result<LavalinkClient, LavalinkError> {
+    LavalinkClient::builder(bot)
+        .set_host(&config.address)
+        .set_password(&config.password)
+        //.set_is_ssl(true)
+        .build(handler)
+        .await
+}
@@ -0,0 +1,172 @@
+use crate::config::PersistenceConfig;
+use crate::player::Playback;
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::{GuildId, UserId};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+const PLAYLIST_DIR: &str = "playlists";
+const PLAYLIST_NAME_LIMIT: usize = 64;
+const PLAYLISTS_PER_USER: usize = 25;
+
+pub type UserPlaylists = HashMap<String, Vec<StoredTrack>>;
+
+///Stores the Queues of every Player as one JSON file per Guild
+/// - Named Playlists are stored as one JSON file per User
+pub struct QueueStore {
+    path: PathBuf,
+    playlist_lock: Mutex<()>,
+}
+
+impl QueueStore {
+    pub fn new(config: PersistenceConfig) -> Result<Self, PersistenceError> {
+        let path = PathBuf::from(config.path);
+        std::fs::create_dir_all(path.join(PLAYLIST_DIR)).map_err(PersistenceError::Io)?;
+        Ok(QueueStore {
+            path,
+            playlist_lock: Mutex::new(()),
+        })
+    }
+
+    ///Saves a named Playlist for a User, replacing one with the same name
+    pub async fn save_playlist(
+        &self,
+        user: UserId,
+        name: String,
+        tracks: Vec<StoredTrack>,
+    ) -> Result<(), PersistenceError> {
+        if name.is_empty() || name.chars().count() > PLAYLIST_NAME_LIMIT {
+            return Err(PersistenceError::InvalidName(name));
+        }
+        let _lock = self.playlist_lock.lock().await;
+        let mut playlists = self.user_playlists(user).await?;
+        if !playlists.contains_key(&name) && playlists.len() >= PLAYLISTS_PER_USER {
+            return Err(PersistenceError::TooManyPlaylists(PLAYLISTS_PER_USER));
+        }
+        playlists.insert(name, tracks);
+        self.write(self.user_file(user), &playlists).await
+    }
+
+    pub async fn load_playlist(
+        &self,
+        user: UserId,
+        name: &str,
+    ) -> Result<Vec<StoredTrack>, PersistenceError> {
+        self.user_playlists(user)
+            .await?
+            .remove(name)
+            .ok_or_else(|| PersistenceError::PlaylistNotFound(name.to_string()))
+    }
+
+    ///Names of every Playlist of the User, sorted
+    pub async fn playlists(&self, user: UserId) -> Result<Vec<String>, PersistenceError> {
+        let mut names: Vec<_> = self.user_playlists(user).await?.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn user_playlists(&self, user: UserId) -> Result<UserPlaylists, PersistenceError> {
+        self.read(self.user_file(user)).await
+    }
+
+    fn user_file(&self, user: UserId) -> PathBuf {
+        self.path
+            .join(PLAYLIST_DIR)
+            .join(format!("{}.json", user.0))
+    }
+
+    async fn write<T: Serialize>(&self, file: PathBuf, data: &T) -> Result<(), PersistenceError> {
+        let data = serde_json::to_vec_pretty(data).map_err(PersistenceError::Json)?;
+        tokio::task::spawn_blocking(move || {
+            //Write to a temporary file first, so a crash never leaves a half written file
+            let tmp = file.with_extension("tmp");
+            std::fs::write(&tmp, data)?;
+            std::fs::rename(&tmp, &file)
+        })
+        .await
+        .map_err(|e| PersistenceError::Io(e.into()))?
+        .map_err(PersistenceError::Io)
+    }
+
+    ///Reads a JSON file, a missing file counts as empty
+    async fn read<T: Default + for<'de> Deserialize<'de>>(
+        &self,
+        file: PathBuf,
+    ) -> Result<T, PersistenceError> {
+        let data = tokio::task::spawn_blocking(move || std::fs::read(file))
+            .await
+            .map_err(|e| PersistenceError::Io(e.into()))?;
+        match data {
+            Ok(data) => serde_json::from_slice(&data).map_err(PersistenceError::Json),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+            Err(e) => Err(PersistenceError::Io(e)),
+        }
+    }
+
+    pub async fn save(
+        &self,
+        guild: GuildId,
+        players: Vec<StoredPlayer>,
+    ) -> Result<(), PersistenceError> {
+        self.write(self.guild_file(guild), &players).await
+    }
+
+    ///Loads the stored Players of a Guild
+    /// - Returns nothing, if nothing was stored yet
+    pub async fn load(&self, guild: GuildId) -> Result<Vec<StoredPlayer>, PersistenceError> {
+        self.read(self.guild_file(guild)).await
+    }
+
+    fn guild_file(&self, guild: GuildId) -> PathBuf {
+        self.path.join(format!("{}.json", guild.0))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredPlayer {
+    pub channel: u64,
+    pub playback: Playback,
+    pub current: Option<StoredTrack>,
+    pub playlist: Vec<StoredTrack>,
+    pub history: Vec<StoredTrack>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredTrack {
+    pub uri: String,
+    pub title: String,
+    ///Milliseconds into the Track, only relevant for the current Track
+    #[serde(default)]
+    pub position: u64,
+}
+
+impl StoredTrack {
+    ///Tracks without info can not be resolved again
+    pub fn new(track: &lavalink_rs::model::Track, position: u64) -> Option<Self> {
+        let info = track.info.as_ref()?;
+        Some(StoredTrack {
+            uri: info.uri.clone(),
+            title: info.title.clone(),
+            position,
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PersistenceError {
+    #[error("IO Error occurred: {0:?}")]
+    Io(std::io::Error),
+    #[error("JSON Error occurred: {0:?}")]
+    Json(serde_json::Error),
+    #[error("Persistence is not configured")]
+    NotConfigured(),
+    #[error("Invalid Playlist Name: {0:?}")]
+    InvalidName(String),
+    #[error("Playlist not found: {0:?}")]
+    PlaylistNotFound(String),
+    #[error("Too many Playlists, the limit is {0}")]
+    TooManyPlaylists(usize),
+}
@@ -1,22 +1,58 @@
 use std::borrow::BorrowMut;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::config::GuildConfig;
 use arraydeque::{ArrayDeque, CapacityError};
 use futures::Future;
 use lavalink_rs::error::LavalinkError;
-use lavalink_rs::model::{PlayerUpdate, Track, TrackFinish, TrackStart};
+use lavalink_rs::model::{
+    Band, Filters, PlayerUpdate, Rotation, TimeScale, Track, TrackFinish, TrackStart, Tracks,
+};
 use lavalink_rs::LavalinkClient;
+use log::warn;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use serenity::model::prelude::{ChannelId, GuildId, UserId};
 use songbird::error::JoinError;
 use songbird::Songbird;
 use thiserror::Error;
 use tokio::sync::watch::{Receiver as WatchReceiver, Sender as WatchSender};
+use tokio::task::JoinHandle;
 
 use std::fmt::{Display, Formatter};
 use strum_macros::AsRefStr;
 
 const MUSIC_QUEUE_LIMIT: usize = 100;
+const SPEED_RANGE: (f32, f32) = (0.5, 2.0);
+const PITCH_RANGE: (f32, f32) = (0.5, 2.0);
+///Lavalink accepts Volumes from 0 to 1000, 100 is unchanged
+const VOLUME_LIMIT: u16 = 1000;
+const DEFAULT_VOLUME: u16 = 100;
+///Tracks failing to start in a row, before the Player stops trying
+const MAX_FAILED_STARTS: u32 = 5;
+///Finish Reason of a Track, that could not be loaded
+const LOAD_FAILED: &str = "LOAD_FAILED";
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+///A second Previous press within this window goes to the previous Track
+const PREVIOUS_WINDOW: Duration = Duration::from_secs(3);
+///Position only updates are sent at most this often, see PositionThrottle
+const POSITION_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+///The next Track gets scheduled once the current one has less than this left
+/// - Longer than the Lavalink Update Interval, so no Track end is missed
+const HANDOFF_LOOKAHEAD: Duration = Duration::from_secs(10);
+///How long before the end of the current Track the next one is started
+const HANDOFF_LEAD: Duration = Duration::from_millis(200);
+///A Handoff this close to its time starts right away instead of sleeping again
+const HANDOFF_PRECISION: Duration = Duration::from_millis(10);
+///Finish Reason of a Track, that was replaced by another one
+const REPLACED: &str = "REPLACED";
+pub const PLAYLIST_LOADED: &str = "PLAYLIST_LOADED";
+pub const TRACK_LOADED: &str = "TRACK_LOADED";
+pub const SEARCH_RESULT: &str = "SEARCH_RESULT";
+const NO_MATCHES: &str = "NO_MATCHES";
 
 pub struct Player {
     channel: ChannelId,
@@ -24,6 +60,17 @@ pub struct Player {
     lavalink: LavalinkClient,
     songbird: Arc<Songbird>,
     player_state: PlayerState,
+    ///How often transient Lavalink Errors are retried
+    retries: u32,
+    ///Most Tracks kept in the History
+    history_limit: usize,
+    defaults: PlayerDefaults,
+    ///When Previous last restarted the current Track
+    last_restart: Option<Instant>,
+    position_throttle: PositionThrottle,
+    ///Tracks that failed to start since the last Track started
+    failed_starts: u32,
+    handoff: Option<Handoff>,
 
     send: WatchSender<Arc<PlayerState>>,
     receive: WatchReceiver<Arc<PlayerState>>,
@@ -36,6 +83,7 @@ impl Player {
         guild: GuildId,
         songbird: Arc<Songbird>,
         lavalink: LavalinkClient,
+        config: &GuildConfig,
     ) -> Result<(Player, WatchReceiver<Arc<PlayerState>>), PlayerError> {
         let connection_info = songbird
             .join_gateway(guild, channel)
@@ -47,7 +95,15 @@ impl Player {
             .await
             .map_err(PlayerError::Lavalink)?;
 
-        let player_state = PlayerState::new(bot);
+        let defaults = PlayerDefaults::new(config);
+        if defaults.volume != DEFAULT_VOLUME {
+            lavalink
+                .volume(guild, defaults.volume)
+                .await
+                .map_err(PlayerError::Lavalink)?;
+        }
+
+        let player_state = PlayerState::new(bot, defaults);
         let (send, receive) = tokio::sync::watch::channel(Arc::new(player_state.clone()));
 
         let player = Player {
@@ -56,6 +112,13 @@ impl Player {
             lavalink,
             songbird,
             player_state,
+            retries: config.lavalink_retries,
+            history_limit: config.history_limit,
+            defaults,
+            last_restart: None,
+            position_throttle: PositionThrottle::default(),
+            failed_starts: 0,
+            handoff: None,
 
             send,
             receive: receive.clone(),
@@ -72,6 +135,12 @@ impl Player {
         self.lavalink.clone()
     }
 
+    ///Moves the Player to another Lavalink Node
+    /// - Takes effect with the next Reconnect
+    pub fn set_lavalink(&mut self, lavalink: LavalinkClient) {
+        self.lavalink = lavalink;
+    }
+
     pub fn get_channel(&self) -> ChannelId {
         self.channel
     }
@@ -80,23 +149,34 @@ impl Player {
         self.player_state.bot
     }
 
+    ///Resumes the Player, resuming a playing Player changes nothing
     pub async fn resume(&mut self) -> Result<(), PlayerError> {
+        if self.player_state.play_state == PlayState::Play {
+            return Ok(());
+        }
         self.lavalink
             .resume(self.guild)
             .await
             .map_err(PlayerError::Lavalink)?;
-        self.player_state.play_state = PlayState::Play;
+        self.cancel_handoff();
+        self.player_state.set_play_state(PlayState::Play);
 
         self.send.send(Arc::new(self.player_state.clone())).ok();
         Ok(())
     }
 
+    ///Pauses the Player, pausing a paused Player changes nothing
     pub async fn pause(&mut self) -> Result<(), PlayerError> {
+        if self.player_state.play_state == PlayState::Pause {
+            return Ok(());
+        }
         self.lavalink
             .pause(self.guild)
             .await
             .map_err(PlayerError::Lavalink)?;
-        self.player_state.play_state = PlayState::Pause;
+        //The scheduled Handoff assumed the Track keeps playing
+        self.cancel_handoff();
+        self.player_state.set_play_state(PlayState::Pause);
 
         self.send.send(Arc::new(self.player_state.clone())).ok();
         Ok(())
@@ -115,36 +195,76 @@ impl Player {
             return Ok(());
         }
 
-        let mut changed = false;
+        if self.player_state.skip(i, self.history_limit) {
+            self.send.send(Arc::new(self.player_state.clone())).ok();
+        }
 
-        //If loop is one, move the current track to history, so a new Track gets played
-        if let Some((_, track)) = self.player_state.current.take() {
-            match self.player_state.playback {
-                Playback::AllLoop => self.push_to_playlist_back(track),
-                _ => self.push_to_history_front(track),
-            }
-            changed = true;
+        retry(self.retries, || self.lavalink.stop(self.guild)).await
+    }
+
+    pub async fn skip_to(&mut self, index: usize) -> Result<(), PlayerError> {
+        if index >= self.player_state.playlist.len() {
+            return Err(PlayerError::IndexOutOfRange(index));
         }
 
-        for _i in 0..i - 1 {
-            if let Some(track) = self.player_state.playlist.pop_front() {
-                match self.player_state.playback {
-                    Playback::AllLoop => self.push_to_playlist_back(track),
-                    _ => self.push_to_history_front(track),
-                }
-            } else {
-                break;
-            }
+        let current_was_some = self.player_state.current.is_some();
+        //Move the current track out of the way, so the chosen one gets played next
+        self.player_state.skip(index + 1, self.history_limit);
+
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+
+        if current_was_some {
+            retry(self.retries, || self.lavalink.stop(self.guild)).await
+        } else {
+            self.play_next().await
         }
+    }
 
-        if changed {
-            self.send.send(Arc::new(self.player_state.clone())).ok();
+    ///Registers a Skip Vote for the current Track
+    /// - Skips once the required amount of votes is reached
+    pub async fn vote_skip(&mut self, user: UserId, required: usize) -> Result<(), PlayerError> {
+        let track = match &self.player_state.current {
+            None => return Err(PlayerError::NoCurrentSong()),
+            Some((_, track)) => track.track.clone(),
+        };
+
+        //Reset votes if they belong to another Track
+        let votes = match self.player_state.skip_votes.take() {
+            Some(votes) if votes.track.eq(&track) => votes,
+            _ => SkipVotes {
+                track,
+                users: HashSet::new(),
+                required,
+            },
+        };
+        let mut votes = SkipVotes { required, ..votes };
+        votes.users.insert(user);
+
+        if votes.users.len() >= votes.required {
+            return self.skip(1).await;
         }
+        self.player_state.skip_votes = Some(votes);
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
+    }
 
-        self.lavalink
-            .stop(self.guild)
-            .await
-            .map_err(PlayerError::Lavalink)
+    ///Previous Button behaviour
+    /// - A single press restarts the current Track
+    /// - A second press within the PREVIOUS_WINDOW plays the previous Track from the History
+    /// - Without a current Track, the previous Track is played right away
+    /// - With an empty History, the second press restarts the current Track again
+    pub async fn previous(&mut self) -> Result<(), PlayerError> {
+        let recently_restarted = self
+            .last_restart
+            .take()
+            .map(|last| last.elapsed() < PREVIOUS_WINDOW)
+            .unwrap_or(false);
+        if self.player_state.previous_goes_back(recently_restarted) {
+            return self.back_skip(1).await;
+        }
+
+        self.last_restart = Some(Instant::now());
+        self.jump(Duration::from_secs(0)).await
     }
 
     pub async fn back_skip(&mut self, i: usize) -> Result<(), PlayerError> {
@@ -152,23 +272,8 @@ impl Player {
             return Ok(());
         }
 
-        let mut changed = false;
-        let mut current_was_some = false;
-
-        if let Some((_, track)) = self.player_state.current.take() {
-            self.push_to_playlist_front(track);
-            changed = true;
-            current_was_some = true;
-        }
-
-        for _i in 0..i {
-            if let Some(history_track) = self.player_state.history.pop_front() {
-                self.push_to_playlist_front(history_track);
-                changed = true;
-            }
-        }
-
-        if changed {
+        let current_was_some = self.player_state.current.is_some();
+        if self.player_state.back_skip(i) {
             self.send.send(Arc::new(self.player_state.clone())).ok();
         }
         if current_was_some {
@@ -181,74 +286,99 @@ impl Player {
         }
     }
 
-    fn push_to_history_front(&mut self, track: Track) {
-        if self.player_state.history.is_full() {
-            self.player_state
-                .history
-                .pop_back()
-                .expect("History is empty");
-        }
-        self.player_state
-            .history
-            .push_front(track)
-            .expect("History is full");
-    }
-
-    fn push_to_playlist_back(&mut self, track: Track) {
-        if self.player_state.playlist.is_full() {
-            self.player_state
-                .playlist
-                .pop_back()
-                .expect("Playlist is empty");
-        }
-        self.player_state
-            .playlist
-            .push_back(track)
-            .expect("Playlist is full");
-    }
-
-    fn push_to_playlist_front(&mut self, track: Track) {
-        if self.player_state.playlist.is_full() {
-            self.player_state
-                .playlist
-                .pop_back()
-                .expect("Playlist is empty");
-        }
+    fn push_to_history_front(&mut self, track: Track, reason: HistoryReason) {
         self.player_state
-            .playlist
-            .push_front(track)
-            .expect("Playlist is full");
+            .push_to_history_front(track, reason, self.history_limit);
     }
 
+    ///Appends Tracks to the Playlist and returns what happened to them
+    /// - Skips Tracks that are already queued or current, if dedupe is set
+    /// - Fails only if not a single Track fit into the Playlist
     pub async fn enqueue(
         &mut self,
         tracks: impl Iterator<Item = Track>,
-    ) -> Result<(), PlayerError> {
-        for (i, track) in tracks.enumerate() {
-            let res = self.player_state.playlist.push_back(track);
-            if i == 0 {
-                res.map_err(PlayerError::PlaylistFull)?;
-            }
-        }
-
+        dedupe: bool,
+    ) -> Result<Enqueued, PlayerError> {
+        let enqueued = self.player_state.enqueue(tracks, dedupe)?;
         if self.player_state.current.is_none() {
             self.play_next().await?;
         } else {
             self.send.send(Arc::new(self.player_state.clone())).ok();
         }
-        Ok(())
+        Ok(enqueued)
     }
 
     pub async fn jump(&mut self, pos: Duration) -> Result<(), PlayerError> {
-        if self.player_state.current.is_some() {
-            return self
-                .lavalink
-                .jump_to_time(self.guild, pos)
-                .await
-                .map_err(PlayerError::Lavalink);
+        match &self.player_state.current {
+            None => return Err(PlayerError::NoCurrentSong()),
+            Some((_, track)) if is_stream(track) => return Err(PlayerError::NotSeekable()),
+            _ => {}
         }
+        retry(self.retries, || self.lavalink.jump_to_time(self.guild, pos)).await?;
+
+        //Show the new Position right away, Lavalink does not send updates while paused
+        self.set_position(pos);
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
+    }
+
+    ///Stores the Position of the current Track as of now
+    fn set_position(&mut self, position: Duration) {
+        //The scheduled Handoff was timed for the old Position
+        self.cancel_handoff();
+        self.player_state.set_position(position);
+    }
+
+    ///Jumps to a percentage of the current Track, clamped to 0-100
+    pub async fn jump_percent(&mut self, pct: f32) -> Result<(), PlayerError> {
+        let pct = finite(pct as f64)?;
+        self.jump_fraction(pct / 100.0).await
+    }
+
+    ///Jumps to a fraction of the current Track, clamped to 0.0-1.0
+    /// - Resolved against the length of the current Track, not a length the Client saw
+    pub async fn jump_fraction(&mut self, fraction: f64) -> Result<(), PlayerError> {
+        let (_, track) = self
+            .player_state
+            .current
+            .as_ref()
+            .ok_or(PlayerError::NoCurrentSong())?;
+        let length = match &track.info {
+            Some(info) if !info.is_stream && info.length > 0 => info.length,
+            _ => return Err(PlayerError::UnknownLength()),
+        };
+        let fraction = finite(fraction)?.clamp(0.0, 1.0);
+        let pos = Duration::from_millis((length as f64 * fraction) as u64);
+        self.jump(pos).await
+    }
 
-        return Err(PlayerError::NoCurrentSong());
+    ///Moves a queued Track to the front, so it gets played after the current one
+    pub fn play_next_track(&mut self, index: usize) -> Result<(), PlayerError> {
+        let track = self
+            .player_state
+            .playlist
+            .remove(index)
+            .ok_or(PlayerError::IndexOutOfRange(index))?;
+        //There is room again, as the Track was just removed
+        self.player_state.playlist.push_front(track).ok();
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
+    }
+
+    ///Queues the last finished Track again, right up next
+    /// - Plays it right away, if nothing is current
+    pub async fn replay_last(&mut self) -> Result<(), PlayerError> {
+        let (track, _) = self
+            .player_state
+            .history
+            .pop_front()
+            .ok_or(PlayerError::EmptyHistory())?;
+        self.player_state.push_to_playlist_front(track);
+        if self.player_state.current.is_none() {
+            return self.play_next().await;
+        }
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
     }
 
     pub fn clear_queue(&mut self) {
@@ -258,6 +388,135 @@ impl Player {
         }
     }
 
+    pub fn clear_history(&mut self) {
+        if self.player_state.clear_history() {
+            self.send.send(Arc::new(self.player_state.clone())).ok();
+        }
+    }
+
+    ///Applies a Filter preset, replacing the active one
+    pub async fn set_filter(&mut self, filter: AudioFilter) -> Result<(), PlayerError> {
+        let mut state = self.player_state.clone();
+        state.filter = Some(filter);
+        self.apply_filters(state).await
+    }
+
+    ///Removes the active Filter and restores default playback
+    /// - Speed and Pitch are kept
+    pub async fn clear_filter(&mut self) -> Result<(), PlayerError> {
+        if self.player_state.filter.is_none() {
+            return Ok(());
+        }
+        let mut state = self.player_state.clone();
+        state.filter = None;
+        self.apply_filters(state).await
+    }
+
+    ///Sets the Volume in percent, at most VOLUME_LIMIT
+    pub async fn set_volume(&mut self, volume: u16) -> Result<(), PlayerError> {
+        let volume = volume.min(VOLUME_LIMIT);
+        self.lavalink
+            .volume(self.guild, volume)
+            .await
+            .map_err(PlayerError::Lavalink)?;
+        self.player_state.volume = volume;
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
+    }
+
+    ///Sets the playback speed, clamped to a sane range
+    pub async fn set_speed(&mut self, speed: f32) -> Result<(), PlayerError> {
+        finite(speed as f64)?;
+        let mut state = self.player_state.clone();
+        state.speed = speed.clamp(SPEED_RANGE.0, SPEED_RANGE.1);
+        self.apply_filters(state).await
+    }
+
+    ///Sets the pitch, clamped to a sane range
+    pub async fn set_pitch(&mut self, pitch: f32) -> Result<(), PlayerError> {
+        finite(pitch as f64)?;
+        let mut state = self.player_state.clone();
+        state.pitch = pitch.clamp(PITCH_RANGE.0, PITCH_RANGE.1);
+        self.apply_filters(state).await
+    }
+
+    ///Sends the Filters of the new State to Lavalink and takes the State on success
+    async fn apply_filters(&mut self, mut state: PlayerState) -> Result<(), PlayerError> {
+        self.lavalink
+            .set_filters(self.guild, state.filters())
+            .await
+            .map_err(PlayerError::Lavalink)?;
+
+        //Anchor the progress, so the elapsed time before the change keeps its old rate
+        if let Some(((pos, when), _)) = state.current.borrow_mut() {
+            if self.player_state.play_state == PlayState::Play {
+                *pos += when.elapsed().mul_f64(self.player_state.rate());
+            }
+            *when = Instant::now();
+        }
+        self.player_state = state;
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
+    }
+
+    ///Clears the Filter if it is active, otherwise applies it
+    pub async fn toggle_filter(&mut self, filter: AudioFilter) -> Result<(), PlayerError> {
+        if self.player_state.filter == Some(filter) {
+            self.clear_filter().await
+        } else {
+            self.set_filter(filter).await
+        }
+    }
+
+    ///Restores a persisted Player
+    /// - The current Track continues from its Position, like after a Takeover
+    /// - History is ordered from the most recent Track
+    /// - Reasons are not persisted, restored Tracks count as finished
+    pub async fn restore(
+        &mut self,
+        playback: Playback,
+        current: Option<(Track, Duration)>,
+        playlist: Vec<Track>,
+        history: Vec<Track>,
+    ) -> Result<(), PlayerError> {
+        self.player_state.playback = playback;
+        for track in history.into_iter().rev() {
+            self.push_to_history_front(track, HistoryReason::Finished);
+        }
+        for track in playlist {
+            if self.player_state.playlist.push_back(track).is_err() {
+                break;
+            }
+        }
+        self.player_state.current = current.map(|(track, pos)| ((pos, Instant::now()), track));
+        self.restore_session().await?;
+        if self.player_state.current.is_none() && !self.player_state.playlist.is_empty() {
+            return self.play_next().await;
+        }
+        Ok(())
+    }
+
+    ///Sets the Playback, setting the current one again changes nothing
+    pub fn set_playback(&mut self, playback: Playback) {
+        if self.player_state.set_playback(playback) {
+            self.send.send(Arc::new(self.player_state.clone())).ok();
+        }
+    }
+
+    ///Sets Shuffle, so new and recycled Tracks land at random positions of the Playlist
+    /// - Already queued Tracks keep their order
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        if self.player_state.shuffle != shuffle {
+            self.player_state.shuffle = shuffle;
+            self.send.send(Arc::new(self.player_state.clone())).ok();
+        }
+    }
+
+    pub fn toggle_shuffle(&mut self) {
+        self.set_shuffle(!self.player_state.shuffle);
+    }
+
+    ///Toggles the Playback, setting the current one again goes back to Normal
     pub fn playback(&mut self, playback: Playback) {
         if self.player_state.playback != playback {
             self.player_state.playback = playback;
@@ -267,7 +526,97 @@ impl Player {
         self.send.send(Arc::new(self.player_state.clone())).ok();
     }
 
-    pub async fn disconnect(self) -> Result<(), PlayerError> {
+    ///Re-establishes the Lavalink Session and plays the current Track from its last position
+    /// - Playlist and History are kept
+    /// - The Client has to be connected, a closed one is renewed through its NodePool first
+    pub async fn reconnect(&mut self) -> Result<(), PlayerError> {
+        let connection_info = self
+            .songbird
+            .join_gateway(self.guild, self.channel)
+            .await
+            .1
+            .map_err(PlayerError::SongbirdJoin)?;
+        self.lavalink
+            .create_session(&connection_info)
+            .await
+            .map_err(PlayerError::Lavalink)?;
+        self.restore_session().await
+    }
+
+    ///The State to keep, when leaving without clearing the Queue
+    /// - The Position of the current Track is frozen as of now
+    pub fn keep(&self) -> PlayerState {
+        self.player_state.kept()
+    }
+
+    ///Continues with the kept State of a previous Player in the Channel
+    /// - The current Track continues from where it was left
+    pub async fn take_over(&mut self, mut state: PlayerState) -> Result<(), PlayerError> {
+        state.take_over(self.player_state.bot);
+        self.player_state = state;
+        self.restore_session().await?;
+        if self.player_state.current.is_none() && !self.player_state.playlist.is_empty() {
+            return self.play_next().await;
+        }
+        Ok(())
+    }
+
+    ///Brings a fresh Lavalink Session up to the State of the Player
+    /// - Applies Filters and Volume and continues the current Track at its Position
+    async fn restore_session(&mut self) -> Result<(), PlayerError> {
+        self.lavalink
+            .set_filters(self.guild, self.player_state.filters())
+            .await
+            .map_err(PlayerError::Lavalink)?;
+        if self.player_state.volume != DEFAULT_VOLUME {
+            self.lavalink
+                .volume(self.guild, self.player_state.volume)
+                .await
+                .map_err(PlayerError::Lavalink)?;
+        }
+
+        let position = match self.player_state.position() {
+            None => {
+                self.send.send(Arc::new(self.player_state.clone())).ok();
+                return Ok(());
+            }
+            Some(position) => position,
+        };
+        if let Some(((pos, when), track)) = self.player_state.current.borrow_mut() {
+            //Live Streams can only continue from now
+            let position = if is_stream(track) {
+                Duration::from_secs(0)
+            } else {
+                position
+            };
+            self.lavalink
+                .play(self.guild, track.clone())
+                .start_time(position)
+                .start()
+                .await
+                .map_err(PlayerError::Lavalink)?;
+            *pos = position;
+            *when = Instant::now();
+        }
+        if self.player_state.play_state == PlayState::Pause {
+            self.lavalink
+                .pause(self.guild)
+                .await
+                .map_err(PlayerError::Lavalink)?;
+        }
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        Ok(())
+    }
+
+    ///Drops every Track and announces the empty State
+    pub fn reset(&mut self) {
+        self.drop_handoff();
+        self.player_state = PlayerState::new(self.player_state.bot, self.defaults);
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+    }
+
+    pub async fn disconnect(mut self) -> Result<(), PlayerError> {
+        self.drop_handoff();
         self.songbird
             .get(self.guild)
             .ok_or(PlayerError::NotInAVoiceChannel())?;
@@ -283,102 +632,366 @@ impl Player {
         Ok(())
     }
 
-    pub fn search<F, Fut>(&self, query: String, callback: F)
-    where
-        F: Send + Sync + 'static + FnOnce(Result<Vec<Track>, PlayerError>) -> Fut,
-        Fut: Future<Output = ()> + Send + Sync,
-    {
+    ///Searches through the Lavalink Node of the Player, without any side effects
+    /// - The returned Future does not borrow the Player, so it can be awaited after unlocking
+    /// - Fails with NoMatches instead of returning no Tracks
+    pub fn search(&self, query: String) -> impl Future<Output = Result<Tracks, PlayerError>> {
         let lavalink = self.lavalink.clone();
-        tokio::spawn(async move {
-            match lavalink.auto_search_tracks(query).await {
-                Err(e) => callback(Err(PlayerError::Lavalink(e))).await,
-                Ok(tracks) => {
-                    if tracks.load_type.eq("LOAD_FAILED") {
-                        callback(Err(PlayerError::SearchFailed(tracks.load_type))).await;
-                        return;
-                    }
-                    callback(Ok(tracks.tracks)).await;
-                }
-            }
-        });
+        async move {
+            let tracks = lavalink
+                .auto_search_tracks(query.as_str())
+                .await
+                .map_err(PlayerError::Lavalink)?;
+            load_result(query, tracks)
+        }
     }
 
+    ///Plays the next Track, Tracks that fail to start are skipped
     async fn play_next(&mut self) -> Result<(), PlayerError> {
-        let mut changed = false;
-
-        match self.player_state.playback {
-            //Add Current to History
-            Playback::Normal => {
-                if let Some((_, track)) = self.player_state.current.take() {
-                    self.push_to_history_front(track);
-                    changed = true;
-                }
-            }
-            //Add Current to Playlist
-            Playback::AllLoop => {
-                if let Some((_, track)) = self.player_state.current.take() {
-                    self.push_to_playlist_back(track);
-                    changed = true;
-                }
-            }
-            //Reset Duration of Current
-            Playback::OneLoop => {
-                if let Some(((duration, instant), _)) = self.player_state.current.borrow_mut() {
-                    *duration = Duration::from_secs(0);
-                    *instant = Instant::now();
+        loop {
+            match self.start_next().await {
+                Err(e) if !e.is_fatal() && self.player_state.current.is_some() => {
+                    if !self.track_failed(e.to_string()) {
+                        return self.stop_failing().await;
+                    }
                 }
+                result => return result,
             }
         }
+    }
 
-        //If current is None: Pull new one from Playlist
-        if self.player_state.current.is_none() {
-            if let Some(track) = self.player_state.playlist.pop_front() {
-                self.player_state.current = Some(((Duration::from_secs(0), Instant::now()), track));
-                self.player_state.play_state = PlayState::Play;
-                changed = true;
-            }
+    ///Records that the current Track could not be played and moves it to the History
+    /// - Returns false once too many Tracks failed in a row
+    fn track_failed(&mut self, reason: String) -> bool {
+        if let Some((_, track)) = self.player_state.current.take() {
+            let title = track
+                .info
+                .as_ref()
+                .map_or("Unknown Track".to_string(), |i| i.title.clone());
+            warn!(
+                "Track failed to play. {:?}, {:?}, {:?}",
+                self.guild, title, reason
+            );
+            self.player_state.failed = Some((title, reason));
+            self.push_to_history_front(track, HistoryReason::Error);
         }
+        self.failed_starts += 1;
+        self.failed_starts < MAX_FAILED_STARTS
+    }
 
-        if changed {
-            self.send.send(Arc::new(self.player_state.clone())).ok();
-        }
+    ///Stops the Player, so a Playlist full of broken Tracks is not skipped through endlessly
+    async fn stop_failing(&mut self) -> Result<(), PlayerError> {
+        warn!(
+            "Too many Tracks failed in a row, stopping. {:?}, Failed: {}",
+            self.guild, self.failed_starts
+        );
+        self.failed_starts = 0;
+        self.player_state.current = None;
+        self.player_state.skip_votes = None;
+        self.send.send(Arc::new(self.player_state.clone())).ok();
+        retry(self.retries, || self.lavalink.stop(self.guild)).await
+    }
+
+    async fn start_next(&mut self) -> Result<(), PlayerError> {
+        self.advance();
 
         //Start if Current is some. Stop if Current is none.
+        //Only the Lavalink call is retried, so the State changes above happen once
         match &self.player_state.current {
-            None => self
-                .lavalink
-                .stop(self.guild)
-                .await
-                .map_err(PlayerError::Lavalink),
-            Some((_, track)) => self
-                .lavalink
-                .play(self.guild, track.clone())
-                .start()
+            None => retry(self.retries, || self.lavalink.stop(self.guild)).await,
+            Some((_, track)) => {
+                retry(self.retries, || {
+                    self.lavalink.play(self.guild, track.clone()).start()
+                })
                 .await
-                .map_err(PlayerError::Lavalink),
+            }
         }
     }
 
-    pub fn update(&mut self, update: PlayerUpdate) {
-        let now = Instant::now();
-        let new_pos = Duration::from_millis(update.state.position as u64);
-        if let Some(((pos, when), _)) = self.player_state.current.borrow_mut() {
-            *pos = new_pos;
-            *when = now;
+    ///Moves on to the next Track in the State, according to the Playback
+    /// - Does not tell Lavalink anything
+    fn advance(&mut self) {
+        if self.player_state.advance(self.history_limit) {
             self.send.send(Arc::new(self.player_state.clone())).ok();
         }
     }
 
-    pub async fn track_end(&mut self, _end: TrackFinish) -> Result<(), PlayerError> {
-        self.play_next().await
-    }
+    ///Schedules the next Track to start right before the current one ends, so there is no gap
+    /// - The Task checks the latest State again, so changes to the Queue in the meantime win
+    /// - OneLoop and Live Streams are left to the regular Track end
+    fn schedule_handoff(&mut self) {
+        //A started Handoff waits for the REPLACED Track end
+        if self.handoff.as_ref().map_or(false, Handoff::started) {
+            return;
+        }
+        let (current, next) = match self.handoff_target() {
+            None => return self.cancel_handoff(),
+            Some(target) => target,
+        };
+        //The pending Task keeps up with the Position itself
+        if self.handoff.as_ref().map(|h| &h.track) == Some(&next.track) {
+            return;
+        }
+        self.cancel_handoff();
 
-    pub fn track_start(&mut self, _start: TrackStart) {
-        //TODO maybe do something, maybe dont
+        let lavalink = self.lavalink.clone();
+        let guild = self.guild;
+        let receive = self.receive.clone();
+        let encoded = next.track.clone();
+        let started = Arc::new(AtomicBool::new(false));
+        let task_started = started.clone();
+        let task = tokio::spawn(async move {
+            //Sleeps until right before the end, judged by the latest Position Lavalink reported
+            // - The Position is extrapolated in between, so it is checked again after every sleep
+            loop {
+                let wait = {
+                    let state = receive.borrow();
+                    let unchanged = state.play_state == PlayState::Play
+                        && state.playback != Playback::OneLoop
+                        && state.current.as_ref().map(|(_, t)| &t.track) == Some(&current)
+                        && state.playlist.front().map(|t| &t.track) == Some(&next.track);
+                    match state.remaining() {
+                        Some(remaining) if unchanged => {
+                            remaining.checked_sub(HANDOFF_LEAD).unwrap_or_default()
+                        }
+                        _ => return,
+                    }
+                };
+                if wait < HANDOFF_PRECISION {
+                    break;
+                }
+                tokio::time::sleep(wait).await;
+            }
+            //Replacing ends the current Track, track_end then only catches up with the State
+            task_started.store(true, Ordering::SeqCst);
+            if let Err(e) = lavalink.play(guild, next).replace(true).start().await {
+                //The regular Track end takes over
+                task_started.store(false, Ordering::SeqCst);
+                warn!("Could not hand off to next Track. {:?}, {:?}", guild, e);
+            }
+        });
+        self.handoff = Some(Handoff {
+            track: encoded,
+            task,
+            started,
+        });
     }
-}
 
-#[derive(Copy, Clone, Debug, AsRefStr, Eq, PartialEq)]
+    ///The encoded current Track and the next Track
+    /// - None, if there is nothing to hand off to within the Lookahead
+    fn handoff_target(&self) -> Option<(String, Track)> {
+        if self.player_state.playback == Playback::OneLoop
+            || self.player_state.play_state != PlayState::Play
+        {
+            return None;
+        }
+        let (current, next) = match (
+            &self.player_state.current,
+            self.player_state.playlist.front(),
+        ) {
+            (Some((_, current)), Some(next)) => (current, next),
+            _ => return None,
+        };
+        if self.player_state.remaining()? > HANDOFF_LOOKAHEAD {
+            return None;
+        }
+        Some((current.track.clone(), next.clone()))
+    }
+
+    ///Aborts the scheduled Handoff, a started one is kept until its REPLACED Track end
+    fn cancel_handoff(&mut self) {
+        self.handoff = self.handoff.take().and_then(Handoff::cancel);
+    }
+
+    ///Forgets the Handoff, even a started one, the State it was meant for is gone
+    fn drop_handoff(&mut self) {
+        if let Some(handoff) = self.handoff.take() {
+            handoff.task.abort();
+        }
+    }
+
+    ///Catches up with a Handoff, the next Track is already playing
+    /// - Falls back to stopping, if the Queue changed after all, so the regular Track end takes over
+    async fn handed_off(&mut self) -> Result<(), PlayerError> {
+        //Only a started Handoff survives cancel
+        let prepared = self
+            .handoff
+            .take()
+            .and_then(Handoff::cancel)
+            .map(|h| h.track);
+        let front = self.player_state.playlist.front().map(|t| &t.track);
+        if prepared.is_some()
+            && prepared.as_ref() == front
+            && self.player_state.playback != Playback::OneLoop
+        {
+            self.player_state.failed = None;
+            self.advance();
+            return Ok(());
+        }
+        retry(self.retries, || self.lavalink.stop(self.guild)).await
+    }
+
+    pub fn update(&mut self, update: PlayerUpdate) {
+        //Lavalink already plays the next Track, its Position applies once REPLACED was handled
+        if self.handoff.as_ref().map_or(false, Handoff::started) {
+            return;
+        }
+        let now = Instant::now();
+        let new_pos = Duration::from_millis(update.state.position as u64);
+        if let Some(((pos, when), _)) = self.player_state.current.borrow_mut() {
+            *pos = new_pos;
+            *when = now;
+            //The Position is always kept fresh, but only sent now and then
+            // - Other changes are sent right away and carry the fresh Position anyway
+            // - A pending Handoff times the next Track by the sent Position
+            if self.position_throttle.pass(now) || self.handoff.is_some() {
+                self.send.send(Arc::new(self.player_state.clone())).ok();
+            }
+        }
+        self.schedule_handoff();
+    }
+
+    pub async fn track_end(&mut self, end: TrackFinish) -> Result<(), PlayerError> {
+        //The current Track may also have finished on its own, right before the Handoff started the next one
+        if end.reason.eq(REPLACED) || self.handoff.as_ref().map_or(false, Handoff::started) {
+            return self.handed_off().await;
+        }
+        self.cancel_handoff();
+        if end.reason.eq(LOAD_FAILED) {
+            if !self.track_failed("Lavalink could not load the Track".to_string()) {
+                return self.stop_failing().await;
+            }
+        } else {
+            //A Track played through, so the last failure is old news
+            self.player_state.failed = None;
+        }
+        self.play_next().await
+    }
+
+    pub fn track_start(&mut self, _start: TrackStart) {
+        self.failed_starts = 0;
+    }
+}
+
+///Whether Lavalink marked the Track as Live Stream
+/// - Streams have no meaningful Length and can not be seeked
+pub fn is_stream(track: &Track) -> bool {
+    track.info.as_ref().map(|i| i.is_stream).unwrap_or(false)
+}
+
+///Passes finite Numbers through, clamping NaN or Infinity would not make them sane
+/// - A NaN Speed would poison every Position calculation
+fn finite(value: f64) -> Result<f64, PlayerError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(PlayerError::NotFinite(value))
+    }
+}
+
+///How many Tracks an Enqueue added, skipped as Duplicates and dropped, because the Playlist was full
+/// - Rejected Tracks broke the Length limits of the Guild
+#[derive(Clone, Debug, Default)]
+pub struct Enqueued {
+    pub added: usize,
+    pub skipped: usize,
+    pub dropped: usize,
+    pub rejected: usize,
+    ///Where the added Tracks landed, 0 is now playing and 1 is up next
+    /// - In the order the Tracks were given, Positions already account for later Tracks
+    pub positions: Vec<usize>,
+    ///Title of the first added Track
+    /// - None, if nothing was added or the Track has no Info
+    pub title: Option<String>,
+}
+
+impl Display for Enqueued {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let title = self.title.as_deref().unwrap_or("Unknown Track");
+        match (self.added, self.positions.iter().min().copied()) {
+            (1, Some(0)) => write!(f, "Now playing '{}'", title)?,
+            (1, Some(position)) => write!(f, "Added '{}' at position {}", title, position)?,
+            (added, Some(0)) => write!(f, "Added {} Tracks, playing now", added)?,
+            (added, Some(position)) => {
+                write!(f, "Added {} Tracks from position {}", added, position)?
+            }
+            (added, None) => write!(f, "Added {} Tracks", added)?,
+        }
+        if self.dropped > 0 {
+            write!(f, ", Playlist is full, dropped {}", self.dropped)?;
+        }
+        if self.skipped > 0 {
+            write!(f, ", skipped {} Duplicates", self.skipped)?;
+        }
+        if self.rejected > 0 {
+            write!(f, ", rejected {} for their Length", self.rejected)?;
+        }
+        Ok(())
+    }
+}
+
+///The next Track, scheduled to start right before the current one ends
+struct Handoff {
+    ///Encoded next Track
+    track: String,
+    task: JoinHandle<()>,
+    ///Set once the next Track was started, Lavalink then ends the current one as REPLACED
+    started: Arc<AtomicBool>,
+}
+
+impl Handoff {
+    fn started(&self) -> bool {
+        self.started.load(Ordering::SeqCst)
+    }
+
+    ///Aborts the Handoff, unless the next Track was started already
+    /// - A started Handoff is kept, so its REPLACED Track end is still recognised
+    fn cancel(self) -> Option<Handoff> {
+        if self.started() {
+            return Some(self);
+        }
+        self.task.abort();
+        None
+    }
+}
+
+///Lets Position only updates through at most once per POSITION_UPDATE_INTERVAL
+/// - A Throttle, not a Debounce: updates in between are dropped instead of sent later,
+///   the sent State carries when its Position was taken, so it can be extrapolated
+#[derive(Debug, Default)]
+struct PositionThrottle {
+    last_sent: Option<Instant>,
+}
+
+impl PositionThrottle {
+    ///Whether an update at that time goes through
+    fn pass(&mut self, now: Instant) -> bool {
+        let throttled = self.last_sent.map_or(false, |last| {
+            now.duration_since(last) < POSITION_UPDATE_INTERVAL
+        });
+        if !throttled {
+            self.last_sent = Some(now);
+        }
+        !throttled
+    }
+}
+
+///Tells failed and empty Loads apart from found Tracks
+/// - The Exception Lavalink sends with LOAD_FAILED is not kept by lavalink-rs, as Tracks has no
+///   field for it, so the Users only learn what could not be loaded
+fn load_result(query: String, tracks: Tracks) -> Result<Tracks, PlayerError> {
+    match tracks.load_type.as_str() {
+        LOAD_FAILED => Err(PlayerError::SearchFailed(format!(
+            "Lavalink could not load {}",
+            query
+        ))),
+        NO_MATCHES => Err(PlayerError::NoMatches(query)),
+        _ if tracks.tracks.is_empty() => Err(PlayerError::NoMatches(query)),
+        _ => Ok(tracks),
+    }
+}
+
+#[derive(Copy, Clone, Debug, AsRefStr, Eq, PartialEq)]
 pub enum PlayState {
     Play,
     Pause,
@@ -402,7 +1015,20 @@ impl PlayState {
     }
 }
 
+///How a Track ended up in the History
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum HistoryReason {
+    ///Played through
+    Finished,
+    ///Skipped while it was playing
+    Skipped,
+    ///Left the Queue without playing, e.g. jumped over by Skip To
+    Removed,
+    ///Failed to play
+    Error,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Playback {
     Normal,
     AllLoop,
@@ -419,22 +1045,123 @@ impl Display for Playback {
     }
 }
 
+///Filter presets applied through Lavalink
+/// - Lavalink keeps Filters for the whole Player, so they survive Track changes
+#[derive(Eq, PartialEq, Clone, Copy, Debug, AsRefStr)]
+pub enum AudioFilter {
+    BassBoost,
+    Nightcore,
+    Rotation,
+}
+
+impl AudioFilter {
+    const BASS_BOOST_GAINS: [f64; 6] = [0.2, 0.15, 0.1, 0.05, 0.0, -0.05];
+    const NIGHTCORE_SPEED: f64 = 1.2;
+
+    ///Speed change caused by the preset itself
+    pub fn speed(&self) -> f64 {
+        match self {
+            AudioFilter::Nightcore => Self::NIGHTCORE_SPEED,
+            _ => 1.0,
+        }
+    }
+
+    pub fn filters(&self) -> Filters {
+        match self {
+            AudioFilter::BassBoost => Filters {
+                equalizer: Some(
+                    Self::BASS_BOOST_GAINS
+                        .iter()
+                        .enumerate()
+                        .map(|(band, gain)| Band {
+                            band: band as u8,
+                            gain: *gain,
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            AudioFilter::Nightcore => Filters {
+                timescale: Some(TimeScale {
+                    speed: Some(Self::NIGHTCORE_SPEED),
+                    pitch: Some(Self::NIGHTCORE_SPEED),
+                    rate: Some(1.0),
+                }),
+                ..Default::default()
+            },
+            AudioFilter::Rotation => Filters {
+                rotation: Some(Rotation { rotation_hz: 0.2 }),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Display for AudioFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.as_ref())
+    }
+}
+
+///Runs a Lavalink call, retrying transient Errors with exponential backoff
+/// - Fatal Errors are returned right away, they need a new Session
+async fn retry<T, F, Fut>(retries: u32, call: F) -> Result<T, PlayerError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, LavalinkError>>,
+{
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match call().await.map_err(PlayerError::Lavalink) {
+            Err(e) if attempt < retries && e.is_lavalink_error() && !e.is_fatal() => {
+                attempt += 1;
+                warn!(
+                    "Lavalink call failed, retrying. Attempt: {}, {:?}",
+                    attempt, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            res => return res,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum PlayerError {
     #[error("LavalinkError occurred: {0:?}")]
     Lavalink(LavalinkError),
     #[error("Error joining Channel: {0:?}")]
     SongbirdJoin(JoinError),
+    #[error("Missing Connect or Speak Permission for Voice Channel: {0}")]
+    MissingPermissions(ChannelId),
     #[error("Error leaving Channel: {0:?}")]
     SongbirdLeave(JoinError),
     #[error("Not in a Voice Channel")]
     NotInAVoiceChannel(),
     #[error("Playlist is full: {0:?}")]
     PlaylistFull(CapacityError<Track>),
-    #[error("Search failed: {0:?}")]
+    #[error("Search failed: {0}")]
     SearchFailed(String),
+    #[error("Nothing found for: {0}")]
+    NoMatches(String),
     #[error("There is no current song")]
     NoCurrentSong(),
+    #[error("There is no finished song to replay")]
+    EmptyHistory(),
+    #[error("Index is out of range: {0:?}")]
+    IndexOutOfRange(usize),
+    #[error("The current song has no known length")]
+    UnknownLength(),
+    #[error("Live Streams can not be seeked")]
+    NotSeekable(),
+    #[error("Track is longer than the maximum of {0} seconds")]
+    TrackTooLong(u64),
+    #[error("Live Streams are not allowed")]
+    StreamsNotAllowed(),
+    #[error("Not a valid Number: {0}")]
+    NotFinite(f64),
 }
 
 impl PlayerError {
@@ -453,25 +1180,838 @@ impl PlayerError {
     }
 }
 
+///Playback and Volume every new or reset State starts with
+#[derive(Clone, Copy, Debug)]
+struct PlayerDefaults {
+    playback: Playback,
+    ///Volume in percent, at most VOLUME_LIMIT
+    volume: u16,
+}
+
+impl PlayerDefaults {
+    fn new(config: &GuildConfig) -> Self {
+        PlayerDefaults {
+            playback: config.playback.unwrap_or(Playback::Normal),
+            volume: config.volume.min(VOLUME_LIMIT),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PlayerState {
     pub bot: UserId,
     pub current: Option<((Duration, Instant), Track)>,
     pub playlist: ArrayDeque<[Track; MUSIC_QUEUE_LIMIT]>,
-    pub history: ArrayDeque<[Track; MUSIC_QUEUE_LIMIT]>,
+    ///Most recent Track first, with how it left
+    pub history: VecDeque<(Track, HistoryReason)>,
     pub play_state: PlayState,
     pub playback: Playback,
+    pub skip_votes: Option<SkipVotes>,
+    ///Title and Reason of the last Track that failed to play
+    pub failed: Option<(String, String)>,
+    pub filter: Option<AudioFilter>,
+    pub speed: f32,
+    pub pitch: f32,
+    ///Volume in percent, 100 is unchanged
+    pub volume: u16,
+    ///New Tracks are inserted at random positions, instead of appended
+    pub shuffle: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct SkipVotes {
+    pub track: String,
+    pub users: HashSet<UserId>,
+    pub required: usize,
 }
 
 impl PlayerState {
-    fn new(bot: UserId) -> Self {
+    fn new(bot: UserId, defaults: PlayerDefaults) -> Self {
         PlayerState {
             bot,
             current: None,
             playlist: ArrayDeque::new(),
-            history: ArrayDeque::new(),
+            history: VecDeque::new(),
             play_state: PlayState::Play,
-            playback: Playback::Normal,
+            playback: defaults.playback,
+            skip_votes: None,
+            failed: None,
+            filter: None,
+            speed: 1.0,
+            pitch: 1.0,
+            volume: defaults.volume,
+            shuffle: false,
+        }
+    }
+
+    ///Current position in the current Track
+    pub fn position(&self) -> Option<Duration> {
+        self.current.as_ref().map(|((pos, when), _)| {
+            if self.play_state == PlayState::Play {
+                *pos + when.elapsed().mul_f64(self.rate())
+            } else {
+                *pos
+            }
+        })
+    }
+
+    ///Copy of the State with the Position of the current Track frozen as of now
+    fn kept(&self) -> PlayerState {
+        let mut state = self.clone();
+        if let Some(position) = state.position() {
+            if let Some(((pos, _), _)) = state.current.borrow_mut() {
+                *pos = position;
+            }
+        }
+        state
+    }
+
+    ///Hands a kept State over to another Bot
+    /// - The frozen Position continues from now, the time in between does not count
+    fn take_over(&mut self, bot: UserId) {
+        self.bot = bot;
+        self.skip_votes = None;
+        if let Some(((_, when), _)) = self.current.borrow_mut() {
+            *when = Instant::now();
+        }
+    }
+
+    ///Appends Tracks to the Playlist, see Player::enqueue
+    /// - Positions count from the current Track, the first one becomes current if there is none
+    fn enqueue(
+        &mut self,
+        mut tracks: impl Iterator<Item = Track>,
+        dedupe: bool,
+    ) -> Result<Enqueued, PlayerError> {
+        let mut enqueued = Enqueued::default();
+        //Indices in the Playlist, shifted by later Tracks inserted in front of them
+        let mut indices: Vec<usize> = Vec::new();
+        while let Some(track) = tracks.next() {
+            //The same Live Stream twice in a row never makes sense
+            if (dedupe || is_stream(&track)) && self.is_queued(&track) {
+                enqueued.skipped += 1;
+                continue;
+            }
+            let title = track.info.as_ref().map(|i| i.title.clone());
+            let index = match self.push_upcoming(track) {
+                Ok(index) => index,
+                Err(e) => {
+                    if enqueued.added == 0 {
+                        return Err(PlayerError::PlaylistFull(e));
+                    }
+                    enqueued.dropped = 1 + tracks.count();
+                    break;
+                }
+            };
+            indices
+                .iter_mut()
+                .filter(|i| **i >= index)
+                .for_each(|i| *i += 1);
+            indices.push(index);
+            if enqueued.added == 0 {
+                enqueued.title = title;
+            }
+            enqueued.added += 1;
+        }
+
+        enqueued.positions = if self.current.is_none() {
+            //The first Track of the Playlist becomes current, so every Index is already one less
+            indices
+        } else {
+            indices.into_iter().map(|i| i + 1).collect()
+        };
+        Ok(enqueued)
+    }
+
+    ///Whether a Track with the same Uri is current or in the Playlist
+    /// - Tracks without info are never considered queued
+    fn is_queued(&self, track: &Track) -> bool {
+        let uri = match &track.info {
+            None => return false,
+            Some(info) => &info.uri,
+        };
+        let same = |t: &Track| t.info.as_ref().map(|i| i.uri.eq(uri)).unwrap_or(false);
+        self.current.as_ref().map(|(_, t)| same(t)).unwrap_or(false)
+            || self.playlist.iter().any(same)
+    }
+
+    ///Moves the current Track and the next i - 1 Tracks out of the Playlist
+    /// - The current Track counts as Skipped, the others as Removed, AllLoop recycles them instead
+    /// - Returns whether there was a current Track
+    fn skip(&mut self, i: usize, history_limit: usize) -> bool {
+        let mut changed = false;
+        //Skipped Tracks in AllLoop, in the order they were skipped
+        let mut recycled = Vec::new();
+
+        //If loop is one, move the current track to history, so a new Track gets played
+        if let Some((_, track)) = self.current.take() {
+            match self.playback {
+                Playback::AllLoop => recycled.push(track),
+                _ => self.push_to_history_front(track, HistoryReason::Skipped, history_limit),
+            }
+            changed = true;
+        }
+
+        for _i in 1..i {
+            if let Some(track) = self.playlist.pop_front() {
+                match self.playback {
+                    Playback::AllLoop => recycled.push(track),
+                    _ => self.push_to_history_front(track, HistoryReason::Removed, history_limit),
+                }
+            } else {
+                break;
+            }
+        }
+
+        //Recycled only now, so they can not land in front of the Track skipped to
+        // - Always at the back, even while shuffling
+        for track in recycled {
+            if self.playlist.is_full() {
+                self.playlist.pop_back().expect("Playlist is empty");
+            }
+            self.playlist.push_back(track).expect("Playlist is full");
+        }
+        changed
+    }
+
+    ///Moves on to the next Track according to the Playback, see Player::advance
+    /// - A Track that played through counts as Finished
+    /// - Returns whether the State changed
+    fn advance(&mut self, history_limit: usize) -> bool {
+        let mut changed = false;
+
+        match self.playback {
+            //Add Current to History
+            Playback::Normal => {
+                if let Some((_, track)) = self.current.take() {
+                    self.push_to_history_front(track, HistoryReason::Finished, history_limit);
+                    changed = true;
+                }
+            }
+            //Add Current to Playlist
+            Playback::AllLoop => {
+                if let Some((_, track)) = self.current.take() {
+                    self.push_to_playlist_back(track);
+                    changed = true;
+                }
+            }
+            //Reset Duration of Current
+            Playback::OneLoop => {
+                if let Some(((duration, instant), _)) = self.current.borrow_mut() {
+                    *duration = Duration::from_secs(0);
+                    *instant = Instant::now();
+                }
+            }
+        }
+
+        //If current is None: Pull new one from Playlist
+        if self.current.is_none() {
+            if let Some(track) = self.playlist.pop_front() {
+                self.current = Some(((Duration::from_secs(0), Instant::now()), track));
+                self.play_state = PlayState::Play;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.skip_votes = None;
+        }
+        changed
+    }
+
+    ///Moves the current Track and up to i Tracks from the History to the front of the Playlist
+    /// - They keep the order they were played in
+    /// - Returns whether anything moved
+    fn back_skip(&mut self, i: usize) -> bool {
+        let mut changed = false;
+        if let Some((_, track)) = self.current.take() {
+            self.push_to_playlist_front(track);
+            changed = true;
+        }
+        for _i in 0..i {
+            if let Some((history_track, _)) = self.history.pop_front() {
+                self.push_to_playlist_front(history_track);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    ///Forgets every played Track, Previous then has nothing to go back to
+    /// - Returns whether there was a History
+    fn clear_history(&mut self) -> bool {
+        let changed = !self.history.is_empty();
+        self.history.clear();
+        changed
+    }
+
+    ///Adds a finished Track to the History, the oldest ones beyond the limit are dropped
+    fn push_to_history_front(&mut self, track: Track, reason: HistoryReason, limit: usize) {
+        self.history.push_front((track, reason));
+        self.history.truncate(limit);
+    }
+
+    ///Recycles a Track into the Playlist, making room by dropping the last one if full
+    fn push_to_playlist_back(&mut self, track: Track) {
+        if self.playlist.is_full() {
+            self.playlist.pop_back().expect("Playlist is empty");
+        }
+        self.push_upcoming(track).expect("Playlist is full");
+    }
+
+    ///Adds a Track to the upcoming Playlist
+    /// - Appends it normally, inserts it at a random position while shuffling
+    /// - Returns the Index the Track landed at
+    fn push_upcoming(&mut self, track: Track) -> Result<usize, CapacityError<Track>> {
+        let index = if self.shuffle {
+            rand::thread_rng().gen_range(0..=self.playlist.len())
+        } else {
+            self.playlist.len()
+        };
+        self.playlist.insert(index, track).map(|_| index)
+    }
+
+    fn push_to_playlist_front(&mut self, track: Track) {
+        if self.playlist.is_full() {
+            self.playlist.pop_back().expect("Playlist is empty");
+        }
+        self.playlist.push_front(track).expect("Playlist is full");
+    }
+
+    ///Sets the Play State and returns whether it changed
+    /// - The Position is anchored to now, so no time passes while paused
+    fn set_play_state(&mut self, play_state: PlayState) -> bool {
+        if self.play_state == play_state {
+            return false;
+        }
+        if let Some(position) = self.position() {
+            self.set_position(position);
+        }
+        self.play_state = play_state;
+        true
+    }
+
+    ///Stores the Position of the current Track as of now
+    fn set_position(&mut self, position: Duration) {
+        if let Some(((pos, when), _)) = self.current.borrow_mut() {
+            *pos = position;
+            *when = Instant::now();
+        }
+    }
+
+    ///Sets the Playback and returns whether it changed
+    fn set_playback(&mut self, playback: Playback) -> bool {
+        let changed = self.playback != playback;
+        self.playback = playback;
+        changed
+    }
+
+    ///Time until the current Track ends, at the current rate
+    /// - None for Live Streams or Tracks without Info
+    pub fn remaining(&self) -> Option<Duration> {
+        let (_, track) = self.current.as_ref()?;
+        if is_stream(track) {
+            return None;
+        }
+        let length = Duration::from_millis(track.info.as_ref()?.length);
+        let position = self.position()?;
+        Some(
+            length
+                .checked_sub(position)
+                .unwrap_or_default()
+                .div_f64(self.rate()),
+        )
+    }
+
+    ///Whether Previous goes back to the History instead of restarting the current Track
+    /// - Live Streams can not seek to their start, so they always go back
+    fn previous_goes_back(&self, recently_restarted: bool) -> bool {
+        match &self.current {
+            None => true,
+            Some((_, track)) if is_stream(track) => true,
+            Some(_) => recently_restarted && !self.history.is_empty(),
+        }
+    }
+
+    ///Factor by which the Track progresses faster than real time
+    pub fn rate(&self) -> f64 {
+        self.speed as f64 * self.filter.map_or(1.0, |f| f.speed())
+    }
+
+    ///Lavalink Filters for the preset, combined with speed and pitch
+    fn filters(&self) -> Filters {
+        let mut filters = self.filter.map_or_else(Filters::default, |f| f.filters());
+        if self.speed != 1.0 || self.pitch != 1.0 {
+            let timescale = filters.timescale.get_or_insert(TimeScale {
+                speed: None,
+                pitch: None,
+                rate: None,
+            });
+            timescale.speed = Some(timescale.speed.unwrap_or(1.0) * self.speed as f64);
+            timescale.pitch = Some(timescale.pitch.unwrap_or(1.0) * self.pitch as f64);
+        }
+        filters
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::config::tests::guild_config;
+
+    ///Track as Lavalink sends it, with a Length in milliseconds
+    pub fn track(uri: &str, length: u64, is_stream: bool) -> Track {
+        serde_json::from_value(track_json(uri, length, is_stream)).expect("Invalid Track")
+    }
+
+    fn track_json(uri: &str, length: u64, is_stream: bool) -> serde_json::Value {
+        serde_json::json!({
+            "track": format!("encoded {}", uri),
+            "info": {
+                "identifier": uri,
+                "isSeekable": !is_stream,
+                "author": "Author",
+                "length": length,
+                "isStream": is_stream,
+                "position": 0,
+                "title": format!("Title {}", uri),
+                "uri": uri,
+            }
+        })
+    }
+
+    ///Empty State with default Playback and Volume
+    pub fn player_state() -> PlayerState {
+        PlayerState::new(UserId(1), PlayerDefaults::new(&guild_config("")))
+    }
+
+    ///Makes the Track current, as if it started playing at that Position
+    fn play(state: &mut PlayerState, track: Track, position: Duration) {
+        state.current = Some(((position, Instant::now()), track));
+    }
+
+    ///Load Result as Lavalink sends it
+    fn tracks(load_type: &str, tracks: Vec<serde_json::Value>) -> Tracks {
+        serde_json::from_value(serde_json::json!({
+            "loadType": load_type,
+            "playlistInfo": {},
+            "tracks": tracks,
+        }))
+        .expect("Invalid Tracks")
+    }
+
+    #[test]
+    fn load_type_maps_to_error() {
+        let query = || "query".to_string();
+        assert!(matches!(
+            load_result(query(), tracks(LOAD_FAILED, Vec::new())),
+            Err(PlayerError::SearchFailed(_))
+        ));
+        assert!(matches!(
+            load_result(query(), tracks(NO_MATCHES, Vec::new())),
+            Err(PlayerError::NoMatches(_))
+        ));
+        assert!(matches!(
+            load_result(query(), tracks(SEARCH_RESULT, Vec::new())),
+            Err(PlayerError::NoMatches(_))
+        ));
+        for load_type in [TRACK_LOADED, PLAYLIST_LOADED, SEARCH_RESULT] {
+            let loaded = load_result(
+                query(),
+                tracks(load_type, vec![track_json("a", 1000, false)]),
+            );
+            assert_eq!(loaded.map(|t| t.tracks.len()).ok(), Some(1));
+        }
+    }
+
+    #[test]
+    fn rapid_position_updates_coalesce() {
+        let start = Instant::now();
+        let mut throttle = PositionThrottle::default();
+        //Lavalink Updates every 100ms for 2 seconds
+        let passed = (0..20)
+            .filter(|i| throttle.pass(start + Duration::from_millis(i * 100)))
+            .count();
+        assert_eq!(passed, 4);
+
+        let mut throttle = PositionThrottle::default();
+        assert!(throttle.pass(start));
+        assert!(!throttle.pass(start + POSITION_UPDATE_INTERVAL - Duration::from_millis(1)));
+        assert!(throttle.pass(start + POSITION_UPDATE_INTERVAL));
+    }
+
+    #[test]
+    fn remaining_follows_the_rate() {
+        let mut state = player_state();
+        assert_eq!(state.remaining(), None);
+        state.play_state = PlayState::Pause;
+        play(
+            &mut state,
+            track("a", 10_000, false),
+            Duration::from_secs(4),
+        );
+        assert_eq!(state.remaining(), Some(Duration::from_secs(6)));
+        state.speed = 2.0;
+        assert_eq!(state.remaining(), Some(Duration::from_secs(3)));
+        play(
+            &mut state,
+            track("a", 10_000, false),
+            Duration::from_secs(12),
+        );
+        assert_eq!(state.remaining(), Some(Duration::ZERO));
+        play(&mut state, track("live", 0, true), Duration::from_secs(4));
+        assert_eq!(state.remaining(), None);
+    }
+
+    #[tokio::test]
+    async fn started_handoff_survives_cancel() {
+        let handoff = |started: bool| Handoff {
+            track: "encoded a".to_string(),
+            task: tokio::spawn(futures::future::pending()),
+            started: Arc::new(AtomicBool::new(started)),
+        };
+        assert!(handoff(false).cancel().is_none());
+        let kept = handoff(true).cancel().expect("Started Handoff was dropped");
+        assert_eq!(kept.track, "encoded a");
+        kept.task.abort();
+    }
+
+    #[test]
+    fn previous_restarts_before_going_back() {
+        let mut state = player_state();
+        play(
+            &mut state,
+            track("a", 60_000, false),
+            Duration::from_secs(30),
+        );
+        state
+            .history
+            .push_front((track("b", 60_000, false), HistoryReason::Finished));
+
+        //A single Press restarts, a second one within the Window goes to the History
+        assert!(!state.previous_goes_back(false));
+        assert!(state.previous_goes_back(true));
+
+        //Without History, every Press restarts
+        state.history.clear();
+        assert!(!state.previous_goes_back(false));
+        assert!(!state.previous_goes_back(true));
+
+        //Without a current Track, there is nothing to restart
+        state.current = None;
+        assert!(state.previous_goes_back(false));
+
+        //Live Streams can not be restarted, a single Press goes back
+        play(&mut state, track("live", 0, true), Duration::from_secs(30));
+        state
+            .history
+            .push_front((track("b", 60_000, false), HistoryReason::Finished));
+        assert!(state.previous_goes_back(false));
+    }
+
+    #[test]
+    fn set_playback_is_idempotent() {
+        let mut state = player_state();
+        assert!(!state.set_playback(Playback::Normal));
+        assert!(state.set_playback(Playback::AllLoop));
+        assert!(!state.set_playback(Playback::AllLoop));
+        assert_eq!(state.playback, Playback::AllLoop);
+        assert!(state.set_playback(Playback::OneLoop));
+        assert_eq!(state.playback, Playback::OneLoop);
+    }
+
+    #[test]
+    fn pause_and_resume_are_idempotent() {
+        let mut state = player_state();
+        play(
+            &mut state,
+            track("a", 60_000, false),
+            Duration::from_secs(30),
+        );
+
+        assert!(!state.set_play_state(PlayState::Play));
+        assert!(state.set_play_state(PlayState::Pause));
+        assert!(!state.set_play_state(PlayState::Pause));
+        assert_eq!(state.play_state, PlayState::Pause);
+
+        //Pausing again does not move the frozen Position
+        let paused = state.position();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!state.set_play_state(PlayState::Pause));
+        assert_eq!(state.position(), paused);
+
+        assert!(state.set_play_state(PlayState::Play));
+        assert!(!state.set_play_state(PlayState::Play));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(state.position() > paused);
+    }
+
+    #[test]
+    fn seek_while_paused_keeps_the_new_position() {
+        let mut state = player_state();
+        play(
+            &mut state,
+            track("a", 180_000, false),
+            Duration::from_secs(30),
+        );
+        state.set_play_state(PlayState::Pause);
+
+        state.set_position(Duration::from_secs(90));
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(state.position(), Some(Duration::from_secs(90)));
+
+        //Continues from the new Position once resumed
+        state.set_play_state(PlayState::Play);
+        let position = state.position().unwrap();
+        assert!(position >= Duration::from_secs(90));
+        assert!(position < Duration::from_secs(91));
+    }
+
+    ///Uris of the Playlist in order
+    fn uris(state: &PlayerState) -> Vec<String> {
+        state
+            .playlist
+            .iter()
+            .map(|t| t.info.as_ref().unwrap().uri.clone())
+            .collect()
+    }
+
+    #[test]
+    fn shuffle_inserts_at_random_positions() {
+        let mut state = player_state();
+        assert_eq!(state.push_upcoming(track("a", 1000, false)).ok(), Some(0));
+        assert_eq!(state.push_upcoming(track("b", 1000, false)).ok(), Some(1));
+
+        state.shuffle = true;
+        for i in 0..20 {
+            let uri = format!("s{}", i);
+            let index = state.push_upcoming(track(&uri, 1000, false)).unwrap();
+            assert!(index < state.playlist.len());
+            assert_eq!(uris(&state)[index], uri);
+        }
+        assert_eq!(state.playlist.len(), 22);
+        //Already queued Tracks keep their order
+        let uris = uris(&state);
+        let a = uris.iter().position(|u| u == "a").unwrap();
+        let b = uris.iter().position(|u| u == "b").unwrap();
+        assert!(a < b);
+    }
+
+    #[test]
+    fn all_loop_recycles_into_a_full_shuffled_playlist() {
+        let mut state = player_state();
+        state.shuffle = true;
+        while !state.playlist.is_full() {
+            let uri = format!("q{}", state.playlist.len());
+            state.push_upcoming(track(&uri, 1000, false)).unwrap();
+        }
+        assert!(state.push_upcoming(track("x", 1000, false)).is_err());
+
+        //The recycled Track always finds room, the last queued one is dropped for it
+        let last = uris(&state).pop().unwrap();
+        state.push_to_playlist_back(track("current", 1000, false));
+        assert_eq!(state.playlist.len(), MUSIC_QUEUE_LIMIT);
+        assert!(uris(&state).contains(&"current".to_string()));
+        assert!(!uris(&state).contains(&last));
+    }
+
+    #[test]
+    fn all_loop_skip_recycles_to_the_back_while_shuffled() {
+        let mut state = player_state();
+        for uri in ["a", "b", "c", "d"] {
+            state.push_upcoming(track(uri, 1000, false)).unwrap();
+        }
+        state.shuffle = true;
+        state.playback = Playback::AllLoop;
+        play(
+            &mut state,
+            track("now", 1000, false),
+            Duration::from_secs(0),
+        );
+
+        //Skip To "c", the Tracks skipped past must not end up in front of it
+        assert!(state.skip(3, 10));
+        assert_eq!(uris(&state), ["c", "d", "now", "a", "b"]);
+        assert!(state.history.is_empty());
+    }
+
+    #[test]
+    fn rejoin_continues_where_it_left() {
+        for play_state in [PlayState::Play, PlayState::Pause] {
+            let mut state = player_state();
+            play(
+                &mut state,
+                track("a", 180_000, false),
+                Duration::from_secs(30),
+            );
+            state.push_upcoming(track("b", 1000, false)).unwrap();
+            state.set_play_state(play_state);
+
+            //Left at 30s, rejoined a while later by another Bot
+            let mut kept = state.kept();
+            std::thread::sleep(Duration::from_millis(50));
+            kept.take_over(UserId(2));
+
+            assert_eq!(kept.bot, UserId(2));
+            assert_eq!(kept.play_state, play_state);
+            assert_eq!(uris(&kept), ["b"]);
+            let position = kept.position().unwrap();
+            assert!(position >= Duration::from_secs(30));
+            assert!(position < Duration::from_millis(30_050), "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn enqueue_reports_positions() {
+        let mut state = player_state();
+        let enqueued = state
+            .enqueue(std::iter::once(track("a", 1000, false)), false)
+            .unwrap();
+        assert_eq!(enqueued.positions, [0]);
+        assert_eq!(enqueued.to_string(), "Now playing 'Title a'");
+
+        play(&mut state, track("a", 1000, false), Duration::from_secs(0));
+        state.playlist.clear();
+        state.push_upcoming(track("b", 1000, false)).unwrap();
+        state.push_upcoming(track("c", 1000, false)).unwrap();
+        let enqueued = state
+            .enqueue(std::iter::once(track("d", 1000, false)), false)
+            .unwrap();
+        assert_eq!(enqueued.positions, [3]);
+        assert_eq!(enqueued.to_string(), "Added 'Title d' at position 3");
+
+        //Duplicates are skipped, the rest lands after the queued Tracks
+        let tracks = vec![track("b", 1000, false), track("e", 1000, false)];
+        let enqueued = state.enqueue(tracks.into_iter(), true).unwrap();
+        assert_eq!(enqueued.positions, [4]);
+        assert_eq!(
+            enqueued.to_string(),
+            "Added 'Title e' at position 4, skipped 1 Duplicates"
+        );
+    }
+
+    ///Uris of the History, most recent first
+    fn history(state: &PlayerState) -> Vec<String> {
+        state
+            .history
+            .iter()
+            .map(|(t, _)| t.info.as_ref().unwrap().uri.clone())
+            .collect()
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_tracks() {
+        let mut state = player_state();
+        for uri in ["a", "b", "c"] {
+            state.push_to_history_front(track(uri, 1000, false), HistoryReason::Finished, 3);
+        }
+        assert_eq!(history(&state), ["c", "b", "a"]);
+        //One beyond the limit drops the oldest
+        state.push_to_history_front(track("d", 1000, false), HistoryReason::Finished, 3);
+        assert_eq!(history(&state), ["d", "c", "b"]);
+
+        //A limit of 0 keeps no History at all
+        let mut state = player_state();
+        state.push_to_history_front(track("a", 1000, false), HistoryReason::Finished, 0);
+        assert!(state.history.is_empty());
+        assert!(!state.back_skip(1));
+    }
+
+    #[test]
+    fn back_skip_beyond_the_history() {
+        let mut state = player_state();
+        play(&mut state, track("c", 1000, false), Duration::from_secs(0));
+        state.push_upcoming(track("d", 1000, false)).unwrap();
+        for uri in ["a", "b"] {
+            state.push_to_history_front(track(uri, 1000, false), HistoryReason::Finished, 10);
+        }
+
+        //Only two Tracks to go back to, the rest of the Skip is ignored
+        assert!(state.back_skip(5));
+        assert!(state.history.is_empty());
+        assert!(state.current.is_none());
+        assert_eq!(uris(&state), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn back_skip_after_clear_history() {
+        let mut state = player_state();
+        state.push_upcoming(track("c", 1000, false)).unwrap();
+        for uri in ["a", "b"] {
+            state.push_to_history_front(track(uri, 1000, false), HistoryReason::Finished, 10);
+        }
+        assert!(state.clear_history());
+        assert!(!state.clear_history());
+
+        //Nothing to go back to, nothing moves
+        assert!(!state.back_skip(1));
+        assert_eq!(uris(&state), ["c"]);
+        //The current Track only restarts
+        play(&mut state, track("b", 1000, false), Duration::from_secs(0));
+        assert!(state.back_skip(1));
+        assert_eq!(uris(&state), ["b", "c"]);
+        assert!(history(&state).is_empty());
+    }
+
+    #[test]
+    fn skip_and_natural_end_are_told_apart() {
+        let mut state = player_state();
+        for uri in ["a", "b", "c", "d"] {
+            state.push_upcoming(track(uri, 1000, false)).unwrap();
         }
+        assert!(state.advance(10));
+
+        //Skipping two jumps over the next Track without playing it
+        assert!(state.skip(2, 10));
+        assert!(state.advance(10));
+        //c plays through
+        assert!(state.advance(10));
+
+        let reasons: Vec<_> = state.history.iter().map(|(_, r)| *r).collect();
+        assert_eq!(history(&state), ["c", "b", "a"]);
+        assert_eq!(
+            reasons,
+            [
+                HistoryReason::Finished,
+                HistoryReason::Removed,
+                HistoryReason::Skipped
+            ]
+        );
+
+        //AllLoop recycles skipped Tracks instead of keeping them in the History
+        state.set_playback(Playback::AllLoop);
+        assert!(state.skip(1, 10));
+        assert_eq!(history(&state), ["c", "b", "a"]);
+        assert_eq!(uris(&state), ["d"]);
+    }
+
+    #[test]
+    fn new_state_inherits_configured_defaults() {
+        let state = player_state();
+        assert_eq!(state.playback, Playback::Normal);
+        assert_eq!(state.volume, DEFAULT_VOLUME);
+
+        let config = guild_config("playback: AllLoop\nvolume: 50");
+        let state = PlayerState::new(UserId(1), PlayerDefaults::new(&config));
+        assert_eq!(state.playback, Playback::AllLoop);
+        assert_eq!(state.volume, 50);
+
+        //Volumes beyond what Lavalink accepts are capped
+        let config = guild_config("volume: 5000");
+        assert_eq!(PlayerDefaults::new(&config).volume, VOLUME_LIMIT);
+    }
+
+    #[test]
+    fn enqueued_shows_rejected_tracks() {
+        let enqueued = Enqueued {
+            added: 2,
+            rejected: 1,
+            positions: vec![3, 4],
+            ..Enqueued::default()
+        };
+        assert_eq!(
+            enqueued.to_string(),
+            "Added 2 Tracks from position 3, rejected 1 for their Length"
+        );
     }
 }
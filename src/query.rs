@@ -0,0 +1,81 @@
+use thiserror::Error;
+use url::Url;
+
+///Longer Queries are neither sensible searches nor Urls
+const QUERY_LENGTH_LIMIT: usize = 500;
+///Query Parameters that only track where a Link was shared
+const TRACKING_PARAMS: [&str; 7] = [
+    "si",
+    "feature",
+    "pp",
+    "ab_channel",
+    "fbclid",
+    "gclid",
+    "igshid",
+];
+const TRACKING_PREFIXES: [&str; 1] = ["utm_"];
+
+///Normalizes an Enqueue Query, before it is searched
+/// - Plain search terms pass through trimmed
+/// - Urls lose their tracking Parameters and youtu.be Urls are expanded
+/// - Rejects empty, oversized and broken Queries, so they never reach Lavalink
+pub fn normalize(query: &str) -> Result<String, QueryError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(QueryError::Empty());
+    }
+    if query.chars().count() > QUERY_LENGTH_LIMIT {
+        return Err(QueryError::TooLong(QUERY_LENGTH_LIMIT));
+    }
+    if !query.contains("://") {
+        return Ok(query.to_string());
+    }
+
+    let mut url = Url::parse(query).map_err(|_| QueryError::InvalidUrl(query.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(QueryError::UnsupportedScheme(url.scheme().to_string()));
+    }
+    if url.host_str() == Some("youtu.be") {
+        url = expand_short_url(&url).ok_or_else(|| QueryError::InvalidUrl(query.to_string()))?;
+    }
+    strip_tracking(&mut url);
+    Ok(url.to_string())
+}
+
+///Turns youtu.be/<id> into the full YouTube Url, keeping the other Parameters
+fn expand_short_url(url: &Url) -> Option<Url> {
+    let id = url.path_segments()?.find(|s| !s.is_empty())?;
+    let mut full = Url::parse("https://www.youtube.com/watch").ok()?;
+    full.query_pairs_mut()
+        .append_pair("v", id)
+        .extend_pairs(url.query_pairs());
+    Some(full)
+}
+
+fn strip_tracking(url: &mut Url) {
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| {
+            !TRACKING_PARAMS.contains(&key.as_ref())
+                && !TRACKING_PREFIXES.iter().any(|p| key.starts_with(p))
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("Nothing to search for")]
+    Empty(),
+    #[error("Query is longer than {0} characters")]
+    TooLong(usize),
+    #[error("Invalid Url: {0}")]
+    InvalidUrl(String),
+    #[error("Only http and https Urls are supported, not {0}")]
+    UnsupportedScheme(String),
+}
@@ -0,0 +1,199 @@
+use crate::config::SpotifyConfig;
+use log::{debug, info};
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use url::Url;
+
+const SPOTIFY_TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const SPOTIFY_API_URL: &str = "https://api.spotify.com/v1";
+///Most Tracks resolved from a single Album or Playlist
+const SPOTIFY_TRACK_LIMIT: usize = 50;
+///Renew the Token a bit before it actually expires
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpotifyUrl {
+    Track(String),
+    Album(String),
+    Playlist(String),
+}
+
+impl SpotifyUrl {
+    ///Parses open.spotify.com Urls and spotify: URIs
+    pub fn parse(query: &str) -> Option<SpotifyUrl> {
+        let query = query.trim();
+        let (kind, id) = if let Some(uri) = query.strip_prefix("spotify:") {
+            let mut split = uri.split(':');
+            (split.next()?.to_string(), split.next()?.to_string())
+        } else {
+            let url = Url::parse(query).ok()?;
+            if !url.host_str()?.eq("open.spotify.com") {
+                return None;
+            }
+            //Skip locale segments like "intl-de"
+            let mut segments = url
+                .path_segments()?
+                .filter(|s| !s.is_empty() && !s.starts_with("intl-"));
+            (segments.next()?.to_string(), segments.next()?.to_string())
+        };
+
+        match kind.as_str() {
+            "track" => Some(SpotifyUrl::Track(id)),
+            "album" => Some(SpotifyUrl::Album(id)),
+            "playlist" => Some(SpotifyUrl::Playlist(id)),
+            _ => None,
+        }
+    }
+
+    pub fn is_track(&self) -> bool {
+        matches!(self, SpotifyUrl::Track(_))
+    }
+}
+
+pub struct SpotifyResolver {
+    client: Client,
+    config: SpotifyConfig,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl SpotifyResolver {
+    pub fn new(config: SpotifyConfig) -> Self {
+        SpotifyResolver {
+            client: Client::new(),
+            config,
+            token: Mutex::new(None),
+        }
+    }
+
+    ///Resolves a Spotify Url into "Title Artist" search queries
+    pub async fn resolve(&self, url: &SpotifyUrl) -> Result<Vec<String>, SpotifyError> {
+        info!("Resolving Spotify Url. {:?}", url);
+        let queries = match url {
+            SpotifyUrl::Track(id) => {
+                let track: SpotifyTrack = self.get(&format!("/tracks/{}", id)).await?;
+                vec![track.query()]
+            }
+            SpotifyUrl::Album(id) => {
+                let page: SpotifyPage<SpotifyTrack> = self
+                    .get(&format!(
+                        "/albums/{}/tracks?limit={}",
+                        id, SPOTIFY_TRACK_LIMIT
+                    ))
+                    .await?;
+                page.items.iter().map(SpotifyTrack::query).collect()
+            }
+            SpotifyUrl::Playlist(id) => {
+                let page: SpotifyPage<SpotifyPlaylistItem> = self
+                    .get(&format!(
+                        "/playlists/{}/tracks?limit={}&fields=items(track(name,artists(name)))",
+                        id, SPOTIFY_TRACK_LIMIT
+                    ))
+                    .await?;
+                //Local or removed Tracks have no track object
+                page.items
+                    .iter()
+                    .filter_map(|i| i.track.as_ref().map(SpotifyTrack::query))
+                    .collect()
+            }
+        };
+        debug!(
+            "Resolved Spotify Url. {:?}, Queries: {}",
+            url,
+            queries.len()
+        );
+        Ok(queries)
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T, SpotifyError> {
+        let token = self.token().await?;
+        let res = self
+            .client
+            .get(format!("{}{}", SPOTIFY_API_URL, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(SpotifyError::Request)?;
+        if !res.status().is_success() {
+            return Err(SpotifyError::Status(res.status().as_u16()));
+        }
+        res.json().await.map_err(SpotifyError::Request)
+    }
+
+    ///Returns a cached Token or requests a new one with the Client Credentials
+    async fn token(&self) -> Result<String, SpotifyError> {
+        let mut lock = self.token.lock().await;
+        if let Some((token, expires)) = lock.as_ref() {
+            if Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        let res = self
+            .client
+            .post(SPOTIFY_TOKEN_URL)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await
+            .map_err(SpotifyError::Request)?;
+        if !res.status().is_success() {
+            return Err(SpotifyError::Status(res.status().as_u16()));
+        }
+        let token: SpotifyToken = res.json().await.map_err(SpotifyError::Request)?;
+        let expires = Instant::now() + Duration::from_secs(token.expires_in);
+        *lock = Some((
+            token.access_token.clone(),
+            expires
+                .checked_sub(TOKEN_EXPIRY_MARGIN)
+                .unwrap_or_else(Instant::now),
+        ));
+        Ok(token.access_token)
+    }
+}
+
+#[derive(Deserialize)]
+struct SpotifyToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct SpotifyPage<T> {
+    items: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct SpotifyPlaylistItem {
+    track: Option<SpotifyTrack>,
+}
+
+#[derive(Deserialize)]
+struct SpotifyTrack {
+    name: String,
+    artists: Vec<SpotifyArtist>,
+}
+
+impl SpotifyTrack {
+    fn query(&self) -> String {
+        match self.artists.first() {
+            None => self.name.clone(),
+            Some(artist) => format!("{} {}", self.name, artist.name),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SpotifyArtist {
+    name: String,
+}
+
+#[derive(Error, Debug)]
+pub enum SpotifyError {
+    #[error("Spotify Request failed: {0:?}")]
+    Request(reqwest::Error),
+    #[error("Spotify responded with Status: {0}")]
+    Status(u16),
+}
@@ -1,15 +1,17 @@
 use crate::context::SearchMessageId;
 use log::{debug, error, warn};
 use serenity::async_trait;
-use serenity::http::routing::Route;
-use serenity::http::Http;
+use serenity::http::routing::{LightMethod, Route};
+use serenity::http::{Http, HttpError};
 use serenity::model::prelude::{ChannelId, GuildId, Message, MessageId, ReactionType, UserId};
 use serenity::prelude::SerenityError;
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use strum_macros::{EnumCount as EnumCountMacro, EnumIter};
 use thiserror::Error;
 use tokio::sync::oneshot::{Receiver as OneShotReceiver, Sender as OneShotSender};
@@ -19,6 +21,11 @@ use uuid::Uuid;
 
 type PinedTask = Pin<Box<dyn Task>>;
 
+const RATE_LIMIT_RETRIES: usize = 3;
+const RATE_LIMIT_MIN_WAIT: Duration = Duration::from_millis(500);
+///Rate limit Backoffs since start, for spotting misbehaving Routes in the Logs
+static RATE_LIMIT_BACKOFFS: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Debug)]
 pub struct TaskHandle {
     sender: Option<OneShotSender<Result<PinedTask, TaskHandlerError>>>,
@@ -44,13 +51,25 @@ impl TaskHandle {
     }
 
     ///Tries to complete the Task with the help of an Http Instance
-    pub async fn run(mut self, client: Arc<Http>) -> Result<(), ()> {
+    /// - Retries after the reset of the Route, if Discord rate limited the Task
+    pub async fn run(mut self, client: Arc<Http>, route: Route) -> Result<(), ()> {
         if let Some(mut task) = self.task.take() {
-            let result = task
-                .run(client.clone())
-                .await
-                .map(|_| task)
-                .map_err(TaskHandlerError::FailedExecution);
+            let mut retries = 0;
+            let result = loop {
+                match task.run(client.clone()).await {
+                    Err(e) if is_rate_limited(&e) && retries < RATE_LIMIT_RETRIES => {
+                        retries += 1;
+                        let wait = retry_after(&client, &route).await;
+                        let backoffs = RATE_LIMIT_BACKOFFS.fetch_add(1, Ordering::Relaxed) + 1;
+                        warn!(
+                            "Task was rate limited, retrying. {:?}, Retry after: {:?}, Attempt: {}, Total Backoffs: {}",
+                            task, wait, retries, backoffs
+                        );
+                        tokio::time::sleep(wait).await;
+                    }
+                    res => break res.map(|_| task).map_err(TaskHandlerError::FailedExecution),
+                }
+            };
 
             let mut ret = Ok(());
             if result.is_err() {
@@ -69,6 +88,39 @@ impl TaskHandle {
     }
 }
 
+///Whether Discord responded with 429 Too Many Requests
+fn is_rate_limited(error: &SerenityError) -> bool {
+    if let SerenityError::Http(error) = error {
+        if let HttpError::UnsuccessfulRequest(res) = error.as_ref() {
+            return res.status_code.as_u16() == 429;
+        }
+    }
+    false
+}
+
+///Whether Discord responded with 403 Forbidden, e.g. for missing permissions
+fn is_forbidden(error: &SerenityError) -> bool {
+    if let SerenityError::Http(error) = error {
+        if let HttpError::UnsuccessfulRequest(res) = error.as_ref() {
+            return res.status_code.as_u16() == 403;
+        }
+    }
+    false
+}
+
+///Time until the Ratelimit of the Route resets
+async fn retry_after(client: &Http, route: &Route) -> Duration {
+    let routes = client.ratelimiter.routes();
+    if let Some(ratelimit) = routes.read().await.get(route) {
+        if let Some(reset) = ratelimit.lock().await.reset() {
+            if let Err(until) = reset.elapsed() {
+                return until.duration().max(RATE_LIMIT_MIN_WAIT);
+            }
+        }
+    }
+    RATE_LIMIT_MIN_WAIT
+}
+
 impl Drop for TaskHandle {
     fn drop(&mut self) {
         if let Some(sender) = self.sender.take() {
@@ -95,6 +147,7 @@ pub enum TaskRoute {
     ChannelMessage,
     ChannelMessageReactionSelf,
     ChannelMessagesBulkDelete,
+    ChannelMessageEdit,
 }
 
 impl TaskRoute {
@@ -107,6 +160,9 @@ impl TaskRoute {
             }
 
             TaskRoute::ChannelMessagesBulkDelete => Route::ChannelsIdMessagesBulkDelete(channel.0),
+            TaskRoute::ChannelMessageEdit => {
+                Route::ChannelsIdMessagesId(LightMethod::Any, channel.0)
+            }
         }
     }
 }
@@ -124,19 +180,30 @@ pub struct DeleteMessageReactionTask {
     pub message: MessageId,
     pub user: UserId,
     pub reaction: ReactionType,
+    ///Set once Discord denies removing Reactions, so the Message stops trying
+    pub forbidden: Option<Arc<AtomicBool>>,
 }
 
 #[async_trait]
 impl Task for DeleteMessageReactionTask {
     async fn run(&mut self, client: Arc<Http>) -> Result<(), SerenityError> {
-        client
+        let res = client
             .delete_reaction(
                 self.channel.0,
                 self.message.0,
                 Some(self.user.0),
                 &self.reaction,
             )
-            .await
+            .await;
+        if let (Err(e), Some(forbidden)) = (&res, &self.forbidden) {
+            if is_forbidden(e) && !forbidden.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Missing Manage Messages permission, leaving Reactions in place from now on. {:?}, {:?}",
+                    self.channel, self.message
+                );
+            }
+        }
+        res
     }
 
     fn route(&self) -> TaskRoute {
@@ -144,6 +211,26 @@ impl Task for DeleteMessageReactionTask {
     }
 }
 
+#[derive(Debug)]
+pub struct DeleteOwnMessageReactionTask {
+    pub channel: ChannelId,
+    pub message: MessageId,
+    pub reaction: ReactionType,
+}
+
+#[async_trait]
+impl Task for DeleteOwnMessageReactionTask {
+    async fn run(&mut self, client: Arc<Http>) -> Result<(), SerenityError> {
+        client
+            .delete_reaction(self.channel.0, self.message.0, None, &self.reaction)
+            .await
+    }
+
+    fn route(&self) -> TaskRoute {
+        TaskRoute::ChannelMessageReactionSelf
+    }
+}
+
 #[derive(Debug)]
 pub struct DeleteMessagePoolTask {
     pub channel: ChannelId,
@@ -204,10 +291,56 @@ impl Task for AddMessageReactionTask {
     }
 }
 
+#[derive(Debug)]
+pub struct EditMessageTask {
+    pub channel: ChannelId,
+    pub message: MessageId,
+    pub text: String,
+}
+
+#[async_trait]
+impl Task for EditMessageTask {
+    async fn run(&mut self, client: Arc<Http>) -> Result<(), SerenityError> {
+        let text = self.text.clone();
+        self.channel
+            .edit_message(client, self.message, |m| m.content(text))
+            .await
+            .map(|_| ())
+    }
+
+    fn route(&self) -> TaskRoute {
+        TaskRoute::ChannelMessageEdit
+    }
+}
+
+#[derive(Debug)]
+pub struct SendMessageTask {
+    pub channel: ChannelId,
+    pub text: String,
+    pub callback: WatchSender<Option<Message>>,
+}
+
+#[async_trait]
+impl Task for SendMessageTask {
+    async fn run(&mut self, client: Arc<Http>) -> Result<(), SerenityError> {
+        let msg = self.channel.say(client, self.text.clone()).await;
+        if let Ok(msg) = &msg {
+            self.callback.send(Some(msg.clone())).ok();
+        }
+        msg.map(|_| ())
+    }
+
+    fn route(&self) -> TaskRoute {
+        TaskRoute::ChannelMessage
+    }
+}
+
 #[derive(Debug)]
 pub struct SendSearchMessage {
     pub channel: ChannelId,
     pub text: String,
+    ///Labels for a Select Menu, the value of each option is its index
+    pub menu: Option<Vec<String>>,
     pub uuid: Uuid,
     pub search_messages: Arc<RwLock<HashMap<UserId, SearchMessageId>>>,
     pub callback: WatchSender<Option<Message>>,
@@ -225,9 +358,29 @@ impl Task for SendSearchMessage {
         if !relevant {
             return Ok(());
         }
+        let menu = self.menu.clone();
         let msg = self
             .channel
-            .send_message(client, |m| m.content(self.text.clone()))
+            .send_message(client, |m| {
+                m.content(self.text.clone());
+                if let Some(labels) = menu {
+                    m.components(|c| {
+                        c.create_action_row(|r| {
+                            r.create_select_menu(|s| {
+                                s.custom_id("search").options(|o| {
+                                    for (i, label) in labels.iter().enumerate() {
+                                        o.create_option(|opt| {
+                                            opt.label(label).value(i.to_string())
+                                        });
+                                    }
+                                    o
+                                })
+                            })
+                        })
+                    });
+                }
+                m
+            })
             .await;
         if let Ok(msg) = &msg {
             self.callback.send(Some(msg.clone())).ok();